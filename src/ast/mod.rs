@@ -0,0 +1,1133 @@
+pub mod equation {
+
+    use crate::parser::equation_parser::{TokenIterator, TokenType};
+    use crate::error::balance_error::{BalanceError, ErrorKind};
+    use crate::elements::{abbreviations, periodic_table};
+
+    use num_rational::Ratio;
+
+    use num_traits::identities::{One, Zero};
+    use num_traits::{CheckedAdd, CheckedMul};
+
+    use std::collections::HashMap;
+    use std::fmt;
+    use std::ops::Index;
+
+    // Writes a ratio the way it would be typed, i.e. 2, 0.5, 2.25. Parsed quantities always come from decimals,
+    // so their denominators only contain factors of two and five and always have a finite decimal form
+    pub fn format_quantity(quantity: Ratio<i32>) -> String {
+        if quantity.is_integer() {
+            return format!("{}", quantity.numer());
+        }
+
+        let mut denom = *quantity.denom();
+        let mut twos = 0;
+        let mut fives = 0;
+
+        while denom % 2 == 0 {
+            denom /= 2;
+            twos += 1;
+        }
+
+        while denom % 5 == 0 {
+            denom /= 5;
+            fives += 1;
+        }
+
+        if denom != 1 {
+            //Not a terminating decimal, so fall back on the fraction
+            return format!("{}", quantity);
+        }
+
+        //Scale the denominator up to a power of ten, i.e. 4 becomes 100 by multiplying by 25
+        let places = std::cmp::max(twos, fives);
+
+        let scale = match 2i64.checked_pow(places - twos).and_then(|two| 5i64.checked_pow(places - fives).and_then(|five| two.checked_mul(five))) {
+            Some(scale) => scale,
+            None => return format!("{}", quantity)
+        };
+
+        let scaled = match (*quantity.numer() as i64).checked_mul(scale) {
+            Some(scaled) => scaled,
+            None => return format!("{}", quantity)
+        };
+
+        let power = 10i64.pow(places);
+
+        format!("{}{}.{:0width$}", if scaled < 0 { "-" } else { "" }, scaled.abs() / power, scaled.abs() % power, width = places as usize)
+    }
+
+    // One piece of a formula
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum Part {
+        Element(String, Ratio<i32>), // A symbol with its quantity, i.e. H2, Na, Mg3
+        Group(u8, Vec<Part>, Ratio<i32>), // The opening bracket, the contents and the quantity of a group, i.e. (OH)2, [Fe(CN)6]
+        Hydrate(Ratio<i32>, Vec<Part>) // Water of crystallisation and its quantity, i.e. *5H2O
+    }
+
+    // The chemical formula of a molecule, without its coefficient, charge or state
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Formula {
+        pub parts: Vec<Part>
+    }
+
+    // The physical state of a molecule, i.e. (s), (l), (g) or (aq)
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum State {
+        Solid,
+        Liquid,
+        Gas,
+        Aqueous
+    }
+
+    // A single molecule, ion or electron in an equation
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Term {
+        pub coefficient: Ratio<i32>,
+        pub formula: Formula,
+        pub state: Option<State>,
+        pub charge: Ratio<i32>
+    }
+
+    // Two differently written terms with the same canonical formula, i.e. C2H5OH and CH3CH2OH
+    pub struct Respelling<'a> {
+        pub canonical: String,
+        pub first: & 'a Term,
+        pub second: & 'a Term,
+        pub both_sides: bool
+    }
+
+    // A chemical equation, reactants on the left of the equals and products on the right
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Equation {
+        pub reactants: Vec<Term>,
+        pub products: Vec<Term>
+    }
+
+    // A quantity for each element (or the charge), kept in the order each was first added. Anything built from it, such as
+    // the rows of the balancing matrix, comes out the same on every run
+    #[derive(Clone, Debug, Default, PartialEq)]
+    pub struct ElementTable<'a> {
+        entries: Vec<(&'a str, Ratio<i32>)>
+    }
+
+    impl<'a> ElementTable<'a> {
+        pub fn new() -> Self {
+            ElementTable {
+                entries: Vec::new()
+            }
+        }
+
+        // Add to the quantity of an element, which is added to the end of the table if it is not already there. Fails if
+        // the total is too large for the ratio
+        pub fn add(& mut self, element: &'a str, quantity: Ratio<i32>) -> Result<(), BalanceError> {
+            match self.entries.iter_mut().find(|(existing, _)| *existing == element) {
+                Some((_, total)) => *total = total.checked_add(&quantity).ok_or_else(|| too_large(element))?,
+                None => self.entries.push((element, quantity))
+            }
+
+            Ok(())
+        }
+
+        pub fn get(&self, element: &str) -> Option<Ratio<i32>> {
+            self.entries.iter().find(|(existing, _)| *existing == element).map(|(_, quantity)| *quantity)
+        }
+
+        pub fn contains(&self, element: &str) -> bool {
+            self.get(element).is_some()
+        }
+
+        pub fn len(&self) -> usize {
+            self.entries.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.entries.is_empty()
+        }
+
+        // Each element and its quantity, in the order they were added
+        pub fn iter(&self) -> impl Iterator<Item = (&'a str, Ratio<i32>)> + '_ {
+            self.entries.iter().cloned()
+        }
+
+        // Each element, in the order they were added
+        pub fn keys(&self) -> impl Iterator<Item = &'a str> + '_ {
+            self.entries.iter().map(|(element, _)| *element)
+        }
+    }
+
+    impl<'a> Index<&str> for ElementTable<'a> {
+        type Output = Ratio<i32>;
+
+        // The quantity of an element, which must be in the table
+        fn index(&self, element: &str) -> &Ratio<i32> {
+            &self.entries.iter().find(|(existing, _)| *existing == element).expect("Element is not in the table").1
+        }
+    }
+
+    impl<'a> IntoIterator for ElementTable<'a> {
+        type Item = (&'a str, Ratio<i32>);
+        type IntoIter = std::vec::IntoIter<(&'a str, Ratio<i32>)>;
+
+        fn into_iter(self) -> Self::IntoIter {
+            self.entries.into_iter()
+        }
+    }
+
+    //An error for a total too large for the ratio, i.e. the H in (((H1000)1000)1000)1000
+    fn too_large(element: &str) -> BalanceError {
+        BalanceError::new(ErrorKind::Parse, format!("The quantity of {} is too large", element))
+    }
+
+    //Add the quantity of every element within parts to the table, multiplied by scalar
+    fn count_elements<'a>(parts: & 'a [Part], map: & mut ElementTable<'a>, scalar: Ratio<i32>) -> Result<(), BalanceError> {
+        for part in parts {
+            match part {
+                Part::Element(element, quantity) => match abbreviations::expand(element) {
+                    //An abbreviation counts as the elements it stands for, but is still written as it was typed
+                    Some(expansion) => {
+                        for (symbol, count) in expansion {
+                            let total = quantity.checked_mul(&scalar).and_then(|total| total.checked_mul(&Ratio::from_integer(count)));
+
+                            map.add(symbol, total.ok_or_else(|| too_large(symbol))?)?;
+                        }
+                    },
+                    None => map.add(element.as_str(), quantity.checked_mul(&scalar).ok_or_else(|| too_large(element))?)?
+                },
+                Part::Group(_, group, quantity) | Part::Hydrate(quantity, group) => {
+                    let scalar = scalar.checked_mul(quantity).ok_or_else(|| too_large(&part.to_string()))?;
+
+                    count_elements(group, map, scalar)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Add the quantity of each element (and the charge) in a term to the table, multiplied by scalar
+    pub fn parse_group<'a>(term: & 'a Term, map: & mut ElementTable<'a>, scalar: Ratio<i32>) -> Result<(), BalanceError> {
+        for (element, quantity) in term.formula.checked_elements()? {
+            map.add(element, quantity.checked_mul(&scalar).ok_or_else(|| too_large(element))?)?;
+        }
+
+        if term.charge != Ratio::zero() {
+            map.add("charge", term.charge.checked_mul(&scalar).ok_or_else(|| too_large("charge"))?)?;
+        }
+
+        Ok(())
+    }
+
+    //Parse the tokens within a group into parts. Errors are located within equation, which contains the group
+    fn parse_parts(equation: &[u8], group: &[u8]) -> Result<Vec<Part>, BalanceError> {
+        let mut parts = Vec::new();
+
+        for token in TokenIterator::new(group) {
+            match token {
+                TokenType::Symbol(element, string, quantity) => {
+                    if element == "charge".as_bytes() {
+                        return Err(BalanceError::parse(format!("Charges must come after the formula, i.e. [Fe(CN)6]{{4-}} ({})", string), equation, string.as_bytes()));
+                    }
+
+                    parts.push(Part::Element(String::from(unsafe { std::str::from_utf8_unchecked(element) }), quantity));
+                },
+                TokenType::Group(group, string, quantity) => {
+                    let inner = parse_parts(equation, group)?;
+
+                    if string.as_bytes()[0] == b'*' {
+                        parts.push(Part::Hydrate(quantity, inner));
+                    }
+                    else {
+                        parts.push(Part::Group(string.as_bytes()[0], inner, quantity));
+                    }
+                },
+                TokenType::Coefficient(string, _) => {
+                    return Err(BalanceError::parse(format!("Coefficients must come at the start of a molecule ({})", string), equation, string.as_bytes()));
+                },
+                TokenType::Invalid(c) => {
+                    return Err(BalanceError::parse(format!("Invalid token '{}'", unsafe { std::str::from_utf8_unchecked(c) }), equation, c));
+                },
+                TokenType::Error(slice, error) => {
+                    return Err(BalanceError::parse(format!("{} ({})", error, slice), equation, slice.as_bytes()));
+                },
+                TokenType::Separator(sep) => {
+                    //Separator within group, error
+                    return Err(BalanceError::parse(format!("Invalid symbol ({}) found within parenthesis", sep as char), equation, group));
+                }
+            }
+        }
+
+        Ok(parts)
+    }
+
+    //Identify a group token as a state symbol, i.e. (g)
+    fn parse_state(group: &[u8], string: &str) -> Option<State> {
+        if string.as_bytes()[0] != b'(' {
+            return None;
+        }
+
+        State::from_symbol(group)
+    }
+
+    impl State {
+        // The state written without brackets, i.e. s, l, g or aq
+        pub fn from_symbol(symbol: &[u8]) -> Option<State> {
+            match symbol {
+                b"s" => Some(State::Solid),
+                b"l" => Some(State::Liquid),
+                b"g" => Some(State::Gas),
+                b"aq" => Some(State::Aqueous),
+                _ => None
+            }
+        }
+    }
+
+    impl Formula {
+        // The mass of one mole of the formula in g/mol
+        pub fn molar_mass(&self) -> Result<f64, BalanceError> {
+            let mut mass = 0.0;
+
+            for (element, quantity) in self.elements() {
+                match periodic_table::lookup(element) {
+                    Some(found) => mass += found.mass * (*quantity.numer() as f64 / *quantity.denom() as f64),
+                    None => return Err(BalanceError::new(ErrorKind::Parse, format!("Unknown element '{}'", element)))
+                }
+            }
+
+            Ok(mass)
+        }
+
+        // The total quantity of each element in the formula, in the order each element first appears. Parsing checks that
+        // every total fits in the ratio, so only a formula built some other way needs checked_elements
+        pub fn elements(&self) -> ElementTable<'_> {
+            self.checked_elements().expect("Parsed formulas have totals that fit in the ratio")
+        }
+
+        // The total quantity of each element in the formula, or an error if a total is too large for the ratio
+        pub fn checked_elements(&self) -> Result<ElementTable<'_>, BalanceError> {
+            let mut map = ElementTable::new();
+
+            count_elements(&self.parts, & mut map, Ratio::one())?;
+
+            Ok(map)
+        }
+
+        // The molecular formula in the Hill system, carbon first, then hydrogen, then everything else alphabetically.
+        // Without carbon, every element (including hydrogen) is alphabetical. i.e. CH3CH2OH gives C2H6O
+        pub fn canonical(&self) -> String {
+            let elements = self.elements();
+
+            let mut symbols: Vec<&str> = elements.iter().filter(|(_, quantity)| *quantity != Ratio::zero()).map(|(element, _)| element).collect();
+
+            symbols.sort();
+
+            if elements.contains("C") {
+                symbols.retain(|element| *element != "C" && *element != "H");
+
+                if elements.contains("H") {
+                    symbols.insert(0, "H");
+                }
+
+                symbols.insert(0, "C");
+            }
+
+            let mut result = String::new();
+
+            for element in symbols {
+                result.push_str(element);
+
+                if elements[element] != Ratio::one() {
+                    result.push_str(&format_quantity(elements[element]));
+                }
+            }
+
+            result
+        }
+    }
+
+    impl Term {
+        fn new() -> Self {
+            Term {
+                coefficient: Ratio::one(),
+                formula: Formula { parts: Vec::new() },
+                state: None,
+                charge: Ratio::zero()
+            }
+        }
+
+        // Whether two terms are the same molecule in the same state, ignoring coefficients
+        pub fn same_species(&self, other: &Term) -> bool {
+            self.formula == other.formula && self.charge == other.charge && self.state == other.state
+        }
+
+        // The Hill system formula with the charge appended, i.e. HCO3{-} gives CHO3{-}
+        pub fn canonical(&self) -> String {
+            let mut canonical = Term {
+                coefficient: Ratio::one(),
+                formula: Formula { parts: Vec::new() },
+                state: None,
+                charge: self.charge
+            }.to_string();
+
+            canonical.insert_str(0, &self.formula.canonical());
+
+            canonical
+        }
+    }
+
+    //Only ascii is supported, which also makes it safe to treat any slice of the input as a str
+    fn check_ascii(equation_asbytes: &[u8]) -> Result<(), BalanceError> {
+        match equation_asbytes.iter().position(|ch| *ch > 127) {
+            Some(index) => Err(BalanceError::parse(String::from("Invalid character detected. Please only use valid Ascii characters (Unicode is not supported)"), equation_asbytes, &equation_asbytes[index..index+1])),
+            None => Ok(())
+        }
+    }
+
+    //Parse a list of terms separated by pluses, with a new list started after each equals
+    fn parse_sides(equation_asbytes: &[u8]) -> Result<Vec<Vec<Term>>, BalanceError> {
+
+        let mut sides = vec![Vec::new()];
+
+        let mut term = Term::new();
+
+        //Number of tokens seen in the current term, so we know whether a coefficient is at the start
+        let mut term_tokens = 0;
+
+        let mut charged = false;
+
+        //Byte range of the current term, so an error in its totals can point at it
+        let mut term_span = (0, 0);
+
+        for token in TokenIterator::new(equation_asbytes) {
+
+            if term.state.is_some() {
+                let slice = match token {
+                    TokenType::Separator(_) => None,
+                    TokenType::Symbol(_, string, _) | TokenType::Group(_, string, _) |
+                    TokenType::Coefficient(string, _) | TokenType::Error(string, _) => Some(string.as_bytes()),
+                    TokenType::Invalid(c) => Some(c)
+                };
+
+                if let Some(slice) = slice {
+                    return Err(BalanceError::parse(String::from("States must come at the end of a molecule, i.e. Fe{3+}(aq)"), equation_asbytes, slice));
+                }
+            }
+
+            if let TokenType::Symbol(_, string, _) | TokenType::Group(_, string, _) | TokenType::Coefficient(string, _) = token {
+                let start = string.as_ptr() as usize - equation_asbytes.as_ptr() as usize;
+
+                if term_tokens == 0 {
+                    term_span.0 = start;
+                }
+
+                term_span.1 = start + string.len();
+            }
+
+            match token {
+                TokenType::Symbol(element, string, quantity) => {
+                    if element == "charge".as_bytes() {
+                        if charged {
+                            return Err(BalanceError::parse(format!("A molecule can only have one charge ({})", string), equation_asbytes, string.as_bytes()));
+                        }
+
+                        term.charge = quantity;
+                        charged = true;
+                    }
+                    else {
+                        if charged {
+                            return Err(BalanceError::parse(format!("Charges must come after the formula, i.e. [Fe(CN)6]{{4-}} ({})", string), equation_asbytes, string.as_bytes()));
+                        }
+
+                        term.formula.parts.push(Part::Element(String::from(unsafe { std::str::from_utf8_unchecked(element) }), quantity));
+                    }
+                },
+                TokenType::Group(group, string, quantity) => {
+                    match parse_state(group, string) {
+                        //A quantity after a state would be lost, i.e. the 2 in (aq)2
+                        Some(_) if string.len() != group.len() + 2 => {
+                            return Err(BalanceError::parse(format!("States cannot have a quantity ({})", string), equation_asbytes, string.as_bytes()));
+                        },
+                        Some(state) => {
+                            term.state = Some(state);
+                        },
+                        None => {
+                            if charged {
+                                return Err(BalanceError::parse(format!("Charges must come after the formula, i.e. [Fe(CN)6]{{4-}} ({})", string), equation_asbytes, string.as_bytes()));
+                            }
+
+                            let inner = parse_parts(equation_asbytes, group)?;
+
+                            if string.as_bytes()[0] == b'*' {
+                                term.formula.parts.push(Part::Hydrate(quantity, inner));
+                            }
+                            else {
+                                term.formula.parts.push(Part::Group(string.as_bytes()[0], inner, quantity));
+                            }
+                        }
+                    }
+                },
+                TokenType::Coefficient(string, coefficient) => {
+                    if term_tokens != 0 {
+                        return Err(BalanceError::parse(format!("Coefficients must come at the start of a molecule ({})", string), equation_asbytes, string.as_bytes()));
+                    }
+
+                    term.coefficient = coefficient;
+                },
+                TokenType::Invalid(c) => {
+                    return Err(BalanceError::parse(format!("Invalid token '{}'", unsafe { std::str::from_utf8_unchecked(c) }), equation_asbytes, c));
+                },
+                TokenType::Error(slice, error) => {
+                    return Err(BalanceError::parse(format!("{} ({})", error, slice), equation_asbytes, slice.as_bytes()));
+                },
+                TokenType::Separator(sep) => {
+                    if term.formula.parts.is_empty() && !charged {
+                        return Err(BalanceError::new(ErrorKind::Parse, String::from("Each molecule must have a formula or a charge")));
+                    }
+
+                    check_totals(&term, equation_asbytes, &equation_asbytes[term_span.0..term_span.1])?;
+
+                    sides.last_mut().unwrap().push(term);
+
+                    //If the separator is an equals, move on to the next side
+                    if sep == 61 {
+                        sides.push(Vec::new());
+                    }
+
+                    term = Term::new();
+                    term_tokens = 0;
+                    charged = false;
+
+                    continue;
+                }
+            }
+
+            term_tokens += 1;
+        }
+
+        if term.formula.parts.is_empty() && !charged {
+            return Err(BalanceError::new(ErrorKind::Parse, String::from("Each molecule must have a formula or a charge")));
+        }
+
+        check_totals(&term, equation_asbytes, &equation_asbytes[term_span.0..term_span.1])?;
+
+        sides.last_mut().unwrap().push(term);
+
+        Ok(sides)
+    }
+
+    //Check that the total of each element (and the charge) in a term, times its coefficient, fits in the ratio. Every
+    //later count of a parsed term, such as Formula::elements, relies on this. The error points at the term's text
+    fn check_totals(term: &Term, equation: &[u8], text: &[u8]) -> Result<(), BalanceError> {
+        let located = |error: BalanceError| BalanceError::parse(format!("{} ({})", error.message, String::from_utf8_lossy(text)), equation, text);
+
+        let mut totals = ElementTable::new();
+
+        parse_group(term, & mut totals, term.coefficient).map_err(located)?;
+
+        Ok(())
+    }
+
+    impl Term {
+        // Parse a single molecule, ion or electron, i.e. 2H2O, [Fe(CN)6]{4-}, NaCl(aq). Whitespace must already be removed
+        pub fn parse(term_asbytes: &[u8]) -> Result<Term, BalanceError> {
+
+            check_ascii(term_asbytes)?;
+
+            if term_asbytes.is_empty() {
+                return Err(BalanceError::new(ErrorKind::Parse, String::from("Each molecule must have a formula or a charge")));
+            }
+
+            let mut sides = parse_sides(term_asbytes)?;
+
+            if sides.len() != 1 || sides[0].len() != 1 {
+                return Err(BalanceError::new(ErrorKind::Parse, String::from("Expected a single molecule, without any + or =")));
+            }
+
+            Ok(sides[0].remove(0))
+        }
+    }
+
+    impl Equation {
+        // Parse an equation, i.e. 2H2 + O2 = 2H2O, in a single pass over the tokens. Whitespace must already be removed
+        pub fn parse(equation_asbytes: &[u8]) -> Result<Equation, BalanceError> {
+
+            check_ascii(equation_asbytes)?;
+
+            let mut equals_count = 0;
+            let mut equals_index = 0;
+
+            for ch in equation_asbytes {
+                //Get the total number of equals signs
+                if *ch == 61 {
+                    equals_count += 1;
+                }
+
+                //Get the index of the first occurence of an equals sign
+                if equals_count == 0 {
+                    equals_index += 1;
+                }
+            }
+
+            if equals_count != 1 {
+                return Err(BalanceError::new(ErrorKind::Parse, String::from("Formula must have exactly one equals")));
+            }
+
+            if equals_index == 0 || equals_index == equation_asbytes.len() - 1 {
+                return Err(BalanceError::new(ErrorKind::Parse, String::from("Formula must have at least one reactant and one product")));
+            }
+
+            let mut sides = parse_sides(equation_asbytes)?;
+
+            let products = sides.pop().unwrap();
+            let reactants = sides.pop().unwrap();
+
+            Ok(Equation {
+                reactants,
+                products
+            })
+        }
+
+        // Parse one side of an equation, i.e. CH4 + 2O2, without any equals. Whitespace must already be removed
+        pub fn parse_side(side_asbytes: &[u8]) -> Result<Vec<Term>, BalanceError> {
+
+            check_ascii(side_asbytes)?;
+
+            if side_asbytes.is_empty() {
+                return Err(BalanceError::new(ErrorKind::Parse, String::from("Formula must have at least one reactant")));
+            }
+
+            let mut sides = parse_sides(side_asbytes)?;
+
+            if sides.len() != 1 {
+                return Err(BalanceError::new(ErrorKind::Parse, String::from("Expected one side of an equation, without any =")));
+            }
+
+            Ok(sides.remove(0))
+        }
+
+        // The total of each element, and the charge, on the reactant side minus the product side, using the current
+        // coefficients. Only non zero differences are returned, so a balanced equation gives an empty list. Fails if a
+        // total is too large for the ratio
+        pub fn imbalance(&self) -> Result<Vec<(String, Ratio<i32>)>, BalanceError> {
+            let mut totals: Vec<(String, Ratio<i32>)> = Vec::new();
+
+            let sides = self.reactants.iter().map(|term| (term, Ratio::one()))
+                .chain(self.products.iter().map(|term| (term, -Ratio::one())));
+
+            for (term, sign) in sides {
+                let mut elements: Vec<(&str, Ratio<i32>)> = term.formula.checked_elements()?.into_iter().collect();
+
+                elements.push(("charge", term.charge));
+
+                for (element, quantity) in elements {
+                    let change = quantity.checked_mul(&term.coefficient).and_then(|change| change.checked_mul(&sign))
+                        .ok_or_else(|| too_large(element))?;
+
+                    match totals.iter_mut().find(|(existing, _)| existing == element) {
+                        Some((_, total)) => *total = total.checked_add(&change).ok_or_else(|| too_large(element))?,
+                        None => totals.push((String::from(element), change))
+                    }
+                }
+            }
+
+            totals.retain(|(_, total)| *total != Ratio::zero());
+
+            Ok(totals)
+        }
+
+        // Every term in the equation, reactants followed by products
+        pub fn terms(&self) -> impl Iterator<Item = &Term> {
+            self.reactants.iter().chain(self.products.iter())
+        }
+
+        // Pairs of terms in the same state that are written differently but share a canonical formula.
+        // These are either the same species spelled twice, or isomers
+        pub fn respellings(&self) -> Vec<Respelling<'_>> {
+            let terms: Vec<(bool, &Term)> = self.reactants.iter().map(|term| (false, term))
+                .chain(self.products.iter().map(|term| (true, term)))
+                .collect();
+
+            let mut result = Vec::new();
+
+            for (i, (first_side, first)) in terms.iter().enumerate() {
+                for (second_side, second) in terms[i+1..].iter() {
+                    if first.state == second.state && first.formula != second.formula {
+                        let canonical = first.canonical();
+
+                        if canonical == second.canonical() {
+                            result.push(Respelling {
+                                canonical,
+                                first,
+                                second,
+                                both_sides: first_side != second_side
+                            });
+                        }
+                    }
+                }
+            }
+
+            result
+        }
+
+        // Merge molecules written more than once on the same side of the equation, i.e. H2O + CO2 + H2O gives 2H2O + CO2.
+        // Returns each merged molecule, with the coefficients added together, and the number of times it was written.
+        // Fails if the added coefficients are too large for the ratio
+        pub fn merge_duplicates(& mut self) -> Result<Vec<(Term, usize)>, BalanceError> {
+            let mut merged = Vec::new();
+
+            for side in [& mut self.reactants, & mut self.products].iter_mut() {
+                let mut kept: Vec<Term> = Vec::with_capacity(side.len());
+                let mut counts: Vec<usize> = Vec::with_capacity(side.len());
+
+                for term in side.drain(..) {
+                    match kept.iter().position(|existing| existing.same_species(&term)) {
+                        Some(index) => {
+                            kept[index].coefficient = kept[index].coefficient.checked_add(&term.coefficient).ok_or_else(|| {
+                                BalanceError::new(ErrorKind::Parse, format!("The total coefficient of {} is too large", term.formula))
+                            })?;
+                            counts[index] += 1;
+                        },
+                        None => {
+                            kept.push(term);
+                            counts.push(1);
+                        }
+                    }
+                }
+
+                for (term, count) in kept.iter().zip(counts.iter()) {
+                    if *count > 1 {
+                        merged.push((term.clone(), *count));
+                    }
+                }
+
+                **side = kept;
+            }
+
+            Ok(merged)
+        }
+
+        // Molecules that appear as both a reactant and a product, i.e. catalysts and spectator ions
+        pub fn spectators(&self) -> Vec<&Term> {
+            self.reactants.iter().filter(|reactant| self.products.iter().any(|product| product.same_species(reactant))).collect()
+        }
+
+        // Remove every molecule that appears on both sides of the equation, returning the removed molecules
+        pub fn cancel_spectators(& mut self) -> Vec<Term> {
+            let cancelled: Vec<Term> = self.spectators().into_iter().cloned().collect();
+
+            self.reactants.retain(|term| !cancelled.iter().any(|spectator| spectator.same_species(term)));
+            self.products.retain(|term| !cancelled.iter().any(|spectator| spectator.same_species(term)));
+
+            cancelled
+        }
+
+        // Mutable access to every term in the equation, reactants followed by products
+        pub fn terms_mut(& mut self) -> impl Iterator<Item = & mut Term> {
+            self.reactants.iter_mut().chain(self.products.iter_mut())
+        }
+    }
+
+    // Molecules parsed earlier, keyed by how they were written (without whitespace), i.e. 2H2O or Fe{3+}(aq). Equations
+    // parsed through the cache only parse the molecules it has not seen, so a session that keeps one cache across
+    // lines does not parse (or check the totals of) the same formula twice
+    #[derive(Default)]
+    pub struct TermCache {
+        terms: HashMap<String, Term>
+    }
+
+    //Split an equation (without whitespace) into the text of each molecule on each side, at the plus and equals signs
+    //outside any brackets. None if it cannot be split into one or more molecules either side of exactly one equals
+    fn split_terms(equation_asbytes: &[u8]) -> Option<Vec<Vec<&[u8]>>> {
+        let mut sides = vec![Vec::new()];
+
+        let mut depth = 0;
+        let mut start = 0;
+
+        for (index, ch) in equation_asbytes.iter().enumerate() {
+            match ch {
+                b'(' | b'[' | b'{' => depth += 1,
+                b')' | b']' | b'}' => depth -= 1,
+                b'+' | b'=' if depth == 0 => {
+                    sides.last_mut().unwrap().push(&equation_asbytes[start..index]);
+
+                    if *ch == b'=' {
+                        sides.push(Vec::new());
+                    }
+
+                    start = index + 1;
+                },
+                _ => {}
+            }
+        }
+
+        sides.last_mut().unwrap().push(&equation_asbytes[start..]);
+
+        if depth != 0 || sides.len() != 2 || sides.iter().flatten().any(|term| term.is_empty()) {
+            return None;
+        }
+
+        Some(sides)
+    }
+
+    impl TermCache {
+        pub fn new() -> Self {
+            TermCache {
+                terms: HashMap::new()
+            }
+        }
+
+        // The number of molecules parsed so far
+        pub fn len(&self) -> usize {
+            self.terms.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.terms.is_empty()
+        }
+
+        // A single molecule, parsed only if its formula has not been seen before with any coefficient, so 2H2O reuses H2O.
+        // Whitespace must already be removed
+        pub fn term(& mut self, term_asbytes: &[u8]) -> Result<Term, BalanceError> {
+            let (coefficient, formula) = match TokenIterator::new(term_asbytes).next() {
+                Some(TokenType::Coefficient(string, coefficient)) => (coefficient, &term_asbytes[string.len()..]),
+                _ => (Ratio::one(), term_asbytes)
+            };
+
+            let key = String::from_utf8_lossy(formula);
+
+            if let Some(term) = self.terms.get(key.as_ref()) {
+                return Ok(Term { coefficient, ..term.clone() });
+            }
+
+            //The whole molecule is parsed, so any error is reported as it would be without the cache
+            let term = Term::parse(term_asbytes)?;
+
+            self.terms.insert(key.into_owned(), Term { coefficient: Ratio::one(), ..term.clone() });
+
+            Ok(term)
+        }
+
+        // An equation, parsing only the molecules that have not been seen before. Anything that does not parse molecule by
+        // molecule is parsed as a whole, so errors (and their spans) are the same as from Equation::parse
+        pub fn equation(& mut self, equation_asbytes: &[u8]) -> Result<Equation, BalanceError> {
+            if let Some(sides) = split_terms(equation_asbytes) {
+                let mut parsed = Vec::with_capacity(2);
+
+                for side in sides {
+                    match side.into_iter().map(|term| self.term(term)).collect::<Result<Vec<Term>, BalanceError>>() {
+                        Ok(terms) => parsed.push(terms),
+                        Err(_) => return Equation::parse(equation_asbytes)
+                    }
+                }
+
+                let products = parsed.pop().unwrap();
+                let reactants = parsed.pop().unwrap();
+
+                return Ok(Equation {
+                    reactants,
+                    products
+                });
+            }
+
+            Equation::parse(equation_asbytes)
+        }
+    }
+
+    impl fmt::Display for Part {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let quantity = match self {
+                Part::Element(element, quantity) => {
+                    write!(f, "{}", element)?;
+                    quantity
+                },
+                Part::Group(bracket, group, quantity) => {
+                    write!(f, "{}", *bracket as char)?;
+                    for part in group {
+                        write!(f, "{}", part)?;
+                    }
+                    write!(f, "{}", if *bracket == b'[' { ']' } else { ')' })?;
+                    quantity
+                },
+                Part::Hydrate(quantity, group) => {
+                    write!(f, "*")?;
+                    if *quantity != Ratio::one() {
+                        write!(f, "{}", format_quantity(*quantity))?;
+                    }
+                    for part in group {
+                        write!(f, "{}", part)?;
+                    }
+                    return Ok(());
+                }
+            };
+
+            if *quantity != Ratio::one() {
+                write!(f, "{}", format_quantity(*quantity))?;
+            }
+
+            Ok(())
+        }
+    }
+
+    impl fmt::Display for Formula {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            for part in self.parts.iter() {
+                write!(f, "{}", part)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    impl fmt::Display for State {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", match self {
+                State::Solid => "(s)",
+                State::Liquid => "(l)",
+                State::Gas => "(g)",
+                State::Aqueous => "(aq)"
+            })
+        }
+    }
+
+    impl fmt::Display for Term {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            if self.coefficient != Ratio::one() {
+                write!(f, "{}", format_quantity(self.coefficient))?;
+            }
+
+            write!(f, "{}", self.formula)?;
+
+            if self.charge != Ratio::zero() {
+                let (sign, magnitude) = if self.charge < Ratio::zero() { ("-", -self.charge) } else { ("+", self.charge) };
+
+                if magnitude == Ratio::one() {
+                    write!(f, "{{{}}}", sign)?;
+                }
+                else {
+                    write!(f, "{{{}{}}}", format_quantity(magnitude), sign)?;
+                }
+            }
+
+            if let Some(state) = self.state {
+                write!(f, "{}", state)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    impl fmt::Display for Equation {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            for (i, term) in self.reactants.iter().enumerate() {
+                if i != 0 {
+                    write!(f, "+")?;
+                }
+                write!(f, "{}", term)?;
+            }
+
+            write!(f, "=")?;
+
+            for (i, term) in self.products.iter().enumerate() {
+                if i != 0 {
+                    write!(f, "+")?;
+                }
+                write!(f, "{}", term)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn parse(equation: &str) -> Equation {
+            Equation::parse(equation.as_bytes()).unwrap()
+        }
+
+        fn error(equation: &str) -> BalanceError {
+            Equation::parse(equation.as_bytes()).unwrap_err()
+        }
+
+        #[test]
+        fn printing_and_parsing_again_gives_the_same_equation() {
+            let equations = [
+                "2H2+O2=2H2O",
+                "K4[Fe(CN)6]+KMnO4=KHSO4+Fe2(SO4)3",
+                "[Cu(NH3)4]{2+}(aq)+4H{+}=Cu{2+}+4NH4{+}",
+                "CuSO4*5H2O=CuSO4(s)+5H2O(l)",
+                "CuSO4*H2O=CuSO4+H2O",
+                "Fe{3+}+{-}=Fe{2+}",
+                "0.5H2+0.25O2=H0.5O0.25",
+                "H1=H1.0",
+                "[Fe(CN)6]{+4}=Fe{2+}",
+                "MeCOOH+EtOH=MeCOOEt+H2O"
+            ];
+
+            for equation in equations.iter() {
+                let parsed = parse(equation);
+                let printed = parsed.to_string();
+
+                assert_eq!(parse(&printed), parsed, "{} printed as {}", equation, printed);
+                assert_eq!(parse(&printed).to_string(), printed);
+            }
+        }
+
+        #[test]
+        fn printing_and_parsing_decimal_quantities_gives_the_same_term() {
+            assert_eq!(Term::parse(b"H0.29").unwrap().to_string(), "H0.29");
+            assert_eq!(parse("H0.29=H0.29").to_string(), "H0.29=H0.29");
+
+            //Every coefficient and quantity with up to three decimal places, i.e. 0.001H0.001, 0.01H0.01 and 0.999H0.999
+            for thousandths in 1..1000 {
+                let decimal = format!("0.{:03}", thousandths);
+                let decimal = decimal.trim_end_matches('0');
+
+                let formula = format!("{}H{}", decimal, decimal);
+
+                let term = Term::parse(formula.as_bytes()).unwrap();
+
+                assert_eq!(term.to_string(), formula);
+                assert_eq!(Term::parse(term.to_string().as_bytes()).unwrap(), term);
+            }
+        }
+
+        #[test]
+        fn keeps_the_written_form() {
+            assert_eq!(parse("2H2+O2=2H2O").to_string(), "2H2+O2=2H2O");
+            assert_eq!(parse("[Fe(CN)6]{4-}(aq)=Fe").to_string(), "[Fe(CN)6]{4-}(aq)=Fe");
+            assert_eq!(parse("H1=H").to_string(), "H=H");
+        }
+
+        #[test]
+        fn rejects_what_cannot_be_printed() {
+            assert_eq!(error("H{0+}=H").span, Some((2, 3)));
+            assert_eq!(error("H1e5=H").span, Some((2, 4)));
+            assert_eq!(error("H{+}{-}=H").message, "A molecule can only have one charge ({-)");
+            assert_eq!(error("Na(aq)2=Na").message, "States cannot have a quantity ((aq)2)");
+        }
+
+        #[test]
+        fn rejects_totals_too_large_to_count() {
+            assert_eq!(error("(((H1000)1000)1000)1000=H").message, "The quantity of H is too large ((((H1000)1000)1000)1000)");
+            assert_eq!(error("(((H1000)1000)1000)1000=H").span, Some((0, 23)));
+            assert_eq!(error("H2+2000000000H2=H").span, Some((3, 15)));
+            assert!(parse("H2147483647+H2147483647=H").imbalance().is_err());
+        }
+
+        #[test]
+        fn reports_misplaced_parts() {
+            assert_eq!(error("H2=H2O=O2").message, "Formula must have exactly one equals");
+            assert_eq!(error("H(aq)O=H2O").message, "States must come at the end of a molecule, i.e. Fe{3+}(aq)");
+            assert_eq!(error("H{+}O=H").message, "Charges must come after the formula, i.e. [Fe(CN)6]{4-} (O)");
+            assert_eq!(error("H2=").span, None);
+            assert_eq!(error("H2 2O=H").span, Some((2, 3)));
+        }
+
+        #[test]
+        fn counts_elements_in_groups_and_hydrates() {
+            let term = Term::parse(b"CuSO4*5H2O").unwrap();
+
+            let elements: Vec<(&str, Ratio<i32>)> = term.formula.elements().into_iter().collect();
+
+            assert_eq!(elements, vec![("Cu", Ratio::from_integer(1)), ("S", Ratio::from_integer(1)), ("O", Ratio::from_integer(9)), ("H", Ratio::from_integer(10))]);
+            assert_eq!(Term::parse(b"Ca3(PO4)2").unwrap().formula.elements().get("O"), Some(Ratio::from_integer(8)));
+        }
+
+        #[test]
+        fn gives_hill_system_formulas() {
+            assert_eq!(Term::parse(b"CH3CH2OH").unwrap().canonical(), "C2H6O");
+            assert_eq!(Term::parse(b"HCO3{-}").unwrap().canonical(), "CHO3{-}");
+            assert_eq!(Term::parse(b"H2SO4").unwrap().canonical(), "H2O4S");
+        }
+
+        #[test]
+        fn finds_the_molar_mass() {
+            assert!((Term::parse(b"H2O").unwrap().formula.molar_mass().unwrap() - 18.015).abs() < 0.001);
+            assert_eq!(Term::parse(b"Xy2").unwrap().formula.molar_mass().unwrap_err().message, "Unknown element 'Xy'");
+        }
+
+        #[test]
+        fn finds_the_imbalance() {
+            assert!(parse("2H2+O2=2H2O").imbalance().unwrap().is_empty());
+            assert_eq!(parse("H2+O2=H2O").imbalance().unwrap(), vec![(String::from("O"), Ratio::from_integer(1))]);
+        }
+
+        #[test]
+        fn finds_respellings() {
+            let equation = parse("C2H5OH=CH3CH2OH");
+            let respellings = equation.respellings();
+
+            assert_eq!(respellings.len(), 1);
+            assert_eq!(respellings[0].canonical, "C2H6O");
+            assert!(respellings[0].both_sides);
+        }
+
+        #[test]
+        fn merges_duplicates_by_adding_coefficients() {
+            let mut equation = parse("2H2+H2+O2=H2O+H2O");
+
+            let merged = equation.merge_duplicates().unwrap();
+
+            assert_eq!(equation.to_string(), "3H2+O2=2H2O");
+            assert_eq!(merged.iter().map(|(term, count)| (term.to_string(), *count)).collect::<Vec<(String, usize)>>(),
+                       vec![(String::from("3H2"), 2), (String::from("2H2O"), 2)]);
+        }
+
+        #[test]
+        fn keeps_different_states_apart_when_merging() {
+            let mut equation = parse("H2O(l)+H2O(g)=H2O2+H2");
+
+            assert!(equation.merge_duplicates().unwrap().is_empty());
+            assert_eq!(equation.reactants.len(), 2);
+        }
+
+        #[test]
+        fn rejects_merged_coefficients_too_large_to_add() {
+            assert_eq!(parse("2000000000H+2000000000H=H").merge_duplicates().unwrap_err().message, "The total coefficient of H is too large");
+        }
+
+        #[test]
+        fn cancels_spectators() {
+            let mut equation = parse("Na{+}+Cl{-}+Ag{+}+NO3{-}=AgCl+Na{+}+NO3{-}");
+
+            let cancelled: Vec<String> = equation.cancel_spectators().iter().map(|term| term.to_string()).collect();
+
+            assert_eq!(cancelled, vec!["Na{+}", "NO3{-}"]);
+            assert_eq!(equation.to_string(), "Cl{-}+Ag{+}=AgCl");
+        }
+
+        #[test]
+        fn cached_equations_match_parsing_the_whole_equation() {
+            let mut cache = TermCache::new();
+
+            let equations = [
+                "2H2+O2=2H2O", "H2O=2H2+O2", "Fe{3+}+e{-}=Fe{2+}", "[Fe(CN)6]{4-}+H{+}=HCN+Fe{2+}", "CuSO4*5H2O(s)=CuSO4+H2O",
+                "H2+O2", "H2=O2=H2O", "=H2O", "H2+=H2O", "H2(+O2=H2O", "Fe{3+}{2-}=Fe", "H2+O2=H2Ox", "H2O(aq)2=H2O"
+            ];
+
+            for equation in equations.iter() {
+                match (Equation::parse(equation.as_bytes()), cache.equation(equation.as_bytes())) {
+                    (Ok(whole), Ok(cached)) => assert_eq!(whole.to_string(), cached.to_string(), "{}", equation),
+                    (Err(whole), Err(cached)) => assert_eq!((whole.message, whole.span), (cached.message, cached.span), "{}", equation),
+                    (whole, cached) => panic!("{} gave {:?} and {:?}", equation, whole.is_ok(), cached.is_ok())
+                }
+            }
+
+            let cached = cache.len();
+
+            cache.equation(b"2H2O=2H2+O2").unwrap();
+            assert_eq!(cache.len(), cached);
+        }
+
+        #[test]
+        fn formats_quantities_as_decimals() {
+            assert_eq!(format_quantity(Ratio::from_integer(3)), "3");
+            assert_eq!(format_quantity(Ratio::new(1, 4)), "0.25");
+            assert_eq!(format_quantity(Ratio::new(-5, 2)), "-2.5");
+            assert_eq!(format_quantity(Ratio::new(3, 40)), "0.075");
+            assert_eq!(format_quantity(Ratio::new(1, 3)), "1/3");
+        }
+    }
+}
@@ -0,0 +1,1213 @@
+pub mod balancer {
+
+    use crate::ast::equation::{parse_group, ElementTable, Equation, TermCache};
+    use crate::error::balance_error::{BalanceError, ErrorKind};
+    use crate::output::matrix;
+    use crate::predict::products;
+    use crate::solve::matrices::Augmented;
+
+    use num_rational::Ratio;
+
+    use num_traits::identities::Zero;
+
+    use std::fmt::Write;
+
+    fn send_column<'a>(table: & mut ElementTable<'a>,col: & mut Vec<Ratio<i32>>,
+                   mat: & mut Augmented,master: &ElementTable<'a>,verbose: bool, log: & mut String) {
+        if verbose {
+            for (element, quantity) in table.iter() {
+                write!(log, "({} {}) ", element, quantity).unwrap();
+            }
+            writeln!(log, "\n").unwrap();
+        }
+
+        for element in master.keys() {
+            col.push(table[element]);
+        }
+
+        mat.add_column(col);
+
+        col.clear();
+
+        *table = master.clone();
+    }
+
+    // The augmented element by species matrix for an equation, with a row for each element (and the charge) and a column
+    // for each species, products negated. Rows and columns are labelled. Intermediate steps (verbose) are written to log
+    pub fn composition_matrix(equation: &Equation, verbose: bool, log: & mut String) -> Result<Augmented, BalanceError> {
+
+        //Elements are in the order they first appear, so the matrix rows are the same every time
+        let mut master_table = ElementTable::new();
+
+        //Perform the initial run looking for ions and symbols
+        for term in equation.terms() {
+            parse_group(term, & mut master_table, Ratio::zero())?;
+        }
+
+        if verbose {
+            writeln!(log, "Symbol table").unwrap();
+            for element in master_table.keys() {
+                write!(log, "{} ", element).unwrap();
+            }
+            writeln!(log, "\n").unwrap();
+        }
+
+        let mut symbol_table = master_table.clone();
+
+        let mut matrix = Augmented::new(symbol_table.len() );
+
+        let mut column = Vec::with_capacity(symbol_table.len() );
+
+        if verbose {
+            writeln!(log, "Molecule matrix").unwrap();
+        }
+
+        //Add a column to the matrix for each molecule, with products negated
+        for term in equation.reactants.iter() {
+            parse_group(term, & mut symbol_table, Ratio::from_integer(1))?;
+
+            send_column(& mut symbol_table, & mut column, & mut matrix, &master_table,verbose, log);
+        }
+
+        for term in equation.products.iter() {
+            parse_group(term, & mut symbol_table, Ratio::from_integer(-1))?;
+
+            send_column(& mut symbol_table, & mut column, & mut matrix, &master_table,verbose, log);
+        }
+
+        matrix.augment();
+
+        let rows = master_table.keys().map(String::from).collect();
+
+        //Coefficients are what is being solved for, so species are named without them
+        let columns = equation.terms().map(|term| {
+            let mut species = term.clone();
+
+            species.coefficient = Ratio::from_integer(1);
+
+            species.to_string()
+        }).collect();
+
+        matrix.label(rows, columns);
+
+        if verbose {
+            writeln!(log, "Augmented matrix").unwrap();
+            writeln!(log, "{}", matrix).unwrap();
+        }
+
+        Ok(matrix)
+    }
+
+    // Balance the equation. Intermediate steps (verbose) or an explanation of the method (explain) are written to log
+    // rather than printed, so equations can be solved in parallel
+    pub fn solve_equation(equation: &Equation, verbose: bool, explain: bool, log: & mut String) -> Result<Equation, BalanceError> {
+
+        let mut matrix = composition_matrix(equation, verbose, log)?;
+
+        if explain {
+            writeln!(log, "Step 1: Count each element (and the charge) in each species. Each row is an element and each column is a species.").unwrap();
+            writeln!(log, "Products are negative, so each row adds up to zero when the equation is balanced").unwrap();
+            writeln!(log, "{}", matrix.labelled()).unwrap();
+            writeln!(log, "Step 2: Row reduce the matrix, one column at a time, until each pivot is 1 with zeros above and below it").unwrap();
+        }
+
+        matrix.row_reduce(if explain { Some(& mut *log) } else { None })?;
+
+        if verbose {
+            writeln!(log, "Row reduced matrix").unwrap();
+            writeln!(log, "{}", matrix).unwrap();
+        }
+
+        if explain {
+            writeln!(log, "Step 3: Choose a free variable and find the smallest whole number coefficients").unwrap();
+        }
+
+        match matrix.solve(if explain { Some(& mut *log) } else { None }) {
+            Ok(solution) => {
+                if verbose {
+
+                    writeln!(log, "Solved matrix").unwrap();
+                    writeln!(log, "{}", matrix).unwrap();
+
+                    writeln!(log, "Solution vector").unwrap();
+                    for coeff in solution.iter() {
+                        write!(log, "{} ", coeff).unwrap();
+                    }
+                    writeln!(log, "\n").unwrap();
+                }
+
+                let mut result = equation.clone();
+
+                for (term, coefficient) in result.terms_mut().zip(solution.iter()) {
+                    term.coefficient = Ratio::from_integer(*coefficient);
+                }
+
+                if explain {
+                    writeln!(log, "\nThe balanced equation is {}\n", result).unwrap();
+                }
+
+                Ok(result)
+            },
+            Err(e) => {
+                Err(e)
+            }
+        }
+
+    }
+
+    // The string with spaces, tabs and newlines removed
+    pub fn remove_whitespace(string: & str) -> String {
+        let mut result = String::new();
+
+        for ch in string.as_bytes() {
+            if *ch != 9 && *ch != 10 && *ch != 13 && *ch != 32 {
+                result.push(char::from(*ch));
+            }
+        }
+
+        result
+    }
+
+    // Ways of printing the result of balancing an equation
+    #[derive(Clone, Copy, PartialEq)]
+    pub enum Format {
+        Text,
+        Json,
+        Latex
+    }
+
+    // Options that change how each equation is balanced
+    pub struct Options {
+        pub verbose: bool,
+        pub explain: bool,
+        pub spellings: bool,
+        pub spectators: bool,
+        pub classify: bool, //Whether the type of each balanced reaction is given
+        pub format: Format,
+        pub export: Option<matrix::Format> //Format to export the composition and row reduced matrices in, if they are wanted
+    }
+
+    // Map a span within the equation with whitespace removed back onto the equation as it was typed
+    pub fn original_span(equation: &str, span: (usize, usize)) -> (usize, usize) {
+        let positions: Vec<usize> = equation.bytes().enumerate()
+            .filter(|(_, ch)| *ch != 9 && *ch != 10 && *ch != 13 && *ch != 32)
+            .map(|(index, _)| index)
+            .collect();
+
+        let start = positions.get(span.0).cloned().unwrap_or(equation.len());
+
+        let end = if span.1 > span.0 {
+            positions.get(span.1 - 1).map(|index| index + 1).unwrap_or(equation.len())
+        }
+        else {
+            start
+        };
+
+        (start, end)
+    }
+
+    // Parse an equation as it was typed, with spans in any error referring to the typed equation
+    pub fn parse(equation: &str) -> Result<Equation, BalanceError> {
+
+        let cleaned_equation = remove_whitespace(equation);
+
+        Equation::parse(cleaned_equation.as_bytes()).map_err(|mut error| {
+            error.span = error.span.map(|span| original_span(equation, span));
+            error
+        })
+    }
+
+    // As parse, but only the molecules not already in cache are parsed
+    pub fn parse_cached(equation: &str, cache: & mut TermCache) -> Result<Equation, BalanceError> {
+
+        let cleaned_equation = remove_whitespace(equation);
+
+        cache.equation(cleaned_equation.as_bytes()).map_err(|mut error| {
+            error.span = error.span.map(|span| original_span(equation, span));
+            error
+        })
+    }
+
+    // Parse, tidy up and balance a single equation. Warnings and notes for the user are added to notes, and intermediate
+    // steps are written to log
+    pub fn balance(equation: &str, options: &Options, notes: & mut Vec<String>, log: & mut String) -> Result<Equation, BalanceError> {
+        balance_with(equation, options, notes, log, parse)
+    }
+
+    // As balance, with a complete equation parsed by parse_equation, i.e. parse_cached in the repl
+    pub fn balance_with(equation: &str, options: &Options, notes: & mut Vec<String>, log: & mut String,
+                        parse_equation: impl FnOnce(&str) -> Result<Equation, BalanceError>) -> Result<Equation, BalanceError> {
+
+        let cleaned_equation = remove_whitespace(equation);
+
+        //Products are predicted for an equation written with only its reactants, i.e. CH4 + O2 = ?
+        let mut parsed = match products::unfinished(&cleaned_equation) {
+            Some(reactants) => {
+                let reactants = Equation::parse_side(reactants.as_bytes()).map_err(|mut error| {
+                    error.span = error.span.map(|span| original_span(equation, span));
+                    error
+                })?;
+
+                let prediction = products::predict(reactants)?;
+
+                notes.push(format!("Note: The products are predicted, assuming {}", prediction.rule.description()));
+
+                prediction.equation
+            },
+            None => parse_equation(equation)?
+        };
+
+        if options.spellings {
+            for respelling in parsed.respellings() {
+                notes.push(format!("Warning: {} and {} are both {}{}, they may be the same species",
+                                   respelling.first.formula, respelling.second.formula, respelling.canonical,
+                                   if respelling.both_sides { " on opposite sides of the equation" } else { "" }));
+            }
+        }
+
+        for (term, count) in parsed.merge_duplicates()? {
+            let mut species = term.clone();
+
+            species.coefficient = Ratio::from_integer(1);
+
+            notes.push(format!("Note: {} is written {} times on the same side, these have been merged into {}", species, count, term));
+        }
+
+        if options.spectators {
+            for term in parsed.cancel_spectators() {
+                notes.push(format!("Note: {} is on both sides of the equation, it has been removed as a catalyst or spectator", term));
+            }
+
+            if parsed.reactants.is_empty() || parsed.products.is_empty() {
+                return Err(BalanceError::new(ErrorKind::Impossible, String::from("Formula must have at least one reactant and one product")));
+            }
+        }
+
+        let spectators: Vec<String> = parsed.spectators().iter().map(|term| term.to_string()).collect();
+
+        solve_equation(&parsed, options.verbose, options.explain, log).map_err(|mut error| {
+            //Quantities too large to balance are a problem with the equation as a whole, rather than any one part of it
+            if error.kind == ErrorKind::Parse && error.span.is_none() {
+                error.span = Some((0, equation.len()));
+            }
+
+            if !spectators.is_empty() {
+                error.message = format!("{} {} on both sides of the equation. Hint: Use --spectators to remove catalysts and spectator ions ({})",
+                                        spectators.join(", "), if spectators.len() == 1 { "is" } else { "are" }, error.message);
+            }
+
+            error
+        })
+    }
+
+    // The composition matrix of an equation and its row reduced form, exported in the given format
+    pub fn export_matrices(equation: &Equation, format: matrix::Format) -> Result<(String, String), BalanceError> {
+
+        let mut composition = composition_matrix(equation, false, & mut String::new())?;
+
+        let exported = matrix::export(&composition, format, &format!("Composition matrix of {}", equation));
+
+        composition.row_reduce(None)?;
+
+        Ok((exported, matrix::export(&composition, format, &format!("Row reduced matrix of {}", equation))))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        use std::panic;
+
+        fn solve(equation: &str) -> Result<Equation, BalanceError> {
+            solve_equation(&Equation::parse(equation.as_bytes())?, false, false, & mut String::new())
+        }
+
+        #[test]
+        fn balances_equations() {
+            assert_eq!(solve("H2+O2=H2O").unwrap().to_string(), "2H2+O2=2H2O");
+            assert_eq!(solve("C3H8+O2=CO2+H2O").unwrap().to_string(), "C3H8+5O2=3CO2+4H2O");
+            assert_eq!(solve("K4[Fe(CN)6]+KMnO4+H2SO4=KHSO4+Fe2(SO4)3+MnSO4+HNO3+CO2+H2O").unwrap().to_string(),
+                       "10K4[Fe(CN)6]+122KMnO4+299H2SO4=162KHSO4+5Fe2(SO4)3+122MnSO4+60HNO3+60CO2+188H2O");
+            assert_eq!(solve("Cu+Ag{+}=Cu{2+}+Ag").unwrap().to_string(), "Cu+2Ag{+}=Cu{2+}+2Ag");
+            assert_eq!(solve("H0.29+O=H2O").unwrap().to_string(), "200H0.29+29O=29H2O");
+            assert_eq!(solve("H0.57=H").unwrap().to_string(), "100H0.57=57H");
+        }
+
+        #[test]
+        fn reports_why_an_equation_cannot_be_balanced() {
+            assert_eq!(solve("H2=O2").unwrap_err().kind, ErrorKind::Impossible);
+            assert_eq!(solve("H2+O2+H2O2=H2O").unwrap_err().kind, ErrorKind::Ambiguous);
+            assert_eq!(solve("H2O=H2+O2+H2O2+O3").unwrap_err().kind, ErrorKind::Ambiguous);
+            assert_eq!(solve("H2O+O2=H2").unwrap_err().kind, ErrorKind::Negative);
+            assert_eq!(solve("H99999999.5=H").unwrap_err().kind, ErrorKind::Parse);
+        }
+
+        #[test]
+        fn labels_the_composition_matrix() {
+            let matrix = composition_matrix(&Equation::parse(b"2H2+O2=H2O").unwrap(), false, & mut String::new()).unwrap();
+
+            assert_eq!(matrix.row_labels(), ["H", "O"]);
+            assert_eq!(matrix.column_labels(), ["H2", "O2", "H2O"]);
+            assert_eq!(matrix.coefficients(), vec![vec![Ratio::from_integer(2), Ratio::zero(), Ratio::from_integer(-2)],
+                                                   vec![Ratio::zero(), Ratio::from_integer(2), Ratio::from_integer(-1)]]);
+        }
+
+        #[test]
+        fn maps_spans_onto_the_typed_equation() {
+            assert_eq!(original_span("H2 + O2 = H2O", (3, 5)), (5, 7));
+            assert_eq!(parse("H2 + (O2 = H2O").unwrap_err().span, Some((5, 14)));
+
+            let options = Options { verbose: false, explain: false, spellings: false, spectators: false, classify: false, format: Format::Text, export: None };
+
+            assert_eq!(balance("H99999999.5 = H", &options, & mut Vec::new(), & mut String::new()).unwrap_err().span, Some((0, 15)));
+        }
+
+        #[test]
+        fn corpus_gives_errors_without_panicking() {
+            for line in include_str!("../../docs/fuzz_corpus.txt").lines() {
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+
+                match panic::catch_unwind(|| solve(line)) {
+                    Ok(result) => assert!(result.is_err(), "'{}' should not balance", line),
+                    Err(_) => panic!("'{}' panicked", line)
+                }
+            }
+        }
+    }
+}
+
+pub mod report {
+
+    use crate::ast::equation::{format_quantity, Equation, Term};
+    use crate::balance::balancer::{balance, balance_with, export_matrices, parse, solve_equation, Format, Options};
+    use crate::classify::reaction;
+    use crate::electro::cell;
+    use crate::elements::periodic_table;
+    use crate::equilibrium::constant::{self, Kind};
+    use crate::error::balance_error::{self, BalanceError, ErrorKind};
+    use crate::output::{json, latex};
+    use crate::predict::combustion::{self, Nitrogen};
+    use crate::redox::oxidation;
+    use crate::stoich::amounts::{self, Conditions, Given, Solution};
+    use crate::stoich::titration::{self, Unknown};
+    use crate::thermo::formation::{self, Spontaneity, Table};
+    use crate::units::quantity::{Dimension, Quantity, Unit, Value};
+
+    use num_rational::Ratio;
+
+    use num_traits::identities::{One, Zero};
+
+    use std::fmt::Write;
+
+    // Everything printed for a single equation, and the exit code for it (zero if it succeeded). Errors go to stderr so
+    // they can be told apart from results
+    #[derive(Clone)]
+    pub struct Outcome {
+        pub output: String,
+        pub errors: String,
+        pub code: i32
+    }
+
+
+    // Temperature of standard conditions, in K
+    pub const STANDARD_TEMPERATURE: f64 = 298.15;
+
+    //Most steps allowed in a --sweep of temperatures
+    const SWEEP_LIMIT: usize = 10000;
+
+    // Balance a single equation, returning everything to be printed for it and its exit code.
+    // The line number is given for equations from an --input file
+    pub fn balance_line(line_number: Option<usize>, line: &str, options: &Options) -> Outcome {
+        balance_line_with(line_number, line, options, parse)
+    }
+
+    // As balance_line, with a complete equation parsed by parse_equation, i.e. parse_cached in the repl
+    pub fn balance_line_with(line_number: Option<usize>, line: &str, options: &Options,
+                             parse_equation: impl FnOnce(&str) -> Result<Equation, BalanceError>) -> Outcome {
+
+        let mut notes = Vec::new();
+        let mut log = String::new();
+
+        let mut result = balance_with(line, options, & mut notes, & mut log, parse_equation);
+
+        //The matrices are those just reduced while balancing, so an error here is only a safeguard
+        let matrices = match result.as_ref().ok().zip(options.export).map(|(s, format)| export_matrices(s, format)) {
+            Some(Ok(matrices)) => Some(matrices),
+            Some(Err(e)) => {
+                result = Err(e);
+                None
+            },
+            None => None
+        };
+
+        let code = match &result {
+            Ok(_) => 0,
+            Err(e) => e.kind.exit_code()
+        };
+
+        let classes = match &result {
+            Ok(s) if options.classify => Some(reaction::classify(s)),
+            _ => None
+        };
+
+        let mut output = String::new();
+        let mut errors = String::new();
+
+        if options.format == Format::Json {
+            let steps = if options.verbose || options.explain { Some(log.as_str()) } else { None };
+
+            let matrices = matrices.as_ref().map(|(composition, reduced)| (composition.as_str(), reduced.as_str()));
+
+            match &result {
+                Ok(s) => writeln!(output, "{}", json::balanced(line_number, line, s, &notes, steps, matrices, classes.as_deref())).unwrap(),
+                Err(e) => writeln!(output, "{}", json::error(line_number, line, e, &notes)).unwrap()
+            }
+
+            return Outcome { output, errors, code };
+        }
+
+        for note in notes.iter() {
+            writeln!(output, "{}", note).unwrap();
+        }
+
+        output.push_str(&log);
+
+        match &result {
+            Ok(s) if options.format == Format::Latex => {
+                writeln!(output, "{}", latex::equation(s)).unwrap();
+            },
+            Ok(s) => {
+                if options.verbose {
+                    writeln!(output, "Equation: {}", s).unwrap();
+                }
+                else {
+                    writeln!(output, "{}", s).unwrap();
+                }
+            },
+            Err(e) => {
+                match line_number {
+                    Some(line_number) => writeln!(errors, "Line {}: Cannot solve equation. {}", line_number, e).unwrap(),
+                    None => writeln!(errors, "Cannot solve equation. {}", e).unwrap()
+                }
+            }
+        }
+
+        if let Some(classes) = classes {
+            let names: Vec<&str> = classes.iter().map(|class| class.name()).collect();
+
+            //A LaTeX comment, so the output can still be pasted into a document
+            let prefix = if options.format == Format::Latex { "% " } else { "" };
+
+            writeln!(output, "{}Type: {}", prefix, if names.is_empty() { String::from("unknown") } else { names.join(", ") }).unwrap();
+        }
+
+        if let Some((composition, reduced)) = matrices {
+            write!(output, "\nComposition matrix\n{}\nRow reduced matrix\n{}", composition, reduced).unwrap();
+        }
+
+        Outcome { output, errors, code }
+    }
+
+    // Check whether an equation with the user's own coefficients is balanced, returning everything to be printed for it and
+    // its exit code, which is EXIT_UNBALANCED if it is not balanced
+    pub fn check_line(line_number: Option<usize>, line: &str, options: &Options) -> Outcome {
+        check_line_with(line_number, line, options, parse)
+    }
+
+    // As check_line, with the equation parsed by parse_equation, i.e. parse_cached in the repl
+    pub fn check_line_with(line_number: Option<usize>, line: &str, options: &Options,
+                           parse_equation: impl FnOnce(&str) -> Result<Equation, BalanceError>) -> Outcome {
+
+        let mut output = String::new();
+        let mut errors = String::new();
+
+        let prefix = match line_number {
+            Some(line_number) => format!("Line {}: ", line_number),
+            None => String::new()
+        };
+
+        let checked = parse_equation(line).and_then(|equation| {
+            let imbalance = equation.imbalance()?;
+
+            Ok((equation, imbalance))
+        });
+
+        let (equation, imbalance) = match checked {
+            Ok(checked) => checked,
+            Err(e) => {
+                if options.format == Format::Json {
+                    writeln!(output, "{}", json::error(line_number, line, &e, &[])).unwrap();
+                }
+                else {
+                    writeln!(errors, "{}Cannot check equation. {}", prefix, e).unwrap();
+                }
+
+                return Outcome { output, errors, code: e.kind.exit_code() };
+            }
+        };
+
+        match options.format {
+            Format::Json => {
+                writeln!(output, "{}", json::checked(line_number, line, &equation, &imbalance, &[])).unwrap();
+            },
+            Format::Text | Format::Latex => {
+                if imbalance.is_empty() {
+                    writeln!(output, "{}{} is balanced", prefix, equation).unwrap();
+                }
+                else {
+                    writeln!(output, "{}{} is not balanced", prefix, equation).unwrap();
+
+                    for (element, difference) in imbalance.iter() {
+                        let (side, magnitude) = if *difference > Ratio::zero() { ("reactants", *difference) } else { ("products", -*difference) };
+
+                        writeln!(output, "    {} has {} more in the {}", element, format_quantity(magnitude), side).unwrap();
+                    }
+                }
+            }
+        }
+
+        let code = if imbalance.is_empty() { 0 } else { balance_error::EXIT_UNBALANCED };
+
+        Outcome { output, errors, code }
+    }
+
+    // The molar mass of a single molecule, with the contribution of each element
+    pub fn mass_report(input: &str, term: &Term, options: &Options) -> Result<String, BalanceError> {
+
+        let total = term.formula.molar_mass()?;
+
+        let mut output = String::new();
+
+        if options.format == Format::Json {
+            writeln!(output, "{}", json::mass(input, term, total)).unwrap();
+
+            return Ok(output);
+        }
+
+        let mut elements: Vec<(&str, Ratio<i32>)> = term.formula.elements().into_iter().collect();
+
+        elements.sort_by(|a, b| a.0.cmp(b.0));
+
+        writeln!(output, "{} = {:.3} g/mol", term.formula, total).unwrap();
+
+        for (element, quantity) in elements {
+            //Every element was found when calculating the total, so the lookup cannot fail
+            let found = periodic_table::lookup(element).unwrap();
+
+            let mass = found.mass * (*quantity.numer() as f64 / *quantity.denom() as f64);
+
+            writeln!(output, "    {:<3} {:<14} {:>6} x {:>8.3} = {:>9.3} g/mol ({:.2}%)", element, found.name, format_quantity(quantity), found.mass, mass,
+                     if total != 0.0 { mass / total * 100.0 } else { 0.0 }).unwrap();
+        }
+
+        Ok(output)
+    }
+
+    // The balanced combustion equation of a fuel, with nitrogen given off as chosen and carbon as CO if the combustion is
+    // incomplete
+    pub fn combust_report(input: &str, fuel: &Term, nitrogen: Nitrogen, incomplete: bool, options: &Options) -> Result<String, BalanceError> {
+
+        let equation = combustion::equation(fuel, nitrogen, incomplete)?;
+
+        let balanced = solve_equation(&equation, false, false, & mut String::new())?;
+
+        let mut notes = Vec::new();
+
+        if incomplete && fuel.formula.elements().contains("C") {
+            notes.push(String::from("Note: The combustion is incomplete, so carbon is given off as CO"));
+        }
+
+        let mut output = String::new();
+
+        if options.format == Format::Json {
+            writeln!(output, "{}", json::balanced(None, input, &balanced, &notes, None, None, None)).unwrap();
+
+            return Ok(output);
+        }
+
+        for note in notes.iter() {
+            writeln!(output, "{}", note).unwrap();
+        }
+
+        writeln!(output, "{}", balanced).unwrap();
+
+        Ok(output)
+    }
+
+    //Format an oxidation state with its sign, i.e. +7, -2 or 0
+    fn signed(state: Ratio<i32>) -> String {
+        if state > Ratio::zero() {
+            format!("+{}", format_quantity(state))
+        }
+        else {
+            format_quantity(state)
+        }
+    }
+
+    //A number of electrons, i.e. 1 electron or 5 electrons
+    fn electrons(count: Ratio<i32>) -> String {
+        format!("{} electron{}", format_quantity(count), if count == Ratio::from_integer(1) { "" } else { "s" })
+    }
+
+    // The oxidation state of each element in a balanced equation, and the elements that are oxidised or reduced
+    pub fn redox_report(equation: &str, options: &Options) -> Result<String, BalanceError> {
+
+        let mut notes = Vec::new();
+        let mut log = String::new();
+
+        let balanced = balance(equation, options, & mut notes, & mut log)?;
+
+        let states: Vec<oxidation::States> = balanced.terms().map(oxidation::states).collect();
+
+        let (changes, unknown) = oxidation::changes(&balanced);
+
+        let transferred = changes.iter().fold(Ratio::zero(), |sum, change| sum + change.lost);
+
+        let mut output = String::new();
+
+        if options.format == Format::Json {
+            writeln!(output, "{}", json::redox(equation, &balanced, &states, &changes, &unknown, transferred)).unwrap();
+
+            return Ok(output);
+        }
+
+        for note in notes.iter() {
+            writeln!(output, "{}", note).unwrap();
+        }
+
+        writeln!(output, "{}", balanced).unwrap();
+
+        writeln!(output, "Oxidation states").unwrap();
+
+        for (term, states) in balanced.terms().zip(states.iter()) {
+            //Electrons have no elements, so no oxidation states
+            if states.is_empty() {
+                continue;
+            }
+
+            let mut species = term.clone();
+
+            species.coefficient = Ratio::from_integer(1);
+
+            let states: Vec<String> = states.iter()
+                .map(|(element, state)| format!("{} {}", element, state.map(signed).unwrap_or_else(|| String::from("?"))))
+                .collect();
+
+            writeln!(output, "    {:<16} {}", species.to_string(), states.join(", ")).unwrap();
+        }
+
+        for change in changes.iter() {
+            let action = match (change.lost != Ratio::zero(), change.gained != Ratio::zero()) {
+                (true, true) => format!("is both oxidised and reduced, loses {} and gains {}", electrons(change.lost), electrons(change.gained)),
+                (true, false) => format!("is oxidised, loses {}", electrons(change.lost)),
+                _ => format!("is reduced, gains {}", electrons(change.gained))
+            };
+
+            let transitions: Vec<String> = change.transitions.iter()
+                .map(|(from, to)| format!("{} \u{2192} {} ({})", signed(*from), signed(*to), if to > from { "oxidised" } else { "reduced" }))
+                .collect();
+
+            writeln!(output, "{} {} ({})", change.element, action, transitions.join(", ")).unwrap();
+        }
+
+        if !unknown.is_empty() {
+            writeln!(output, "The oxidation states of {} could not be found", unknown.join(", ")).unwrap();
+        }
+
+        //The electrons transferred are only known if every oxidation state is
+        if unknown.is_empty() {
+            if changes.is_empty() {
+                writeln!(output, "No oxidation states change, this is not a redox reaction").unwrap();
+            }
+            else {
+                writeln!(output, "{} transferred", electrons(transferred)).unwrap();
+            }
+        }
+
+        Ok(output)
+    }
+
+    // The amount of every species in a balanced equation, from the amounts given for some of them. Given volumes are at
+    // the first conditions, and the volumes of gases are reported at the second
+    pub fn stoich_report(equation: &str, given: &[&str], conditions: (Conditions, Conditions), options: &Options) -> Result<String, BalanceError> {
+
+        let mut notes = Vec::new();
+        let mut log = String::new();
+
+        let balanced = balance(equation, options, & mut notes, & mut log)?;
+
+        let mut given = given.iter().map(|text| Given::parse(text)).collect::<Result<Vec<Given>, BalanceError>>()?;
+
+        for amount in given.iter_mut() {
+            amount.conditions = conditions.0;
+        }
+
+        let result = amounts::solve(&balanced, &given, &conditions.1)?;
+
+        let mut output = String::new();
+
+        if options.format == Format::Json {
+            writeln!(output, "{}", json::stoichiometry(equation, &balanced, &result, &conditions.1)).unwrap();
+
+            return Ok(output);
+        }
+
+        for note in notes.iter() {
+            writeln!(output, "{}", note).unwrap();
+        }
+
+        writeln!(output, "{}", balanced).unwrap();
+
+        if let Some(limiting) = &result.limiting {
+            writeln!(output, "Limiting reactant: {}", limiting).unwrap();
+        }
+
+        for amount in result.amounts.iter() {
+            write!(output, "    {:<16} {:>12.4} mol {:>12.3} g", amount.species.to_string(), amount.moles, amount.grams).unwrap();
+
+            if let Some(litres) = amount.litres {
+                write!(output, " {:>12.4} L", litres).unwrap();
+            }
+
+            match amount.excess {
+                Some(excess) if excess > 0.0 => writeln!(output, " ({:.4} mol left over)", excess).unwrap(),
+                _ => writeln!(output).unwrap()
+            }
+        }
+
+        if result.amounts.iter().any(|amount| amount.litres.is_some()) {
+            writeln!(output, "Gas volumes are at {}", conditions.1).unwrap();
+        }
+
+        Ok(output)
+    }
+
+    // The temperatures in K given with --temperature, followed by those in a --sweep written as start:end:step
+    pub fn temperatures(values: &[&str], sweep: Option<&str>) -> Result<Vec<f64>, BalanceError> {
+        let number = |value: &str| match value.trim().parse::<f64>() {
+            Ok(number) if number > 0.0 && number.is_finite() => Ok(number),
+            _ => Err(BalanceError::new(ErrorKind::Input, format!("'{}' is not a temperature, temperatures are positive numbers in K", value)))
+        };
+
+        let mut result = values.iter().map(|value| number(value)).collect::<Result<Vec<f64>, BalanceError>>()?;
+
+        if let Some(sweep) = sweep {
+            let parts: Vec<&str> = sweep.split(':').collect();
+
+            if parts.len() != 3 {
+                return Err(BalanceError::new(ErrorKind::Input, format!("'{}' must be written as start:end:step, i.e. 300:1000:100", sweep)));
+            }
+
+            let (start, end, step) = (number(parts[0])?, number(parts[1])?, number(parts[2])?);
+
+            let count = ((end - start) / step + 1e-9).floor();
+
+            if !(0.0..=SWEEP_LIMIT as f64).contains(&count) {
+                return Err(BalanceError::new(ErrorKind::Input, format!("'{}' must have an end after its start, and at most {} steps", sweep, SWEEP_LIMIT)));
+            }
+
+            result.extend((0..=count as usize).map(|index| start + index as f64 * step));
+        }
+
+        Ok(result)
+    }
+
+    //A number given by its natural log in scientific notation, so that very large or small equilibrium constants do not
+    //overflow, i.e. 1.234e143
+    fn exponential(ln: f64) -> String {
+        let log = ln / std::f64::consts::LN_10;
+
+        let mut exponent = log.floor();
+        let mut mantissa = 10f64.powf(log - exponent);
+
+        //A log just below a whole number, i.e. 2.9999999 for 1000, would otherwise be written as 10.000e2
+        if mantissa >= 9.9995 {
+            mantissa /= 10.0;
+            exponent += 1.0;
+        }
+
+        format!("{:.3}e{}", mantissa, exponent)
+    }
+
+    // The enthalpy, entropy and Gibbs energy changes of a balanced equation, from the formation data of each species, with
+    // the Gibbs energy change and equilibrium constant at each temperature given
+    pub fn thermo_report(equation: &str, data: &[&str], temperatures: &[f64], options: &Options) -> Result<String, BalanceError> {
+
+        let mut notes = Vec::new();
+        let mut log = String::new();
+
+        let balanced = balance(equation, options, & mut notes, & mut log)?;
+
+        let mut table = Table::embedded();
+
+        for path in data {
+            table.load(path)?;
+        }
+
+        let result = formation::reaction(&balanced, &table).map_err(|missing| {
+            let missing: Vec<String> = missing.iter().map(|term| term.to_string()).collect();
+
+            BalanceError::new(ErrorKind::Data, format!("No formation data for {}. Data can be added with --data", missing.join(", ")))
+        })?;
+
+        let standard: Vec<String> = result.species.iter().filter(|species| species.standard).map(|species| {
+            let mut term = species.term.clone();
+
+            term.coefficient = Ratio::one();
+
+            term.to_string()
+        }).collect();
+
+        if !standard.is_empty() {
+            notes.push(format!("Note: Standard states are used for species without a state, {}", standard.join(", ")));
+        }
+
+        let mut output = String::new();
+
+        if options.format == Format::Json {
+            writeln!(output, "{}", json::thermo(equation, &balanced, &notes, &result, temperatures)).unwrap();
+
+            return Ok(output);
+        }
+
+        for note in notes.iter() {
+            writeln!(output, "{}", note).unwrap();
+        }
+
+        writeln!(output, "{}", balanced).unwrap();
+
+        writeln!(output, "    {:<16} {:>14} {:>14} {:>14}", "Species", "dHf (kJ/mol)", "S (J/mol K)", "dGf (kJ/mol)").unwrap();
+
+        for species in result.species.iter() {
+            let mut term = species.term.clone();
+
+            term.coefficient = Ratio::one();
+
+            writeln!(output, "    {:<16} {:>14.2} {:>14.2} {:>14.2}", term.to_string(), species.formation.enthalpy,
+                     species.formation.entropy, species.formation.gibbs).unwrap();
+        }
+
+        writeln!(output, "Enthalpy change:     {:.2} kJ/mol", result.enthalpy).unwrap();
+        writeln!(output, "Entropy change:      {:.2} J/(mol K)", result.entropy).unwrap();
+        writeln!(output, "Gibbs energy change: {:.2} kJ/mol", result.gibbs).unwrap();
+
+        match result.spontaneity() {
+            Spontaneity::Always => writeln!(output, "Spontaneous at every temperature").unwrap(),
+            Spontaneity::Never => writeln!(output, "Not spontaneous at any temperature").unwrap(),
+            Spontaneity::Below(temperature) => writeln!(output, "Spontaneous below {:.1} K", temperature).unwrap(),
+            Spontaneity::Above(temperature) => writeln!(output, "Spontaneous above {:.1} K", temperature).unwrap()
+        }
+
+        if !temperatures.is_empty() {
+            writeln!(output, "    {:>10} {:>14} {:>14}", "T (K)", "dG (kJ/mol)", "K").unwrap();
+
+            for temperature in temperatures {
+                writeln!(output, "    {:>10.2} {:>14.2} {:>14}", temperature, result.gibbs_at(*temperature),
+                         exponential(result.ln_constant_at(*temperature))).unwrap();
+            }
+        }
+
+        Ok(output)
+    }
+
+    // The equilibrium expression of a balanced equation, and its ICE table if the constant is given
+    pub fn equilibrium_report(equation: &str, initial: &[&str], value: Option<&str>, kind: Kind, options: &Options) -> Result<String, BalanceError> {
+
+        let mut notes = Vec::new();
+        let mut log = String::new();
+
+        let balanced = balance(equation, options, & mut notes, & mut log)?;
+
+        let expression = constant::expression(&balanced, kind);
+
+        let ice = match value {
+            Some(value) => {
+                let value = value.trim().parse::<f64>()
+                    .map_err(|_| BalanceError::new(ErrorKind::Input, format!("{} must be a number, not '{}'", expression.name(), value)))?;
+
+                let initial = initial.iter().map(|text| constant::parse_initial(text, &[kind.dimension()])).collect::<Result<Vec<(f64, Term)>, BalanceError>>()?;
+
+                Some(constant::solve(&expression, &initial, value)?)
+            },
+            None => None
+        };
+
+        let mut output = String::new();
+
+        if options.format == Format::Json {
+            writeln!(output, "{}", json::equilibrium(equation, &balanced, &notes, &expression, ice.as_ref())).unwrap();
+
+            return Ok(output);
+        }
+
+        for note in notes.iter() {
+            writeln!(output, "{}", note).unwrap();
+        }
+
+        writeln!(output, "{}", balanced).unwrap();
+        writeln!(output, "{}", expression).unwrap();
+
+        if !expression.excluded.is_empty() {
+            let excluded: Vec<String> = expression.excluded.iter().map(|term| term.to_string()).collect();
+
+            writeln!(output, "Not in the expression: {}", excluded.join(", ")).unwrap();
+        }
+
+        if let Some(ice) = ice {
+            writeln!(output, "    {:<16} {:>14} {:>14} {:>14}", "Species", "Initial", "Change", "Equilibrium").unwrap();
+
+            for row in ice.rows.iter() {
+                writeln!(output, "    {:<16} {:>14.6} {:>+14.6} {:>14.6}", row.species.to_string(), row.initial, row.change, row.equilibrium).unwrap();
+            }
+        }
+
+        Ok(output)
+    }
+
+    // The half-reactions that make up a balanced redox reaction, its standard cell potential and Gibbs energy change, and
+    // the cell potential from the Nernst equation if any concentrations are given
+    pub fn cell_report(equation: &str, concentrations: &[&str], temperature: Option<&str>, options: &Options) -> Result<String, BalanceError> {
+
+        let mut notes = Vec::new();
+        let mut log = String::new();
+
+        let balanced = balance(equation, options, & mut notes, & mut log)?;
+
+        let result = cell::find(&balanced, &cell::half_reactions())
+            .ok_or_else(|| BalanceError::new(ErrorKind::Data, String::from("No pair of half-reactions in the table makes up this reaction. Write it as an ionic equation, i.e. Zn + Cu{2+} = Zn{2+} + Cu")))?;
+
+        let temperature = match temperature {
+            Some(value) => temperatures(&[value], None)?[0],
+            None => STANDARD_TEMPERATURE
+        };
+
+        //Without any concentrations or a temperature, every species is in its standard state
+        let nernst = if concentrations.is_empty() && temperature == STANDARD_TEMPERATURE {
+            None
+        }
+        else {
+            let amounts = concentrations.iter().map(|text| constant::parse_initial(text, &[Dimension::CONCENTRATION, Dimension::PRESSURE]))
+                .collect::<Result<Vec<(f64, Term)>, BalanceError>>()?;
+
+            let ln_quotient = constant::ln_quotient(&constant::expression(&balanced, Kind::Concentration), &amounts)?;
+
+            Some((temperature, result.nernst(ln_quotient, temperature)))
+        };
+
+        let mut output = String::new();
+
+        if options.format == Format::Json {
+            writeln!(output, "{}", json::cell(equation, &balanced, &notes, &result, nernst)).unwrap();
+
+            return Ok(output);
+        }
+
+        for note in notes.iter() {
+            writeln!(output, "{}", note).unwrap();
+        }
+
+        writeln!(output, "{}", balanced).unwrap();
+        writeln!(output, "Reduction (cathode):  {:<32} E = {:+.2} V", result.cathode.equation.to_string(), result.cathode.potential).unwrap();
+        writeln!(output, "Oxidation (anode):    {:<32} E = {:+.2} V", result.anode.equation.to_string(), result.anode.potential).unwrap();
+        writeln!(output, "{} transferred", electrons(result.electrons)).unwrap();
+        writeln!(output, "Standard cell potential: {:+.3} V", result.potential).unwrap();
+        writeln!(output, "Gibbs energy change: {:.2} kJ/mol", result.gibbs()).unwrap();
+
+        if let Some((temperature, potential)) = nernst {
+            writeln!(output, "Cell potential at {:.2} K: {:+.4} V", temperature, potential).unwrap();
+        }
+
+        Ok(output)
+    }
+
+    // The concentration or volume of a solution needed to reach the equivalence point of a titration
+    pub fn titrate_report(equation: &str, analyte: &str, titrant: &str, options: &Options) -> Result<String, BalanceError> {
+
+        let mut notes = Vec::new();
+        let mut log = String::new();
+
+        let balanced = balance(equation, options, & mut notes, & mut log)?;
+
+        let result = titration::solve(&balanced, &Solution::parse(analyte)?, &Solution::parse(titrant)?)?;
+
+        let mut output = String::new();
+
+        if options.format == Format::Json {
+            writeln!(output, "{}", json::titration(equation, &balanced, &result)).unwrap();
+
+            return Ok(output);
+        }
+
+        for note in notes.iter() {
+            writeln!(output, "{}", note).unwrap();
+        }
+
+        writeln!(output, "{}", balanced).unwrap();
+
+        for (name, sample) in [("Analyte", &result.analyte), ("Titrant", &result.titrant)].iter() {
+            writeln!(output, "    {:<8} {:<16} {:>10.4} M {:>10.2} mL {:>12.6} mol", name, sample.species.to_string(), sample.molarity,
+                     sample.litres * 1000.0, sample.moles).unwrap();
+        }
+
+        match result.unknown {
+            Unknown::AnalyteMolarity => writeln!(output, "Concentration of {}: {:.4} M", result.analyte.species, result.analyte.molarity).unwrap(),
+            Unknown::AnalyteVolume => writeln!(output, "Volume of {}: {:.2} mL", result.analyte.species, result.analyte.litres * 1000.0).unwrap(),
+            Unknown::TitrantMolarity => writeln!(output, "Concentration of {}: {:.4} M", result.titrant.species, result.titrant.molarity).unwrap(),
+            Unknown::TitrantVolume => writeln!(output, "Volume of {} to reach equivalence: {:.2} mL", result.titrant.species, result.titrant.litres * 1000.0).unwrap()
+        }
+
+        Ok(output)
+    }
+
+    // An amount of a substance converted to another unit, with any volumes at the conditions given. An amount without a
+    // formula is converted to another unit of the same kind, i.e. "1 atm" to mmHg
+    pub fn convert_report(amount: &str, unit: &str, conditions: Conditions, options: &Options) -> Result<String, BalanceError> {
+
+        let unit = Unit::parse(unit)?;
+
+        let (from, species, converted) = match Quantity::parse(amount) {
+            Ok(quantity) => {
+                let substance = [Dimension::AMOUNT, Dimension::MASS, Dimension::VOLUME];
+
+                if quantity.unit.dimension != unit.dimension && substance.contains(&quantity.unit.dimension) && substance.contains(&unit.dimension) {
+                    return Err(BalanceError::new(ErrorKind::Input, format!("Converting {} to {} needs a formula, i.e. \"{} H2O\"", quantity, unit, quantity)));
+                }
+
+                (quantity.to_string(), None, quantity.convert(&unit)?)
+            },
+            Err(_) => {
+                let mut given = Given::parse(amount)?;
+
+                given.conditions = conditions;
+
+                let converted = given.convert(&unit, &conditions)?;
+
+                (given.to_string(), Some(given.species), converted)
+            }
+        };
+
+        if options.format == Format::Json {
+            return Ok(format!("{}\n", json::converted(amount, species.as_ref(), &converted)));
+        }
+
+        //Exact results are written in full, anything else to four decimal places
+        let value = match converted.value {
+            Value::Exact(_) => converted.value.to_string(),
+            Value::Approximate(value) => format!("{:.4}", value)
+        };
+
+        Ok(format!("{} = {} {}\n", from, value, unit))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn options(format: Format) -> Options {
+            Options { verbose: false, explain: false, spellings: false, spectators: false, classify: false, format, export: None }
+        }
+
+        #[test]
+        fn reports_failed_lines() {
+            let outcome = balance_line(Some(4), "H2=O2", &options(Format::Text));
+
+            assert_eq!(outcome.output, "");
+            assert_eq!(outcome.errors, "Line 4: Cannot solve equation. Trivial solution detected, impossible chemical equation\n");
+            assert_eq!(outcome.code, ErrorKind::Impossible.exit_code());
+        }
+
+        #[test]
+        fn reports_failed_lines_as_json() {
+            let json = balance_line(Some(4), "H2=O2", &options(Format::Json));
+
+            assert!(json.output.starts_with("{\"line\":4,") && json.errors.is_empty());
+            assert_eq!(json.code, ErrorKind::Impossible.exit_code());
+        }
+
+        #[test]
+        fn reports_balanced_lines_as_latex() {
+            assert_eq!(balance_line(None, "H2+O2=H2O", &options(Format::Latex)).output, "\\ce{2H2 + O2 -> 2H2O}\n");
+        }
+
+        #[test]
+        fn reports_the_type_of_balanced_lines() {
+            let outcome = balance_line(None, "H2 + O2 = H2O", &Options { classify: true, ..options(Format::Text) });
+
+            assert_eq!(outcome.output, "2H2+O2=2H2O\nType: combustion, synthesis, redox\n");
+            assert_eq!(outcome.code, 0);
+        }
+
+        #[test]
+        fn reports_checked_lines() {
+            let balanced = check_line(None, "2H2+O2=2H2O", &options(Format::Text));
+
+            assert_eq!((balanced.output.as_str(), balanced.code), ("2H2+O2=2H2O is balanced\n", 0));
+
+            let unbalanced = check_line(Some(2), "H2+O2=H2O", &options(Format::Text));
+
+            assert_eq!(unbalanced.output, "Line 2: H2+O2=H2O is not balanced\n    O has 1 more in the reactants\n");
+            assert_eq!(unbalanced.code, balance_error::EXIT_UNBALANCED);
+
+            assert_eq!(check_line(None, "H2+=H2O", &options(Format::Text)).code, ErrorKind::Parse.exit_code());
+        }
+
+        #[test]
+        fn reports_each_change_of_oxidation_state() {
+            let disproportionation = redox_report("Cl2+NaOH=NaCl+NaClO3+H2O", &options(Format::Text)).unwrap();
+
+            assert!(disproportionation.contains("\nCl is both oxidised and reduced, loses 5 electrons and gains 5 electrons (0 \u{2192} -1 (reduced), 0 \u{2192} +5 (oxidised))\n"));
+
+            let chlorine = redox_report("HCl+MnO2=MnCl2+Cl2+H2O", &options(Format::Text)).unwrap();
+
+            assert!(chlorine.contains("\nCl is oxidised, loses 2 electrons (-1 \u{2192} 0 (oxidised))\nMn is reduced, gains 2 electrons (+4 \u{2192} +2 (reduced))\n"));
+            assert!(redox_report("HCl+MnO2=MnCl2+Cl2+H2O", &options(Format::Json)).unwrap().contains("\"transitions\":[{\"from\":-1,\"to\":0}]"));
+        }
+
+        #[test]
+        fn reports_molar_masses() {
+            let water = Term::parse(b"H2O").unwrap();
+
+            assert!(mass_report("H2O", &water, &options(Format::Text)).unwrap().starts_with("H2O = 18.015 g/mol\n    H   Hydrogen"));
+            assert_eq!(mass_report(" H2 O", &water, &options(Format::Json)).unwrap(),
+                       "{\"input\":\" H2 O\",\"notes\":[],\"formula\":\"H2O\",\"molar_mass\":18.015,\"elements\":{\"H\":2,\"O\":1}}\n");
+        }
+
+        #[test]
+        fn reads_temperatures() {
+            assert_eq!(temperatures(&["298.15"], Some("300:500:100")).unwrap(), vec![298.15, 300.0, 400.0, 500.0]);
+            assert!(temperatures(&["-5"], None).is_err());
+            assert!(temperatures(&[], Some("500:300:100")).is_err());
+            assert!(temperatures(&[], Some("1:100000:1")).is_err());
+        }
+
+        #[test]
+        fn reads_cell_concentrations_with_units() {
+            let text = options(Format::Text);
+
+            let daniell = |concentration: &str| cell_report("Zn+Cu{2+}=Zn{2+}+Cu", &[concentration], None, &text);
+
+            assert!(daniell("0.1 Cu{2+}").unwrap().ends_with("Cell potential at 298.15 K: +1.0704 V\n"));
+            assert_eq!(daniell("0.1 M Cu{2+}").unwrap(), daniell("0.1 Cu{2+}").unwrap());
+            assert_eq!(daniell("100 mM Cu{2+}").unwrap(), daniell("0.1 Cu{2+}").unwrap());
+            assert_eq!(daniell("25 mL Cu{2+}").unwrap_err().message, "'25 mL' is a volume, but a concentration or a pressure is needed");
+        }
+
+        #[test]
+        fn reports_conversions() {
+            let text = options(Format::Text);
+
+            assert_eq!(convert_report("1 atm", "mmHg", Conditions::STANDARD, &text).unwrap(), "1 atm = 760 mmHg\n");
+            assert_eq!(convert_report("18.015 g H2O", "mol", Conditions::STANDARD, &text).unwrap(), "18.015 g H2O = 1.0000 mol\n");
+            assert!(convert_report("1 mol", "g", Conditions::STANDARD, &text).is_err());
+        }
+
+        #[test]
+        fn writes_large_numbers_from_their_log() {
+            assert_eq!(exponential(1000.0_f64.ln()), "1.000e3");
+            assert_eq!(exponential(2.5e-300_f64.ln()), "2.500e-300");
+            assert_eq!(exponential(0.0), "1.000e0");
+        }
+    }
+}
@@ -0,0 +1,482 @@
+pub mod ions {
+
+    use crate::ast::equation::{ElementTable, Formula, Part, Term};
+
+    use num_rational::Ratio;
+
+    use num_traits::identities::Zero;
+
+    // Common cations, with the charge they usually have in their compounds
+    pub const CATIONS: [(&str, i32); 25] = [
+        ("Li", 1), ("Na", 1), ("K", 1), ("Rb", 1), ("Cs", 1), ("NH4", 1), ("Ag", 1),
+        ("Mg", 2), ("Ca", 2), ("Sr", 2), ("Ba", 2), ("Zn", 2), ("Fe", 2), ("Cu", 2), ("Ni", 2), ("Co", 2), ("Mn", 2),
+        ("Pb", 2), ("Sn", 2), ("Hg", 2), ("Cd", 2),
+        ("Al", 3), ("Cr", 3), ("Au", 3), ("Bi", 3)
+    ];
+
+    // Common anions, with their charge
+    pub const ANIONS: [(&str, i32); 24] = [
+        ("F", -1), ("Cl", -1), ("Br", -1), ("I", -1), ("OH", -1), ("CN", -1), ("NO3", -1), ("NO2", -1), ("ClO3", -1),
+        ("ClO4", -1), ("HCO3", -1), ("HSO4", -1), ("H2PO4", -1), ("CH3COO", -1), ("MnO4", -1),
+        ("O", -2), ("S", -2), ("SO4", -2), ("SO3", -2), ("CO3", -2), ("CrO4", -2), ("Cr2O7", -2), ("HPO4", -2),
+        ("PO4", -3)
+    ];
+
+    // An ionic compound of one of the common cations and one of the common anions, i.e. Ca3(PO4)2
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Salt {
+        pub cation: &'static str,
+        pub cation_charge: i32,
+        pub anion: &'static str,
+        pub anion_charge: i32
+    }
+
+    impl Salt {
+        // The formula of the salt with the fewest of each ion that balance the charges, with polyatomic ions in brackets
+        // if there is more than one, i.e. Al2(SO4)3 or (NH4)2SO4
+        pub fn formula(&self) -> String {
+            let (cations, anions) = counts(self.cation_charge, -self.anion_charge);
+
+            format!("{}{}", group(self.cation, cations), group(self.anion, anions))
+        }
+    }
+
+    //The number of each of two ions with the given charges that balance, i.e. 2 and 3 for Al{3+} and SO4{2-}
+    fn counts(first: i32, second: i32) -> (i32, i32) {
+        let divisor = (1..=first.min(second)).rev().find(|divisor| first % divisor == 0 && second % divisor == 0).unwrap_or(1);
+
+        (second / divisor, first / divisor)
+    }
+
+    //An ion written count times, i.e. Cl2, (NO3)2 or SO4
+    fn group(ion: &str, count: i32) -> String {
+        let polyatomic = ion.chars().filter(|ch| ch.is_ascii_uppercase()).count() > 1 || ion.ends_with(|ch: char| ch.is_ascii_digit());
+
+        match count {
+            1 => String::from(ion),
+            _ if polyatomic => format!("({}){}", ion, count),
+            _ => format!("{}{}", ion, count)
+        }
+    }
+
+    // An ion written with its charge, i.e. Cu{2+} or SO4{2-}
+    pub fn ion(symbol: &str, charge: i32) -> String {
+        let sign = if charge < 0 { '-' } else { '+' };
+
+        match charge.abs() {
+            1 => format!("{}{{{}}}", symbol, sign),
+            magnitude => format!("{}{{{}{}}}", symbol, magnitude, sign)
+        }
+    }
+
+    //Whether every element in first is in second the same number of times, multiplied by scale
+    fn same_counts(first: &ElementTable, second: &ElementTable, scale: Ratio<i32>) -> bool {
+        let nonzero = |table: &ElementTable| table.iter().filter(|(_, quantity)| *quantity != Ratio::zero()).count();
+
+        nonzero(first) == nonzero(second) && first.iter().all(|(element, quantity)| second.get(element) == Some(quantity * scale))
+    }
+
+    //The cation at the start of a formula, its number and the parts after it, i.e. Ca3(PO4)2 gives Ca, 3 and (PO4)2
+    fn cation(parts: &[Part]) -> Option<(&'static str, Ratio<i32>, &[Part])> {
+        let ammonium = Term::parse(b"NH4").ok()?.formula;
+
+        match parts {
+            [Part::Element(nitrogen, one), Part::Element(hydrogen, four), rest @ ..]
+                if nitrogen == "N" && hydrogen == "H" && *one == Ratio::from_integer(1) && *four == Ratio::from_integer(4) => {
+                Some(("NH4", Ratio::from_integer(1), rest))
+            },
+            [Part::Element(symbol, count), rest @ ..] => {
+                let found = CATIONS.iter().find(|(cation, _)| cation == symbol)?;
+
+                Some((found.0, *count, rest))
+            },
+            [Part::Group(_, inner, count), rest @ ..] => {
+                let inner = Formula { parts: inner.clone() };
+
+                if same_counts(&inner.elements(), &ammonium.elements(), Ratio::from_integer(1)) { Some(("NH4", *count, rest)) } else { None }
+            },
+            _ => None
+        }
+    }
+
+    // The cation and anion of a neutral ionic compound, if both are common ions and their charges balance, i.e. NaCl,
+    // CuSO4*5H2O, NH4NO3 or Ca3(PO4)2. The charge of the cation is found from the anion, so both FeCl2 and FeCl3 are
+    // salts of iron
+    pub fn salt(term: &Term) -> Option<Salt> {
+        if term.charge != Ratio::zero() {
+            return None;
+        }
+
+        let parts: Vec<Part> = term.formula.parts.iter().filter(|part| !matches!(part, Part::Hydrate(_, _))).cloned().collect();
+
+        let (cation, cation_count, rest) = cation(&parts)?;
+
+        //The cation's charge is found by dividing by its count, so there must be some of it
+        if cation_count <= Ratio::zero() {
+            return None;
+        }
+
+        let rest = Formula { parts: rest.to_vec() };
+        let rest = rest.elements();
+
+        for (anion, anion_charge) in ANIONS.iter() {
+            //Every anion in the table is a valid formula
+            let formula = Term::parse(anion.as_bytes()).unwrap().formula;
+            let elements = formula.elements();
+
+            let (symbol, quantity) = elements.iter().next().unwrap();
+
+            let anion_count = match rest.get(symbol) {
+                Some(found) => found / quantity,
+                None => continue
+            };
+
+            if !anion_count.is_integer() || anion_count <= Ratio::zero() || !same_counts(&elements, &rest, anion_count) {
+                continue;
+            }
+
+            let cation_charge = anion_count * Ratio::from_integer(-anion_charge) / cation_count;
+
+            if cation_charge.is_integer() && cation_charge >= Ratio::from_integer(1) && cation_charge <= Ratio::from_integer(3) {
+                return Some(Salt {
+                    cation,
+                    cation_charge: cation_charge.to_integer(),
+                    anion,
+                    anion_charge: *anion_charge
+                });
+            }
+        }
+
+        None
+    }
+
+    // Whether a salt dissolves in water, from the usual solubility rules. Group 1 and ammonium salts, nitrates,
+    // acetates, chlorates and perchlorates all dissolve. Halides dissolve except with silver, lead and mercury, sulfates
+    // except with barium, strontium, calcium and lead, and hydroxides only with barium and strontium. Other anions only
+    // dissolve with group 1 and ammonium
+    pub fn soluble(salt: &Salt) -> bool {
+        let alkali = ["Li", "Na", "K", "Rb", "Cs", "NH4"].contains(&salt.cation);
+
+        if alkali {
+            return true;
+        }
+
+        match salt.anion {
+            "NO3" | "NO2" | "CH3COO" | "ClO3" | "ClO4" | "HCO3" | "HSO4" | "H2PO4" | "MnO4" => true,
+            "Cl" | "Br" | "I" => !["Ag", "Pb", "Hg"].contains(&salt.cation),
+            "F" => !["Mg", "Ca", "Sr", "Ba", "Pb"].contains(&salt.cation),
+            "SO4" => !["Ba", "Sr", "Ca", "Pb"].contains(&salt.cation),
+            "OH" => ["Ba", "Sr"].contains(&salt.cation),
+            _ => false
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn parse(formula: &str) -> Option<Salt> {
+            salt(&Term::parse(formula.as_bytes()).unwrap())
+        }
+
+        #[test]
+        fn finds_salts() {
+            assert_eq!(parse("Ca3(PO4)2"), Some(Salt { cation: "Ca", cation_charge: 2, anion: "PO4", anion_charge: -3 }));
+            assert_eq!(parse("FeCl3").map(|salt| salt.cation_charge), Some(3));
+            assert_eq!(parse("(NH4)2SO4").map(|salt| salt.cation), Some("NH4"));
+            assert_eq!(parse("CuSO4*5H2O").map(|salt| salt.anion), Some("SO4"));
+            assert_eq!(parse("NaCl{+}"), None);
+            assert_eq!(parse("CH4"), None);
+            assert_eq!(parse("Na2Cl3"), None);
+        }
+
+        #[test]
+        fn ignores_a_cation_written_zero_times() {
+            let mut term = Term::parse(b"NaCl").unwrap();
+
+            term.formula.parts[0] = Part::Element(String::from("Na"), Ratio::zero());
+
+            assert_eq!(salt(&term), None);
+        }
+
+        #[test]
+        fn writes_salts_and_ions() {
+            assert_eq!(Salt { cation: "Al", cation_charge: 3, anion: "SO4", anion_charge: -2 }.formula(), "Al2(SO4)3");
+            assert_eq!(Salt { cation: "NH4", cation_charge: 1, anion: "Cl", anion_charge: -1 }.formula(), "NH4Cl");
+            assert_eq!(Salt { cation: "Mg", cation_charge: 2, anion: "O", anion_charge: -2 }.formula(), "MgO");
+            assert_eq!(ion("Cu", 2), "Cu{2+}");
+            assert_eq!(ion("NO3", -1), "NO3{-}");
+        }
+
+        #[test]
+        fn follows_the_solubility_rules() {
+            let soluble = |formula: &str| super::soluble(&parse(formula).unwrap());
+
+            assert!(soluble("NaCl") && soluble("AgNO3") && soluble("Ba(OH)2") && soluble("K2CO3"));
+            assert!(!soluble("AgCl") && !soluble("BaSO4") && !soluble("CaCO3") && !soluble("Fe(OH)3"));
+        }
+    }
+}
+
+pub mod reaction {
+
+    use crate::ast::equation::{Equation, Part, State, Term};
+    use crate::classify::ions;
+    use crate::redox::oxidation;
+
+    use num_rational::Ratio;
+
+    use num_traits::identities::Zero;
+
+    use std::fmt;
+
+    // A type of reaction. A reaction can be of several types, i.e. the combustion of carbon is also a synthesis and a
+    // redox reaction
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Class {
+        Combustion,
+        Synthesis,
+        Decomposition,
+        SingleDisplacement,
+        DoubleDisplacement,
+        Neutralisation,
+        Precipitation,
+        Redox
+    }
+
+    impl Class {
+        // The name of the type, i.e. single displacement
+        pub fn name(&self) -> &'static str {
+            match self {
+                Class::Combustion => "combustion",
+                Class::Synthesis => "synthesis",
+                Class::Decomposition => "decomposition",
+                Class::SingleDisplacement => "single displacement",
+                Class::DoubleDisplacement => "double displacement",
+                Class::Neutralisation => "acid-base neutralisation",
+                Class::Precipitation => "precipitation",
+                Class::Redox => "redox"
+            }
+        }
+    }
+
+    impl fmt::Display for Class {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.name())
+        }
+    }
+
+    //Every molecule and ion on one side of an equation, without electrons
+    fn species(terms: &[Term]) -> Vec<&Term> {
+        terms.iter().filter(|term| !term.formula.parts.is_empty()).collect()
+    }
+
+    //The symbol of an uncombined element, i.e. Zn, O2 or S8
+    fn element(term: &Term) -> Option<String> {
+        let elements = term.formula.elements();
+
+        if elements.len() == 1 && term.charge == Ratio::zero() {
+            elements.keys().next().map(String::from)
+        }
+        else {
+            None
+        }
+    }
+
+    // Whether a species is an acid, either a compound written with hydrogen first (HCl or H2SO4), a carboxylic acid
+    // (CH3COOH) or H{+} and H3O{+}
+    pub fn acid(term: &Term) -> bool {
+        let canonical = term.canonical();
+
+        if canonical == "H{+}" || canonical == "H3O{+}" {
+            return true;
+        }
+
+        let hydrogen_first = matches!(term.formula.parts.first(), Some(Part::Element(symbol, _)) if symbol == "H");
+
+        term.charge == Ratio::zero() && term.formula.elements().len() > 1 && canonical != "H2O" && canonical != "H2O2"
+            && (hydrogen_first || term.formula.to_string().contains("COOH"))
+    }
+
+    // Whether a species is a base, either a hydroxide without carbon (NaOH, Ca(OH)2 or OH{-}) or ammonia
+    pub fn base(term: &Term) -> bool {
+        let canonical = term.canonical();
+
+        if canonical == "HO{-}" || canonical == "H3N" {
+            return true;
+        }
+
+        let written = term.formula.to_string();
+
+        term.charge == Ratio::zero() && !term.formula.elements().contains("C") && (written.ends_with("OH") || written.contains("(OH)"))
+            && canonical != "H2O"
+    }
+
+    //Anions that an acid turns into a gas, with their charge and the gas given off, i.e. carbonates give CO2
+    const GAS_FORMING: [(&str, i32, &str); 4] = [("CO3", -2, "CO2"), ("HCO3", -1, "CO2"), ("SO3", -2, "SO2"), ("S", -2, "H2S")];
+
+    //The gas an acid gives off from a species, if it is a salt of a gas forming anion or the anion itself, i.e. CO2
+    //from Na2CO3 or CO3{2-}
+    fn gas_formed(term: &Term) -> Option<String> {
+        let canonical = term.canonical();
+        let salt = ions::salt(term);
+
+        GAS_FORMING.iter()
+            .find(|(anion, charge, _)| salt.map(|salt| salt.anion == *anion).unwrap_or(false)
+                || Term::parse(ions::ion(anion, *charge).as_bytes()).map(|ion| ion.canonical() == canonical).unwrap_or(false))
+            //Every gas in the table is a valid formula
+            .map(|(_, _, gas)| Term::parse(gas.as_bytes()).unwrap().canonical())
+    }
+
+    //Whether a species on the reactant side is in solution, either marked (aq), an ion, an acid or a soluble salt
+    fn dissolved(term: &Term) -> bool {
+        match term.state {
+            Some(State::Aqueous) => true,
+            Some(_) => false,
+            None => term.charge != Ratio::zero() || acid(term) || ions::salt(term).map(|salt| ions::soluble(&salt)).unwrap_or(false)
+        }
+    }
+
+    //Whether a product comes out of solution as a solid, either marked (s) or an insoluble salt
+    fn precipitate(term: &Term) -> bool {
+        match term.state {
+            Some(State::Solid) => true,
+            Some(_) => false,
+            None => ions::salt(term).map(|salt| !ions::soluble(&salt)).unwrap_or(false)
+        }
+    }
+
+    //Whether every product has something from each reactant, as when two compounds swap partners. An element in both
+    //reactants counts for each
+    fn swapped(reactants: &[&Term], products: &[&Term]) -> bool {
+        products.iter().all(|product| {
+            let elements = product.formula.elements();
+
+            reactants.iter().all(|reactant| reactant.formula.elements().keys().any(|element| elements.contains(element)))
+        })
+    }
+
+    // The types of a reaction, from the species on each side. An equation can be of several types or of none. The
+    // equation should be balanced, as the oxidation states are compared to find redox reactions
+    pub fn classify(equation: &Equation) -> Vec<Class> {
+        let reactants = species(&equation.reactants);
+        let products = species(&equation.products);
+
+        let mut result = Vec::new();
+
+        let oxygen = reactants.iter().any(|term| term.canonical() == "O2");
+
+        //A fuel containing carbon or hydrogen burns in oxygen to oxides, and any nitrogen is given off as N2
+        let fuel = reactants.len() == 2 && oxygen && reactants.iter()
+            .any(|term| term.canonical() != "O2" && (term.formula.elements().contains("C") || term.formula.elements().contains("H")));
+
+        let oxides = products.iter().all(|term| {
+            let elements = term.formula.elements();
+
+            term.charge == Ratio::zero() && ((elements.contains("O") && elements.len() == 2) || term.canonical() == "H2O" || term.canonical() == "N2")
+        });
+
+        if fuel && oxides && !products.is_empty() {
+            result.push(Class::Combustion);
+        }
+
+        if reactants.len() >= 2 && products.len() == 1 {
+            result.push(Class::Synthesis);
+        }
+
+        if reactants.len() == 1 && products.len() >= 2 {
+            result.push(Class::Decomposition);
+        }
+
+        let free = |terms: &[&Term]| -> Vec<String> { terms.iter().filter_map(|term| element(term)).collect() };
+
+        let (before, after) = (free(&reactants), free(&products));
+
+        let pairs = reactants.len() == 2 && products.len() == 2;
+
+        if pairs && before.len() == 1 && after.len() == 1 && before[0] != after[0] {
+            result.push(Class::SingleDisplacement);
+        }
+
+        let compounds = reactants.iter().chain(products.iter()).all(|term| term.formula.elements().len() > 1);
+
+        let (changes, _) = oxidation::changes(equation);
+
+        //An element that is only uncombined on one side goes to or from an oxidation state of zero, which also finds
+        //redox reactions whose oxidation states cannot all be found, i.e. Zn and CuSO4
+        let combined = |symbol: &String, terms: &[&Term]| terms.iter().any(|term| term.formula.elements().contains(symbol));
+
+        let freed = before.iter().any(|symbol| !after.contains(symbol) && combined(symbol, &products))
+            || after.iter().any(|symbol| !before.contains(symbol) && combined(symbol, &reactants));
+
+        let redox = freed || changes.iter().any(|change| change.lost != Ratio::zero() || change.gained != Ratio::zero());
+
+        let with_acid = reactants.iter().any(|term| acid(term));
+
+        //An acid and a carbonate, sulfite or sulfide swap partners, but the acid formed breaks down into a gas (and water
+        //for a carbonate or sulfite), so there are more than two products, i.e. Na2CO3 + 2HCl = 2NaCl + H2O + CO2
+        let evolved = with_acid && !redox
+            && reactants.iter().filter_map(|term| gas_formed(term)).any(|gas| products.iter().any(|term| term.canonical() == gas));
+
+        if (pairs && compounds && !redox && swapped(&reactants, &products)) || evolved {
+            result.push(Class::DoubleDisplacement);
+        }
+
+        let water = products.iter().any(|term| term.canonical() == "H2O");
+        let ammonia = reactants.iter().any(|term| term.canonical() == "H3N");
+
+        //A carbonate neutralises an acid as a base does when it gives water
+        if with_acid && (reactants.iter().any(|term| base(term)) || evolved) && (water || ammonia) && !redox {
+            result.push(Class::Neutralisation);
+        }
+
+        if reactants.iter().all(|term| dissolved(term)) && products.iter().any(|term| precipitate(term)) {
+            result.push(Class::Precipitation);
+        }
+
+        if redox {
+            result.push(Class::Redox);
+        }
+
+        result
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn types(equation: &str) -> Vec<Class> {
+            classify(&Equation::parse(equation.as_bytes()).unwrap())
+        }
+
+        #[test]
+        fn classifies_reactions() {
+            assert_eq!(types("CH4+2O2=CO2+2H2O"), vec![Class::Combustion, Class::Redox]);
+            assert_eq!(types("C+O2=CO2"), vec![Class::Combustion, Class::Synthesis, Class::Redox]);
+            assert_eq!(types("CaCO3=CaO+CO2"), vec![Class::Decomposition]);
+            assert_eq!(types("Zn+CuSO4=ZnSO4+Cu"), vec![Class::SingleDisplacement, Class::Redox]);
+            assert_eq!(types("AgNO3+NaCl=AgCl+NaNO3"), vec![Class::DoubleDisplacement, Class::Precipitation]);
+            assert_eq!(types("HCl+NaOH=NaCl+H2O"), vec![Class::DoubleDisplacement, Class::Neutralisation]);
+            assert_eq!(types("HCl+NH3=NH4Cl"), vec![Class::Synthesis, Class::Neutralisation]);
+        }
+
+        #[test]
+        fn classifies_acids_with_carbonates_as_double_displacement() {
+            assert_eq!(types("Na2CO3+2HCl=2NaCl+H2O+CO2"), vec![Class::DoubleDisplacement, Class::Neutralisation]);
+            assert_eq!(types("NaHCO3+HCl=NaCl+H2O+CO2"), vec![Class::DoubleDisplacement, Class::Neutralisation]);
+            assert_eq!(types("CO3{2-}+2H{+}=H2O+CO2"), vec![Class::DoubleDisplacement, Class::Neutralisation]);
+            assert_eq!(types("FeS+2HCl=FeCl2+H2S"), vec![Class::DoubleDisplacement]);
+            assert_eq!(types("CaCO3=CaO+CO2"), vec![Class::Decomposition]);
+        }
+
+        #[test]
+        fn recognises_acids_and_bases() {
+            let parse = |formula: &str| Term::parse(formula.as_bytes()).unwrap();
+
+            assert!(acid(&parse("H2SO4")) && acid(&parse("CH3COOH")) && acid(&parse("H3O{+}")));
+            assert!(!acid(&parse("H2O")) && !acid(&parse("NaCl")));
+            assert!(base(&parse("Ca(OH)2")) && base(&parse("NH3")) && base(&parse("OH{-}")));
+            assert!(!base(&parse("CH3OH")) && !base(&parse("H2O")));
+        }
+    }
+}
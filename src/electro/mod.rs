@@ -0,0 +1,229 @@
+pub mod cell {
+
+    use crate::ast::equation::Equation;
+    use crate::thermo::formation::GAS_CONSTANT;
+
+    use num_rational::Ratio;
+
+    use num_traits::identities::Zero;
+
+    // The Faraday constant, in C/mol
+    pub const FARADAY: f64 = 96485.33212;
+
+    //Standard reduction potentials in V at 298.15 K, as half-reactions with electrons written as a lone charge. Most
+    //oxidising first, which is also the order find tries cathodes in
+    const POTENTIALS: [(&str, f64); 48] = [
+        ("F2+2{-}=2F{-}", 2.87),
+        ("Co{3+}+{-}=Co{2+}", 1.82),
+        ("H2O2+2H{+}+2{-}=2H2O", 1.78),
+        ("Ce{4+}+{-}=Ce{3+}", 1.72),
+        ("PbO2+4H{+}+SO4{2-}+2{-}=PbSO4+2H2O", 1.69),
+        ("MnO4{-}+8H{+}+5{-}=Mn{2+}+4H2O", 1.51),
+        ("Au{3+}+3{-}=Au", 1.50),
+        ("PbO2+4H{+}+2{-}=Pb{2+}+2H2O", 1.46),
+        ("Cl2+2{-}=2Cl{-}", 1.36),
+        ("Cr2O7{2-}+14H{+}+6{-}=2Cr{3+}+7H2O", 1.33),
+        ("O2+4H{+}+4{-}=2H2O", 1.23),
+        ("MnO2+4H{+}+2{-}=Mn{2+}+2H2O", 1.23),
+        ("IO3{-}+6H{+}+5{-}=0.5I2+3H2O", 1.20),
+        ("Br2+2{-}=2Br{-}", 1.07),
+        ("NO3{-}+4H{+}+3{-}=NO+2H2O", 0.96),
+        ("Hg{2+}+2{-}=Hg", 0.85),
+        ("NO3{-}+2H{+}+{-}=NO2+H2O", 0.80),
+        ("Ag{+}+{-}=Ag", 0.80),
+        ("Fe{3+}+{-}=Fe{2+}", 0.77),
+        ("O2+2H{+}+2{-}=H2O2", 0.70),
+        ("I2+2{-}=2I{-}", 0.54),
+        ("Cu{+}+{-}=Cu", 0.52),
+        ("O2+2H2O+4{-}=4OH{-}", 0.40),
+        ("Cu{2+}+2{-}=Cu", 0.34),
+        ("Hg2Cl2+2{-}=2Hg+2Cl{-}", 0.27),
+        ("AgCl+{-}=Ag+Cl{-}", 0.22),
+        ("SO4{2-}+4H{+}+2{-}=H2SO3+H2O", 0.17),
+        ("Cu{2+}+{-}=Cu{+}", 0.15),
+        ("Sn{4+}+2{-}=Sn{2+}", 0.15),
+        ("2H{+}+2{-}=H2", 0.00),
+        ("Fe{3+}+3{-}=Fe", -0.04),
+        ("Pb{2+}+2{-}=Pb", -0.13),
+        ("Sn{2+}+2{-}=Sn", -0.14),
+        ("Ni{2+}+2{-}=Ni", -0.26),
+        ("Co{2+}+2{-}=Co", -0.28),
+        ("PbSO4+2{-}=Pb+SO4{2-}", -0.36),
+        ("Cd{2+}+2{-}=Cd", -0.40),
+        ("Fe{2+}+2{-}=Fe", -0.44),
+        ("Cr{3+}+3{-}=Cr", -0.74),
+        ("Zn{2+}+2{-}=Zn", -0.76),
+        ("2H2O+2{-}=H2+2OH{-}", -0.83),
+        ("Mn{2+}+2{-}=Mn", -1.18),
+        ("Al{3+}+3{-}=Al", -1.66),
+        ("Mg{2+}+2{-}=Mg", -2.37),
+        ("Na{+}+{-}=Na", -2.71),
+        ("Ca{2+}+2{-}=Ca", -2.87),
+        ("K{+}+{-}=K", -2.93),
+        ("Li{+}+{-}=Li", -3.04)
+    ];
+
+    // A reduction half-reaction and its standard reduction potential
+    #[derive(Clone)]
+    pub struct HalfReaction {
+        pub equation: Equation,
+        pub potential: f64, // In V
+        pub electrons: Ratio<i32> // The number of electrons gained, per half-reaction as written
+    }
+
+    // The half-reactions embedded in chembal, most oxidising first
+    pub fn half_reactions() -> Vec<HalfReaction> {
+        POTENTIALS.iter().map(|(equation, potential)| {
+            //Every embedded half-reaction is valid
+            let equation = Equation::parse(equation.as_bytes()).unwrap();
+
+            let electrons = equation.reactants.iter()
+                .filter(|term| term.formula.parts.is_empty())
+                .fold(Ratio::zero(), |sum, term| sum - term.coefficient * term.charge);
+
+            HalfReaction {
+                equation,
+                potential: *potential,
+                electrons
+            }
+        }).collect()
+    }
+
+    // A galvanic cell made of two half-reactions, for a redox reaction as written
+    pub struct Cell {
+        pub cathode: HalfReaction, // The half-reaction where reduction takes place
+        pub anode: HalfReaction, // The half-reaction, written as a reduction, that takes place in reverse as oxidation
+        pub electrons: Ratio<i32>, // The number of electrons transferred, per reaction as written
+        pub potential: f64 // The standard cell potential, in V
+    }
+
+    impl Cell {
+        // The standard Gibbs energy change in kJ/mol, from dG = -nFE
+        pub fn gibbs(&self) -> f64 {
+            -(*self.electrons.numer() as f64 / *self.electrons.denom() as f64) * FARADAY * self.potential / 1000.0
+        }
+
+        // The cell potential in V from the Nernst equation, E = E0 - (RT/nF) ln Q, given the natural log of the reaction
+        // quotient and the temperature in K
+        pub fn nernst(&self, ln_quotient: f64, temperature: f64) -> f64 {
+            let electrons = *self.electrons.numer() as f64 / *self.electrons.denom() as f64;
+
+            self.potential - GAS_CONSTANT * temperature / (electrons * FARADAY) * ln_quotient
+        }
+    }
+
+    //The net amount of each species, by canonical formula, with reactants negative and products positive. States are
+    //ignored, and each amount is multiplied by scale
+    fn net(equation: &Equation, scale: Ratio<i32>, result: & mut Vec<(String, Ratio<i32>)>) {
+        let sides = equation.reactants.iter().map(|term| (term, -scale)).chain(equation.products.iter().map(|term| (term, scale)));
+
+        for (term, sign) in sides {
+            let canonical = term.canonical();
+
+            match result.iter_mut().find(|(found, _)| *found == canonical) {
+                Some((_, amount)) => *amount += term.coefficient * sign,
+                None => result.push((canonical, term.coefficient * sign))
+            }
+        }
+
+        result.retain(|(_, amount)| *amount != Ratio::zero());
+    }
+
+    //The positive multiple of combination that equals reaction, if there is one
+    fn multiple(reaction: &[(String, Ratio<i32>)], combination: &[(String, Ratio<i32>)]) -> Option<Ratio<i32>> {
+        if reaction.len() != combination.len() || reaction.is_empty() {
+            return None;
+        }
+
+        let (species, amount) = &reaction[0];
+
+        let scale = *amount / combination.iter().find(|(found, _)| found == species)?.1;
+
+        if scale <= Ratio::zero() {
+            return None;
+        }
+
+        let matches = reaction.iter().all(|(species, amount)| {
+            combination.iter().any(|(found, other)| found == species && *other * scale == *amount)
+        });
+
+        if matches { Some(scale) } else { None }
+    }
+
+    // The pair of half-reactions that make up a balanced redox reaction, if there is one in the table. The reaction must
+    // be written with the species of the half-reactions, i.e. as an ionic equation with H{+} in acidic solution
+    pub fn find(equation: &Equation, half_reactions: &[HalfReaction]) -> Option<Cell> {
+        let mut reaction = Vec::new();
+
+        net(equation, Ratio::from_integer(1), & mut reaction);
+
+        for cathode in half_reactions {
+            for anode in half_reactions {
+                if std::ptr::eq(cathode, anode) {
+                    continue;
+                }
+
+                //Each half-reaction for one electron, so the electrons cancel when the anode is reversed
+                let mut combination = Vec::new();
+
+                net(&cathode.equation, Ratio::from_integer(1) / cathode.electrons, & mut combination);
+                net(&anode.equation, -Ratio::from_integer(1) / anode.electrons, & mut combination);
+
+                if let Some(electrons) = multiple(&reaction, &combination) {
+                    return Some(Cell {
+                        cathode: cathode.clone(),
+                        anode: anode.clone(),
+                        electrons,
+                        potential: cathode.potential - anode.potential
+                    });
+                }
+            }
+        }
+
+        None
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn cell(equation: &str) -> Option<Cell> {
+            find(&Equation::parse(equation.as_bytes()).unwrap(), &half_reactions())
+        }
+
+        #[test]
+        fn half_reactions_are_most_oxidising_first() {
+            let half_reactions = half_reactions();
+
+            assert!(half_reactions.windows(2).all(|pair| pair[0].potential >= pair[1].potential));
+            assert!(half_reactions.iter().all(|half_reaction| half_reaction.equation.imbalance().unwrap().is_empty()));
+            assert_eq!(half_reactions[0].electrons, Ratio::from_integer(2));
+        }
+
+        #[test]
+        fn finds_the_cell_for_a_reaction() {
+            let daniell = cell("Zn+Cu{2+}=Zn{2+}+Cu").unwrap();
+
+            assert_eq!(daniell.cathode.equation.to_string(), "Cu{2+}+2{-}=Cu");
+            assert_eq!(daniell.anode.equation.to_string(), "Zn{2+}+2{-}=Zn");
+            assert_eq!(daniell.electrons, Ratio::from_integer(2));
+            assert!((daniell.potential - 1.10).abs() < 1e-9);
+            assert!((daniell.gibbs() + 212.27).abs() < 0.01);
+
+            let silver = cell("Cu+2Ag{+}=Cu{2+}+2Ag").unwrap();
+
+            assert!((silver.potential - 0.46).abs() < 1e-9);
+            assert_eq!(silver.electrons, Ratio::from_integer(2));
+
+            assert!(cell("H2+O2=H2O2+NaCl").is_none());
+        }
+
+        #[test]
+        fn applies_the_nernst_equation() {
+            let daniell = cell("Zn+Cu{2+}=Zn{2+}+Cu").unwrap();
+
+            assert!((daniell.nernst(0.0, 298.15) - 1.10).abs() < 1e-9);
+            assert!((daniell.nernst(10.0_f64.ln(), 298.15) - (1.10 - 0.02958)).abs() < 1e-4);
+        }
+    }
+}
@@ -0,0 +1,370 @@
+pub mod periodic_table {
+
+    // A chemical element and its standard atomic weight in g/mol. Elements without a stable isotope use the mass number
+    // of their longest lived isotope
+    pub struct Element {
+        pub symbol: &'static str,
+        pub name: &'static str,
+        pub mass: f64
+    }
+
+    pub const ELEMENTS: [Element; 118] = [
+        Element { symbol: "H", name: "Hydrogen", mass: 1.008 },
+        Element { symbol: "He", name: "Helium", mass: 4.0026 },
+        Element { symbol: "Li", name: "Lithium", mass: 6.94 },
+        Element { symbol: "Be", name: "Beryllium", mass: 9.0122 },
+        Element { symbol: "B", name: "Boron", mass: 10.81 },
+        Element { symbol: "C", name: "Carbon", mass: 12.011 },
+        Element { symbol: "N", name: "Nitrogen", mass: 14.007 },
+        Element { symbol: "O", name: "Oxygen", mass: 15.999 },
+        Element { symbol: "F", name: "Fluorine", mass: 18.998 },
+        Element { symbol: "Ne", name: "Neon", mass: 20.180 },
+        Element { symbol: "Na", name: "Sodium", mass: 22.990 },
+        Element { symbol: "Mg", name: "Magnesium", mass: 24.305 },
+        Element { symbol: "Al", name: "Aluminium", mass: 26.982 },
+        Element { symbol: "Si", name: "Silicon", mass: 28.085 },
+        Element { symbol: "P", name: "Phosphorus", mass: 30.974 },
+        Element { symbol: "S", name: "Sulfur", mass: 32.06 },
+        Element { symbol: "Cl", name: "Chlorine", mass: 35.45 },
+        Element { symbol: "Ar", name: "Argon", mass: 39.948 },
+        Element { symbol: "K", name: "Potassium", mass: 39.098 },
+        Element { symbol: "Ca", name: "Calcium", mass: 40.078 },
+        Element { symbol: "Sc", name: "Scandium", mass: 44.956 },
+        Element { symbol: "Ti", name: "Titanium", mass: 47.867 },
+        Element { symbol: "V", name: "Vanadium", mass: 50.942 },
+        Element { symbol: "Cr", name: "Chromium", mass: 51.996 },
+        Element { symbol: "Mn", name: "Manganese", mass: 54.938 },
+        Element { symbol: "Fe", name: "Iron", mass: 55.845 },
+        Element { symbol: "Co", name: "Cobalt", mass: 58.933 },
+        Element { symbol: "Ni", name: "Nickel", mass: 58.693 },
+        Element { symbol: "Cu", name: "Copper", mass: 63.546 },
+        Element { symbol: "Zn", name: "Zinc", mass: 65.38 },
+        Element { symbol: "Ga", name: "Gallium", mass: 69.723 },
+        Element { symbol: "Ge", name: "Germanium", mass: 72.630 },
+        Element { symbol: "As", name: "Arsenic", mass: 74.922 },
+        Element { symbol: "Se", name: "Selenium", mass: 78.971 },
+        Element { symbol: "Br", name: "Bromine", mass: 79.904 },
+        Element { symbol: "Kr", name: "Krypton", mass: 83.798 },
+        Element { symbol: "Rb", name: "Rubidium", mass: 85.468 },
+        Element { symbol: "Sr", name: "Strontium", mass: 87.62 },
+        Element { symbol: "Y", name: "Yttrium", mass: 88.906 },
+        Element { symbol: "Zr", name: "Zirconium", mass: 91.224 },
+        Element { symbol: "Nb", name: "Niobium", mass: 92.906 },
+        Element { symbol: "Mo", name: "Molybdenum", mass: 95.95 },
+        Element { symbol: "Tc", name: "Technetium", mass: 98.0 },
+        Element { symbol: "Ru", name: "Ruthenium", mass: 101.07 },
+        Element { symbol: "Rh", name: "Rhodium", mass: 102.91 },
+        Element { symbol: "Pd", name: "Palladium", mass: 106.42 },
+        Element { symbol: "Ag", name: "Silver", mass: 107.87 },
+        Element { symbol: "Cd", name: "Cadmium", mass: 112.41 },
+        Element { symbol: "In", name: "Indium", mass: 114.82 },
+        Element { symbol: "Sn", name: "Tin", mass: 118.71 },
+        Element { symbol: "Sb", name: "Antimony", mass: 121.76 },
+        Element { symbol: "Te", name: "Tellurium", mass: 127.60 },
+        Element { symbol: "I", name: "Iodine", mass: 126.90 },
+        Element { symbol: "Xe", name: "Xenon", mass: 131.29 },
+        Element { symbol: "Cs", name: "Caesium", mass: 132.91 },
+        Element { symbol: "Ba", name: "Barium", mass: 137.33 },
+        Element { symbol: "La", name: "Lanthanum", mass: 138.91 },
+        Element { symbol: "Ce", name: "Cerium", mass: 140.12 },
+        Element { symbol: "Pr", name: "Praseodymium", mass: 140.91 },
+        Element { symbol: "Nd", name: "Neodymium", mass: 144.24 },
+        Element { symbol: "Pm", name: "Promethium", mass: 145.0 },
+        Element { symbol: "Sm", name: "Samarium", mass: 150.36 },
+        Element { symbol: "Eu", name: "Europium", mass: 151.96 },
+        Element { symbol: "Gd", name: "Gadolinium", mass: 157.25 },
+        Element { symbol: "Tb", name: "Terbium", mass: 158.93 },
+        Element { symbol: "Dy", name: "Dysprosium", mass: 162.50 },
+        Element { symbol: "Ho", name: "Holmium", mass: 164.93 },
+        Element { symbol: "Er", name: "Erbium", mass: 167.26 },
+        Element { symbol: "Tm", name: "Thulium", mass: 168.93 },
+        Element { symbol: "Yb", name: "Ytterbium", mass: 173.05 },
+        Element { symbol: "Lu", name: "Lutetium", mass: 174.97 },
+        Element { symbol: "Hf", name: "Hafnium", mass: 178.49 },
+        Element { symbol: "Ta", name: "Tantalum", mass: 180.95 },
+        Element { symbol: "W", name: "Tungsten", mass: 183.84 },
+        Element { symbol: "Re", name: "Rhenium", mass: 186.21 },
+        Element { symbol: "Os", name: "Osmium", mass: 190.23 },
+        Element { symbol: "Ir", name: "Iridium", mass: 192.22 },
+        Element { symbol: "Pt", name: "Platinum", mass: 195.08 },
+        Element { symbol: "Au", name: "Gold", mass: 196.97 },
+        Element { symbol: "Hg", name: "Mercury", mass: 200.59 },
+        Element { symbol: "Tl", name: "Thallium", mass: 204.38 },
+        Element { symbol: "Pb", name: "Lead", mass: 207.2 },
+        Element { symbol: "Bi", name: "Bismuth", mass: 208.98 },
+        Element { symbol: "Po", name: "Polonium", mass: 209.0 },
+        Element { symbol: "At", name: "Astatine", mass: 210.0 },
+        Element { symbol: "Rn", name: "Radon", mass: 222.0 },
+        Element { symbol: "Fr", name: "Francium", mass: 223.0 },
+        Element { symbol: "Ra", name: "Radium", mass: 226.0 },
+        Element { symbol: "Ac", name: "Actinium", mass: 227.0 },
+        Element { symbol: "Th", name: "Thorium", mass: 232.04 },
+        Element { symbol: "Pa", name: "Protactinium", mass: 231.04 },
+        Element { symbol: "U", name: "Uranium", mass: 238.03 },
+        Element { symbol: "Np", name: "Neptunium", mass: 237.0 },
+        Element { symbol: "Pu", name: "Plutonium", mass: 244.0 },
+        Element { symbol: "Am", name: "Americium", mass: 243.0 },
+        Element { symbol: "Cm", name: "Curium", mass: 247.0 },
+        Element { symbol: "Bk", name: "Berkelium", mass: 247.0 },
+        Element { symbol: "Cf", name: "Californium", mass: 251.0 },
+        Element { symbol: "Es", name: "Einsteinium", mass: 252.0 },
+        Element { symbol: "Fm", name: "Fermium", mass: 257.0 },
+        Element { symbol: "Md", name: "Mendelevium", mass: 258.0 },
+        Element { symbol: "No", name: "Nobelium", mass: 259.0 },
+        Element { symbol: "Lr", name: "Lawrencium", mass: 266.0 },
+        Element { symbol: "Rf", name: "Rutherfordium", mass: 267.0 },
+        Element { symbol: "Db", name: "Dubnium", mass: 268.0 },
+        Element { symbol: "Sg", name: "Seaborgium", mass: 269.0 },
+        Element { symbol: "Bh", name: "Bohrium", mass: 270.0 },
+        Element { symbol: "Hs", name: "Hassium", mass: 277.0 },
+        Element { symbol: "Mt", name: "Meitnerium", mass: 278.0 },
+        Element { symbol: "Ds", name: "Darmstadtium", mass: 281.0 },
+        Element { symbol: "Rg", name: "Roentgenium", mass: 282.0 },
+        Element { symbol: "Cn", name: "Copernicium", mass: 285.0 },
+        Element { symbol: "Nh", name: "Nihonium", mass: 286.0 },
+        Element { symbol: "Fl", name: "Flerovium", mass: 289.0 },
+        Element { symbol: "Mc", name: "Moscovium", mass: 290.0 },
+        Element { symbol: "Lv", name: "Livermorium", mass: 293.0 },
+        Element { symbol: "Ts", name: "Tennessine", mass: 294.0 },
+        Element { symbol: "Og", name: "Oganesson", mass: 294.0 },
+    ];
+
+    // Find an element by its symbol, i.e. Fe
+    pub fn lookup(symbol: &str) -> Option<&'static Element> {
+        ELEMENTS.iter().find(|element| element.symbol == symbol)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn finds_elements_by_symbol() {
+            assert_eq!(lookup("Fe").map(|element| element.name), Some("Iron"));
+            assert_eq!(lookup("Og").map(|element| element.mass), Some(294.0));
+            assert!(lookup("fe").is_none());
+            assert!(lookup("Xx").is_none());
+        }
+    }
+}
+
+pub mod abbreviations {
+
+    use crate::ast::equation::Term;
+    use crate::elements::periodic_table;
+    use crate::error::balance_error::{BalanceError, ErrorKind};
+
+    use num_rational::Ratio;
+
+    use num_traits::identities::Zero;
+
+    use std::fs;
+    use std::sync::RwLock;
+
+    // The elements an abbreviation stands for, with the number of atoms of each
+    pub type Expansion = Vec<(&'static str, i32)>;
+
+    //Common groups in condensed structural formulas, i.e. EtOH or Bu4N{+}. None of them may be the symbol of an element
+    const BUILT_IN: [(&str, &[(&str, i32)]); 7] = [
+        ("Me", &[("C", 1), ("H", 3)]),
+        ("Et", &[("C", 2), ("H", 5)]),
+        ("Bu", &[("C", 4), ("H", 9)]),
+        ("Ph", &[("C", 6), ("H", 5)]),
+        ("Bn", &[("C", 7), ("H", 7)]),
+        ("Bz", &[("C", 7), ("H", 5), ("O", 1)]),
+        ("Cy", &[("C", 6), ("H", 11)])
+    ];
+
+    //Abbreviations registered by the user, which take the place of any built in abbreviation with the same symbol
+    static REGISTERED: RwLock<Vec<(String, Expansion)>> = RwLock::new(Vec::new());
+
+    // The elements an abbreviation stands for, or None if the symbol is not an abbreviation
+    pub fn expand(symbol: &str) -> Option<Expansion> {
+        if let Some((_, expansion)) = REGISTERED.read().unwrap().iter().find(|(registered, _)| registered == symbol) {
+            return Some(expansion.clone());
+        }
+
+        BUILT_IN.iter().find(|(abbreviation, _)| *abbreviation == symbol).map(|(_, expansion)| expansion.to_vec())
+    }
+
+    // Register an abbreviation for a group of atoms, i.e. Tol for CH3C6H4. The symbol must be a capital letter followed
+    // by lower case letters, so it is read like an element, but cannot be an element itself. The formula may use other
+    // abbreviations
+    pub fn register(symbol: &str, formula: &str) -> Result<(), BalanceError> {
+        let expansion = expansion(symbol, formula, &[])?;
+
+        let mut registered = REGISTERED.write().unwrap();
+
+        registered.retain(|(existing, _)| existing != symbol);
+        registered.push((String::from(symbol), expansion));
+
+        Ok(())
+    }
+
+    //The elements a new abbreviation stands for. Abbreviations in pending, which are not registered yet, may be used in
+    //the formula as well as registered and built in ones
+    fn expansion(symbol: &str, formula: &str, pending: &[(String, Expansion)]) -> Result<Expansion, BalanceError> {
+        let valid = symbol.starts_with(|ch: char| ch.is_ascii_uppercase()) && symbol.chars().skip(1).all(|ch| ch.is_ascii_lowercase());
+
+        if !valid {
+            return Err(BalanceError::new(ErrorKind::Input, format!("'{}' cannot be an abbreviation, it must be a capital letter followed by lower case letters, i.e. Tol", symbol)));
+        }
+
+        if periodic_table::lookup(symbol).is_some() {
+            return Err(BalanceError::new(ErrorKind::Input, format!("'{}' cannot be an abbreviation, it is already an element", symbol)));
+        }
+
+        let term = Term::parse(formula.as_bytes())?;
+
+        if term.charge != Ratio::zero() || term.formula.parts.is_empty() {
+            return Err(BalanceError::new(ErrorKind::Input, format!("'{}' must be a formula without a charge, i.e. CH3C6H4", formula)));
+        }
+
+        let mut expansion = Expansion::new();
+
+        for (element, quantity) in term.formula.elements() {
+            let count = quantity * term.coefficient;
+
+            if !count.is_integer() {
+                return Err(BalanceError::new(ErrorKind::Input, format!("'{}' must have a whole number of each element", formula)));
+            }
+
+            let atoms = match periodic_table::lookup(element) {
+                Some(found) => vec![(found.symbol, 1)],
+                None => pending.iter().find(|(abbreviation, _)| abbreviation == element).map(|(_, atoms)| atoms.clone())
+                    .ok_or_else(|| BalanceError::new(ErrorKind::Input, format!("Unknown element '{}' in the abbreviation {}", element, symbol)))?
+            };
+
+            let too_many = || BalanceError::new(ErrorKind::Input, format!("'{}' has too many atoms to count", formula));
+
+            for (atom, number) in atoms {
+                let number = number.checked_mul(count.to_integer()).ok_or_else(too_many)?;
+
+                match expansion.iter_mut().find(|(existing, _)| *existing == atom) {
+                    Some((_, total)) => *total = total.checked_add(number).ok_or_else(too_many)?,
+                    None => expansion.push((atom, number))
+                }
+            }
+        }
+
+        Ok(expansion)
+    }
+
+    // Register every abbreviation in a file, one per line as the symbol then the formula, i.e. Tol CH3C6H4. Blank lines
+    // and lines starting with # are ignored, and a line may use abbreviations from the lines above it. The whole file is
+    // read before anything is registered, so if any line is invalid none of the file is registered
+    pub fn load(path: &str) -> Result<(), BalanceError> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| BalanceError::new(ErrorKind::Input, format!("Could not read abbreviations from '{}' ({})", path, e)))?;
+
+        let loaded = read_lines(path, &text)?;
+
+        let mut registered = REGISTERED.write().unwrap();
+
+        for (symbol, expansion) in loaded {
+            registered.retain(|(existing, _)| *existing != symbol);
+            registered.push((symbol, expansion));
+        }
+
+        Ok(())
+    }
+
+    //The abbreviation on each line of an abbreviations file, stopping at the first invalid line
+    fn read_lines(path: &str, text: &str) -> Result<Vec<(String, Expansion)>, BalanceError> {
+        let mut loaded: Vec<(String, Expansion)> = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+
+            if fields.len() != 2 {
+                return Err(BalanceError::new(ErrorKind::Input, format!("Line {} of '{}' must be an abbreviation and its formula, i.e. Tol CH3C6H4", index + 1, path)));
+            }
+
+            let expansion = expansion(fields[0], fields[1], &loaded)
+                .map_err(|e| BalanceError::new(ErrorKind::Input, format!("Line {} of '{}' is not a valid abbreviation ({})", index + 1, path, e)))?;
+
+            //A later line for the same symbol takes its place, as it would when registered
+            loaded.retain(|(existing, _)| existing != fields[0]);
+            loaded.push((String::from(fields[0]), expansion));
+        }
+
+        Ok(loaded)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        use std::env;
+
+        #[test]
+        fn built_in_abbreviations_are_not_elements() {
+            for (symbol, _) in BUILT_IN.iter() {
+                assert!(periodic_table::lookup(symbol).is_none(), "{}", symbol);
+            }
+
+            assert_eq!(expand("Et"), Some(vec![("C", 2), ("H", 5)]));
+            assert_eq!(expand("Ac"), None);
+        }
+
+        #[test]
+        fn registers_abbreviations() {
+            register("Tol", "CH3C6H4").unwrap();
+            register("Dme", "2MeO").unwrap();
+            register("Acl", "CH3CO").unwrap();
+
+            assert_eq!(expand("Tol"), Some(vec![("C", 7), ("H", 7)]));
+            assert_eq!(expand("Acl"), Some(vec![("C", 2), ("H", 3), ("O", 1)]));
+            assert_eq!(expand("Dme"), Some(vec![("C", 2), ("H", 6), ("O", 2)]));
+        }
+
+        #[test]
+        fn rejects_invalid_abbreviations() {
+            assert!(register("tol", "CH3C6H4").is_err());
+            assert!(register("Ac", "CH3CO").is_err());
+            assert!(register("Co", "CO").is_err());
+            assert!(register("Hx", "1/2H2").is_err());
+            assert!(register("Hx", "H{+}").is_err());
+            assert!(register("Hx", "Xx2").is_err());
+            assert!(register("Hx", "Ph1000000000").is_err());
+            assert_eq!(expand("Hx"), None);
+        }
+
+        #[test]
+        fn loads_abbreviations_used_later_in_the_same_file() {
+            let path = env::temp_dir().join(format!("chembal-abbreviations-valid-{}", std::process::id()));
+
+            fs::write(&path, "# Groups\nXyl CH3C6H3CH3\nDxy Xyl2O\n").unwrap();
+
+            let result = load(path.to_str().unwrap());
+
+            fs::remove_file(&path).unwrap();
+
+            result.unwrap();
+            assert_eq!(expand("Xyl"), Some(vec![("C", 8), ("H", 9)]));
+            assert_eq!(expand("Dxy"), Some(vec![("C", 16), ("H", 18), ("O", 1)]));
+        }
+
+        #[test]
+        fn registers_nothing_from_an_invalid_file() {
+            let path = env::temp_dir().join(format!("chembal-abbreviations-{}", std::process::id()));
+
+            fs::write(&path, "# Groups\nNpr CH2CH2CH3\nIpr CH(CH3)2\nPh2 C12H10\n").unwrap();
+
+            let result = load(path.to_str().unwrap());
+
+            fs::remove_file(&path).unwrap();
+
+            assert!(result.is_err());
+            assert_eq!(expand("Npr"), None);
+            assert_eq!(expand("Ipr"), None);
+            assert_eq!(expand("Ph"), Some(vec![("C", 6), ("H", 5)]));
+        }
+    }
+}
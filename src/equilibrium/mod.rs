@@ -0,0 +1,421 @@
+pub mod constant {
+
+    use crate::ast::equation::{format_quantity, Equation, State, Term};
+    use crate::error::balance_error::{BalanceError, ErrorKind};
+    use crate::units::quantity::{Dimension, Quantity, Value};
+
+    use num_rational::Ratio;
+
+    use num_traits::identities::One;
+
+    use std::fmt;
+
+    // Whether an equilibrium constant is written with concentrations or partial pressures
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Kind {
+        Concentration, // Kc, with concentrations in mol/L
+        Pressure // Kp, with partial pressures
+    }
+
+    // A species raised to its coefficient in an equilibrium expression
+    pub struct Factor {
+        pub species: Term, // The species without its coefficient
+        pub power: Ratio<i32>
+    }
+
+    // The equilibrium constant of a balanced equation, as products over reactants. Excluded species (pure solids and
+    // liquids, and for Kp anything that is not a gas) do not appear in it
+    pub struct Expression {
+        pub kind: Kind,
+        pub numerator: Vec<Factor>,
+        pub denominator: Vec<Factor>,
+        pub excluded: Vec<Term>
+    }
+
+    //Whether a species takes part in the expression. Species without a state are assumed to take part
+    fn included(term: &Term, kind: Kind) -> bool {
+        !matches!((term.state, kind), (Some(State::Solid), _) | (Some(State::Liquid), _) | (Some(State::Aqueous), Kind::Pressure))
+    }
+
+    //The factors for one side of an equation, adding any excluded species to excluded
+    fn factors(terms: &[Term], kind: Kind, excluded: & mut Vec<Term>) -> Vec<Factor> {
+        let mut result = Vec::with_capacity(terms.len());
+
+        for term in terms {
+            //Electrons have no concentration
+            if term.formula.parts.is_empty() {
+                continue;
+            }
+
+            let mut species = term.clone();
+
+            species.coefficient = Ratio::one();
+
+            if included(term, kind) {
+                result.push(Factor {
+                    species,
+                    power: term.coefficient
+                });
+            }
+            else {
+                excluded.push(species);
+            }
+        }
+
+        result
+    }
+
+    // The equilibrium expression for a balanced equation
+    pub fn expression(equation: &Equation, kind: Kind) -> Expression {
+        let mut excluded = Vec::new();
+
+        let denominator = factors(&equation.reactants, kind, & mut excluded);
+        let numerator = factors(&equation.products, kind, & mut excluded);
+
+        Expression {
+            kind,
+            numerator,
+            denominator,
+            excluded
+        }
+    }
+
+    impl Factor {
+        //The species without its state, i.e. [NH3]^2 or P(NH3)^2
+        fn write(&self, kind: Kind) -> String {
+            let mut species = self.species.clone();
+
+            species.state = None;
+
+            let mut result = match kind {
+                Kind::Concentration => format!("[{}]", species),
+                Kind::Pressure => format!("P({})", species)
+            };
+
+            if self.power != Ratio::one() {
+                result.push('^');
+                result.push_str(&format_quantity(self.power));
+            }
+
+            result
+        }
+    }
+
+    impl Kind {
+        // What the amounts in the expression measure
+        pub fn dimension(self) -> Dimension {
+            match self {
+                Kind::Concentration => Dimension::CONCENTRATION,
+                Kind::Pressure => Dimension::PRESSURE
+            }
+        }
+    }
+
+    impl Expression {
+        // The name of the constant, Kc or Kp
+        pub fn name(&self) -> &'static str {
+            match self.kind {
+                Kind::Concentration => "Kc",
+                Kind::Pressure => "Kp"
+            }
+        }
+
+        //Each factor of a side written out, with brackets around a product of several factors if it is divided by
+        fn side(&self, factors: &[Factor], brackets: bool) -> String {
+            let written: Vec<String> = factors.iter().map(|factor| factor.write(self.kind)).collect();
+
+            let separator = match self.kind {
+                Kind::Concentration => "",
+                Kind::Pressure => " "
+            };
+
+            match written.len() {
+                0 => String::from("1"),
+                1 => written.join(separator),
+                _ if brackets => format!("({})", written.join(separator)),
+                _ => written.join(separator)
+            }
+        }
+    }
+
+    impl fmt::Display for Expression {
+        // The expression, i.e. Kc = [NH3]^2 / ([N2][H2]^3)
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            if self.denominator.is_empty() {
+                write!(f, "{} = {}", self.name(), self.side(&self.numerator, false))
+            }
+            else {
+                write!(f, "{} = {} / {}", self.name(), self.side(&self.numerator, false), self.side(&self.denominator, true))
+            }
+        }
+    }
+
+    // The amount of a species before and at equilibrium, as a concentration or partial pressure
+    pub struct Row {
+        pub species: Term,
+        pub initial: f64,
+        pub change: f64,
+        pub equilibrium: f64
+    }
+
+    // A solved ICE (initial, change, equilibrium) table. The extent is the change in the equation as written, so a
+    // species with coefficient n changes by n times the extent (negative for reactants)
+    pub struct Ice {
+        pub constant: f64,
+        pub extent: f64,
+        pub rows: Vec<Row>
+    }
+
+    // Parse an initial concentration or partial pressure and the species it is for, i.e. "0.5 M N2", "500 mM N2",
+    // "2 atm NH3" or "0.5 N2". Concentrations are in mol/L and pressures in bar, which is also what a number without a
+    // unit is taken to be in. A unit must measure one of dimensions
+    pub fn parse_initial(text: &str, dimensions: &[Dimension]) -> Result<(f64, Term), BalanceError> {
+        let text = text.trim();
+
+        //A number followed by a single word is a formula without a unit, even if the word is also a unit, i.e. 1 K
+        let (value, rest) = match Quantity::parse_prefix(text) {
+            Ok((quantity, rest)) if !rest.is_empty() => {
+                if !dimensions.contains(&quantity.unit.dimension) {
+                    let needed: Vec<String> = dimensions.iter().map(|dimension| dimension.name()).collect();
+
+                    return Err(BalanceError::new(ErrorKind::Input, format!("'{}' is {}, but {} is needed", quantity, quantity.unit.dimension.name(),
+                                                                           needed.join(" or "))));
+                }
+
+                let base = quantity.base().to_f64();
+
+                (if quantity.unit.dimension == Dimension::PRESSURE { base / 100000.0 } else { base / 1000.0 }, rest)
+            },
+            _ => {
+                let number_end = text.find(char::is_whitespace).unwrap_or(text.len());
+
+                let value = Value::parse(&text[..number_end])
+                    .ok_or_else(|| BalanceError::new(ErrorKind::Input, format!("'{}' must start with a number, i.e. 0.5 M N2", text)))?;
+
+                (value.to_f64(), &text[number_end..])
+            }
+        };
+
+        if value < 0.0 {
+            return Err(BalanceError::new(ErrorKind::Input, format!("'{}' must not be negative", text)));
+        }
+
+        let formula: String = rest.chars().filter(|ch| !ch.is_whitespace()).collect();
+
+        if formula.is_empty() {
+            return Err(BalanceError::new(ErrorKind::Input, format!("'{}' must end with a formula, i.e. 0.5 M N2", text)));
+        }
+
+        Ok((value, Term::parse(formula.as_bytes())?))
+    }
+
+    // The natural log of the reaction quotient, from the concentrations (or partial pressures) of some of the species in
+    // the expression. Species that are not given are taken at the standard amount of 1
+    pub fn ln_quotient(expression: &Expression, amounts: &[(f64, Term)]) -> Result<f64, BalanceError> {
+        for (_, term) in amounts {
+            if !expression.numerator.iter().chain(expression.denominator.iter()).any(|factor| factor.species.canonical() == term.canonical()) {
+                return Err(BalanceError::new(ErrorKind::Input, format!("{} is not in the reaction quotient", term)));
+            }
+        }
+
+        let mut result = 0.0;
+
+        for (factors, sign) in [(&expression.denominator, -1.0), (&expression.numerator, 1.0)].iter() {
+            for factor in factors.iter() {
+                let amount = amounts.iter().find(|(_, term)| term.canonical() == factor.species.canonical()).map(|(amount, _)| *amount).unwrap_or(1.0);
+
+                if amount <= 0.0 {
+                    return Err(BalanceError::new(ErrorKind::Input, format!("The amount of {} must be more than zero", factor.species)));
+                }
+
+                result += sign * *factor.power.numer() as f64 / *factor.power.denom() as f64 * amount.ln();
+            }
+        }
+
+        Ok(result)
+    }
+
+    //Number of halvings of the interval the extent lies in, enough to reach the limit of f64 precision
+    const ITERATIONS: usize = 2000;
+
+    // Solve the ICE table for an expression, given the initial amount of some of its species (the rest start at zero)
+    // and the value of the constant. The reaction quotient only increases with the extent, so the extent is found by
+    // bisection between the limits where a reactant or product runs out
+    pub fn solve(expression: &Expression, initial: &[(f64, Term)], constant: f64) -> Result<Ice, BalanceError> {
+        if constant <= 0.0 || !constant.is_finite() {
+            return Err(BalanceError::new(ErrorKind::Input, format!("{} must be a positive number", expression.name())));
+        }
+
+        //Each species with its initial amount and signed coefficient
+        let mut species: Vec<(&Term, f64, f64)> = Vec::new();
+
+        for (factors, sign) in [(&expression.denominator, -1.0), (&expression.numerator, 1.0)].iter() {
+            for factor in factors.iter() {
+                species.push((&factor.species, 0.0, sign * *factor.power.numer() as f64 / *factor.power.denom() as f64));
+            }
+        }
+
+        for (amount, term) in initial {
+            match species.iter_mut().find(|(found, _, _)| found.canonical() == term.canonical()) {
+                Some((_, start, _)) => *start = *amount,
+                None => return Err(BalanceError::new(ErrorKind::Input, format!("{} is not in the equilibrium expression", term)))
+            }
+        }
+
+        //The extent must leave every amount zero or more
+        let mut low = f64::NEG_INFINITY;
+        let mut high = f64::INFINITY;
+
+        for (_, start, coefficient) in species.iter() {
+            if *coefficient > 0.0 {
+                low = low.max(-start / coefficient);
+            }
+            else {
+                high = high.min(-start / coefficient);
+            }
+        }
+
+        if low >= high {
+            return Err(BalanceError::new(ErrorKind::Input, String::from("The initial amounts do not allow the reaction to go either way, give at least one reactant or product")));
+        }
+
+        //The log of the reaction quotient minus the log of the constant, which increases with the extent
+        let difference = |extent: f64| species.iter()
+            .map(|(_, start, coefficient)| coefficient * (start + coefficient * extent).ln())
+            .sum::<f64>() - constant.ln();
+
+        //An unbounded side is widened until it brackets the solution
+        let mut step: f64 = 1.0;
+
+        while low.is_infinite() && step.is_finite() {
+            if difference(high - step) < 0.0 {
+                low = high - step;
+            }
+
+            step *= 2.0;
+        }
+
+        while high.is_infinite() && step.is_finite() {
+            if difference(low + step) > 0.0 {
+                high = low + step;
+            }
+
+            step *= 2.0;
+        }
+
+        if low.is_infinite() || high.is_infinite() {
+            return Err(BalanceError::new(ErrorKind::Input, format!("No equilibrium amounts give {} = {}", expression.name(), constant)));
+        }
+
+        for _ in 0..ITERATIONS {
+            let middle = low + (high - low) / 2.0;
+
+            if middle <= low || middle >= high {
+                break;
+            }
+
+            if difference(middle) < 0.0 {
+                low = middle;
+            }
+            else {
+                high = middle;
+            }
+        }
+
+        let extent = low + (high - low) / 2.0;
+
+        let rows = species.iter().map(|(term, start, coefficient)| Row {
+            species: (*term).clone(),
+            initial: *start,
+            change: coefficient * extent,
+            equilibrium: (start + coefficient * extent).max(0.0)
+        }).collect();
+
+        Ok(Ice {
+            constant,
+            extent,
+            rows
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn parse(equation: &str, kind: Kind) -> Expression {
+            expression(&Equation::parse(equation.as_bytes()).unwrap(), kind)
+        }
+
+        fn amounts(texts: &[&str]) -> Vec<(f64, Term)> {
+            texts.iter().map(|text| parse_initial(text, &[Dimension::CONCENTRATION]).unwrap()).collect()
+        }
+
+        fn equilibrium(ice: &Ice, formula: &str) -> f64 {
+            ice.rows.iter().find(|row| row.species.to_string() == formula).unwrap().equilibrium
+        }
+
+        #[test]
+        fn writes_expressions() {
+            assert_eq!(parse("N2+3H2=2NH3", Kind::Concentration).to_string(), "Kc = [NH3]^2 / ([N2][H2]^3)");
+            assert_eq!(parse("N2(g)+3H2(g)=2NH3(g)", Kind::Pressure).to_string(), "Kp = P(NH3)^2 / (P(N2) P(H2)^3)");
+
+            let decomposition = parse("CaCO3(s)=CaO(s)+CO2(g)", Kind::Pressure);
+
+            assert_eq!(decomposition.to_string(), "Kp = P(CO2)");
+            assert_eq!(decomposition.excluded.len(), 2);
+        }
+
+        #[test]
+        fn solves_ice_tables_from_either_side() {
+            let expression = parse("H2+I2=2HI", Kind::Concentration);
+
+            //(2x)^2 / (1 - x)^2 = 50, so x = sqrt(50) / (2 + sqrt(50))
+            let extent = 50.0f64.sqrt() / (2.0 + 50.0f64.sqrt());
+
+            let forward = solve(&expression, &amounts(&["1 H2", "1 I2"]), 50.0).unwrap();
+
+            assert!((forward.extent - extent).abs() < 1e-9);
+            assert!((equilibrium(&forward, "HI") - 2.0 * extent).abs() < 1e-9);
+
+            let reverse = solve(&expression, &amounts(&["2 HI"]), 50.0).unwrap();
+
+            assert!((equilibrium(&reverse, "H2") - (1.0 - extent)).abs() < 1e-9);
+        }
+
+        #[test]
+        fn rejects_impossible_ice_tables() {
+            let expression = parse("H2+I2=2HI", Kind::Concentration);
+
+            assert!(solve(&expression, &amounts(&["1 H2"]), 0.0).is_err());
+            assert!(solve(&expression, &amounts(&["1 H2"]), 50.0).is_err());
+            assert!(solve(&expression, &amounts(&["1 H2", "1 O2"]), 50.0).is_err());
+        }
+
+        #[test]
+        fn parses_initial_amounts_with_units() {
+            let concentration = [Dimension::CONCENTRATION];
+
+            let initial = |text: &str| parse_initial(text, &concentration).map(|(value, term)| (value, term.to_string()));
+
+            assert_eq!(initial("0.5 N2").unwrap(), (0.5, String::from("N2")));
+            assert_eq!(initial("0.5 M N2").unwrap(), (0.5, String::from("N2")));
+            assert_eq!(initial("250 mM, H2").unwrap(), (0.25, String::from("H2")));
+            assert_eq!(initial("1 K").unwrap(), (1.0, String::from("K")));
+            assert_eq!(initial("2 atm NH3").unwrap_err().message, "'2 atm' is a pressure, but a concentration is needed");
+            assert!((parse_initial("2 atm NH3", &[Dimension::PRESSURE]).unwrap().0 - 2.02650).abs() < 1e-9);
+            assert!(initial("-1 H2").is_err());
+            assert!(initial("-1 M H2").is_err());
+            assert!(initial("1").is_err());
+            assert!(initial("M N2").is_err());
+        }
+
+        #[test]
+        fn finds_the_reaction_quotient() {
+            let expression = parse("N2+3H2=2NH3", Kind::Concentration);
+
+            assert!((ln_quotient(&expression, &amounts(&["2 NH3"])).unwrap() - 2.0 * 2.0f64.ln()).abs() < 1e-12);
+            assert!(ln_quotient(&expression, &amounts(&["0 N2"])).is_err());
+            assert!(ln_quotient(&expression, &amounts(&["1 O2"])).is_err());
+        }
+    }
+}
@@ -0,0 +1,175 @@
+pub mod balance_error {
+
+    use std::cell::RefCell;
+    use std::fmt;
+    use std::panic;
+
+    // The reason an equation could not be balanced
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum ErrorKind {
+        Parse, // The equation is not written correctly
+        Impossible, // The only solution is every coefficient being zero
+        Ambiguous, // There are infinitely many independent solutions
+        Negative, // The solution needs a negative coefficient
+        Input, // The equation could not be read
+        Data, // Data needed for a calculation is missing, i.e. the formation data of a species
+        Internal // Something went wrong in chembal itself, i.e. a panic while balancing
+    }
+
+    impl ErrorKind {
+        // The process exit code for a failure of this kind. 1 is left for usage errors, which are reported by clap
+        pub fn exit_code(&self) -> i32 {
+            match self {
+                ErrorKind::Parse => 2,
+                ErrorKind::Impossible => 3,
+                ErrorKind::Ambiguous => 4,
+                ErrorKind::Negative => 5,
+                ErrorKind::Input => 6,
+                ErrorKind::Internal => 9,
+                ErrorKind::Data => 10
+            }
+        }
+    }
+
+    // Exit code when an equation given to chembal check is not balanced
+    pub const EXIT_UNBALANCED: i32 = 7;
+
+    // Exit code when several equations (or formulas) fail in different ways
+    pub const EXIT_MIXED: i32 = 8;
+
+    // The exit code for two results together. Zero if both succeeded, the code of the failure if only one failed or both
+    // failed in the same way, otherwise EXIT_MIXED
+    pub fn combine_exit_codes(first: i32, second: i32) -> i32 {
+        if first == 0 || first == second {
+            second
+        }
+        else if second == 0 {
+            first
+        }
+        else {
+            EXIT_MIXED
+        }
+    }
+
+    // An error with a message for the user, and the location in the equation it refers to if there is one
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct BalanceError {
+        pub kind: ErrorKind,
+        pub message: String,
+        pub span: Option<(usize, usize)> // Byte range in the equation, start inclusive and end exclusive
+    }
+
+    impl BalanceError {
+        pub fn new(kind: ErrorKind, message: String) -> Self {
+            BalanceError {
+                kind,
+                message,
+                span: None
+            }
+        }
+
+        // A parse error covering slice, which must be a sub slice of equation
+        pub fn parse(message: String, equation: &[u8], slice: &[u8]) -> Self {
+            let start = slice.as_ptr() as usize;
+            let base = equation.as_ptr() as usize;
+
+            //Some error slices are constants rather than part of the equation, these have no span
+            let span = if start >= base && start + slice.len() <= base + equation.len() {
+                Some((start - base, start - base + slice.len()))
+            }
+            else {
+                None
+            };
+
+            BalanceError {
+                kind: ErrorKind::Parse,
+                message,
+                span
+            }
+        }
+
+        // A short, stable name for the kind of error, for machine readable output
+        pub fn code(&self) -> &'static str {
+            match self.kind {
+                ErrorKind::Parse => "parse",
+                ErrorKind::Impossible => "impossible",
+                ErrorKind::Ambiguous => "ambiguous",
+                ErrorKind::Negative => "negative",
+                ErrorKind::Input => "input",
+                ErrorKind::Data => "data",
+                ErrorKind::Internal => "internal"
+            }
+        }
+    }
+
+    impl fmt::Display for BalanceError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.message)
+        }
+    }
+
+    thread_local! {
+        //Why and where the last panic on this thread happened, kept for the internal error that reports it
+        static PANIC: RefCell<Option<String>> = const { RefCell::new(None) };
+    }
+
+    // Keep the reason for each panic, with the file and line it happened at, instead of printing it with a backtrace.
+    // A panic is then only reported once, as an internal error
+    pub fn quiet_panics() {
+        panic::set_hook(Box::new(|info| {
+            let payload = info.payload();
+
+            let message = match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+                (Some(message), _) => String::from(*message),
+                (_, Some(message)) => message.clone(),
+                _ => String::from("unknown cause")
+            };
+
+            let reason = match info.location() {
+                Some(location) => format!("{} at {}:{}", message, location.file(), location.line()),
+                None => message
+            };
+
+            PANIC.with(|panic| *panic.borrow_mut() = Some(reason));
+        }));
+    }
+
+    // An internal error for a panic caught on this thread, with the reason kept by quiet_panics if there is one
+    pub fn internal(message: &str) -> BalanceError {
+        match PANIC.with(|panic| panic.borrow_mut().take()) {
+            Some(reason) => BalanceError::new(ErrorKind::Internal, format!("{} ({}). This is a bug in chembal", message, reason)),
+            None => BalanceError::new(ErrorKind::Internal, format!("{}. This is a bug in chembal", message))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn combines_exit_codes() {
+            assert_eq!(combine_exit_codes(0, 0), 0);
+            assert_eq!(combine_exit_codes(0, 2), 2);
+            assert_eq!(combine_exit_codes(3, 0), 3);
+            assert_eq!(combine_exit_codes(3, 3), 3);
+            assert_eq!(combine_exit_codes(2, 3), EXIT_MIXED);
+        }
+
+        #[test]
+        fn locates_parse_errors_within_the_equation() {
+            let equation = b"H2+O2=H2O)";
+
+            assert_eq!(BalanceError::parse(String::new(), equation, &equation[9..]).span, Some((9, 10)));
+            assert_eq!(BalanceError::parse(String::new(), equation, b"{}").span, None);
+        }
+
+        #[test]
+        fn reports_internal_errors_as_bugs() {
+            let error = internal("Internal error");
+
+            assert_eq!(error.kind, ErrorKind::Internal);
+            assert_eq!(error.message, "Internal error. This is a bug in chembal");
+            assert_eq!(error.kind.exit_code(), 9);
+        }
+    }
+}
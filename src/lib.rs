@@ -0,0 +1,19 @@
+// Balances chemical equations, and finds the masses, amounts, energies and equilibria of the reactions they describe.
+// The chembal binary is a command line interface to this library
+
+pub mod solve;
+pub mod parser;
+pub mod ast;
+pub mod error;
+pub mod output;
+pub mod elements;
+pub mod repl;
+pub mod redox;
+pub mod stoich;
+pub mod thermo;
+pub mod equilibrium;
+pub mod electro;
+pub mod units;
+pub mod classify;
+pub mod predict;
+pub mod balance;
@@ -1,353 +1,763 @@
+use chembal::ast::equation::Term;
+use chembal::balance::balancer::{remove_whitespace, Format, Options};
+use chembal::balance::report::{balance_line, cell_report, check_line, combust_report, convert_report, equilibrium_report, mass_report,
+                               redox_report, stoich_report, temperatures, thermo_report, titrate_report, Outcome};
+use chembal::elements::abbreviations;
+use chembal::error::balance_error::{self, BalanceError, ErrorKind};
+use chembal::output::{json, matrix};
+use chembal::repl::session::Session;
+use chembal::stoich::amounts::Conditions;
+use chembal::equilibrium::constant::Kind;
+use chembal::predict::combustion::Nitrogen;
+
+use clap::{Arg, App, ArgGroup, ArgMatches, SubCommand};
 
-mod solve;
-mod parser;
+use std::time::{Instant};
 
-//extern crate num;
+use std::env;
+use std::fs::File;
+use std::path::Path;
+use std::io::{BufRead, BufReader};
 
-use crate::solve::matrices::Augmented;
-use crate::parser::equation_parser::TokenIterator;
-use crate::parser::equation_parser::TokenType;
+use std::fmt::Write;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
 
-use num_rational::Ratio;
-use std::collections::HashMap;
+//Number of lines read from an --input file before they are balanced and printed
+const BLOCK_SIZE: usize = 4096;
 
-use num_traits::identities::One;
-use num_traits::identities::Zero;
+//Balances or checks a single line, returning everything to be printed for it and its exit code
+type LineFn = fn(Option<usize>, &str, &Options) -> Outcome;
 
-use clap::{Arg, App};
+//Evaluate a single line, reporting a panic as an internal error so the remaining lines are still evaluated
+fn evaluate_line(evaluate: LineFn, line_number: Option<usize>, line: &str, options: &Options) -> Outcome {
+    panic::catch_unwind(AssertUnwindSafe(|| evaluate(line_number, line, options))).unwrap_or_else(|_| {
+        let error = balance_error::internal("Internal error, the equation could not be processed");
 
-use std::time::{Instant};
+        let mut outcome = Outcome { output: String::new(), errors: String::new(), code: error.kind.exit_code() };
 
-fn parse_group<'a>(group: & 'a [u8], map: & mut HashMap<& 'a [u8], Ratio<i32>>, scalar: Ratio<i32>) -> Option<String> {
+        if options.format == Format::Json {
+            writeln!(outcome.output, "{}", json::error(line_number, line, &error, &[])).unwrap();
+        }
+        else {
+            match line_number {
+                Some(line_number) => writeln!(outcome.errors, "Line {}: {}", line_number, error).unwrap(),
+                None => writeln!(outcome.errors, "{}", error).unwrap()
+            }
+        }
 
-    for token in TokenIterator::new(group) {
-        match token {
-            TokenType::Symbol(element, _, quantity) => {
+        outcome
+    })
+}
 
-                let num_ref_option = map.get_mut(element);
+//Evaluate a block of numbered lines, split across jobs threads. The results are in the same order as the lines
+fn process_block(block: &[(usize, String)], options: &Options, jobs: usize, evaluate: LineFn) -> Vec<Outcome> {
 
-                match num_ref_option {
-                    Some(num_ref) => {
-                        *num_ref += quantity * scalar;
-                    },
-                    None => {
-                        map.insert(element, quantity * scalar);
-                    }
-                }
-            },
-            TokenType::Group(group, _, quantity) => {
-                let mut err = parse_group(group, map, scalar * quantity);
-                if err.is_some() {
-                    return err.take();
-                }
-            },
-            TokenType::Invalid(c) => {
-                return Some(format!("Invalid token '{}'", unsafe { std::str::from_utf8_unchecked(c) }));
-            },
-            TokenType::Error(slice, error) => {
-                return Some(format!("{} ({})", error, slice));
-            },
-            TokenType::Separator(sep) => {
-                return Some(format!("Invalid symbol ({}) found within parenthesis", sep))
-                //Separator within group, error
-            }
-        }
+    if jobs <= 1 {
+        return block.iter().map(|(line_number, line)| evaluate_line(evaluate, Some(*line_number), line, options)).collect();
     }
 
-    None
+    //Each thread takes the next unsolved line until there are none left
+    let next = AtomicUsize::new(0);
 
-}
+    let mut results: Vec<Option<Outcome>> = vec![None; block.len()];
 
-fn send_column<'a>(table: & mut HashMap<&'a[u8], Ratio<i32>>,col: & mut Vec<Ratio<i32>>,
-               mat: & mut Augmented,master: HashMap<&'a [u8], Ratio<i32>>,verbose: bool) {
-    if verbose {
-        for (element, quantity) in table.iter() {
-            print!("({} {}) ", unsafe { std::str::from_utf8_unchecked(element) }, quantity);
-        }
-        println!("\n");
-    }
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..jobs).map(|_| scope.spawn(|| {
+            let mut solved = Vec::new();
 
-    for quantity in table.values() {
-        col.push(*quantity);
-    }
+            loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
 
-    mat.add_column(&col);
+                if index >= block.len() {
+                    break;
+                }
+
+                let (line_number, line) = &block[index];
+
+                solved.push((index, evaluate_line(evaluate, Some(*line_number), line, options)));
+            }
+
+            solved
+        })).collect();
 
-    col.clear();
+        for handle in handles {
+            for (index, result) in handle.join().unwrap() {
+                results[index] = Some(result);
+            }
+        }
+    });
 
-    *table = master.clone();
+    results.into_iter().map(|result| result.unwrap()).collect()
 }
 
-fn solve_equation(equation_asbytes: &[u8], verbose: bool) -> Result<String, String> {
+//Evaluate one equation per line, skipping blank lines and lines starting with #. Returns the number of equations
+//attempted, the number that failed and the exit code for them all
+fn process_lines<R: BufRead>(reader: R, options: &Options, jobs: usize, evaluate: LineFn) -> Result<(usize, usize, i32), BalanceError> {
 
-    let mut master_table = HashMap::<&[u8], Ratio<i32>>::new();
+    let mut attempted = 0;
+    let mut failed = 0;
+    let mut code = 0;
 
-    let mut equals_count = 0;
-    let mut equals_index = 0;
+    let mut lines = reader.lines().enumerate();
 
-    for ch in equation_asbytes {
-        if *ch > 127 {
-            return Err(String::from("Invalid character detected. Please only use valid Ascii characters (Unicode is not supported)"));
-        }
+    loop {
+        //Lines are read and balanced in blocks, so the output stays in order without holding the whole input in memory
+        let mut block = Vec::with_capacity(BLOCK_SIZE);
 
-        //Get the total number of equals signs
-        if *ch == 61 {
-            equals_count += 1;
-        }
+        for (index, line) in lines.by_ref() {
+            let line = line.map_err(|error| BalanceError::new(ErrorKind::Input, format!("Could not read line {} ({})", index + 1, error)))?;
 
-        //Get the index of the first occurence of an equals sign
-        if equals_count == 0 {
-            equals_index += 1;
-        }
-    }
+            let trimmed = line.trim();
 
-    if equals_count != 1 {
-        return Err(String::from("Formula must have exactly one equals"));
-    }
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
 
-    if equals_index == 0 || equals_index == equation_asbytes.len() - 1 {
-        return Err(String::from("Formula must have at least one reactant and one product"));
-    }
+            block.push((index + 1, String::from(trimmed)));
 
-    //Perform the initial run looking for ions and symbols
-    for token in TokenIterator::new(equation_asbytes) {
-        match token {
-            TokenType::Symbol(s, _, _) => {
-                master_table.insert(s, Ratio::zero());
-            },
-            TokenType::Group(group, _, _) => {
-                let err = parse_group(group, & mut master_table, Ratio::zero());
-                if err.is_some() {
-                    return Err(err.unwrap());
-                };
-            },
-            TokenType::Invalid(c) => {
+            if block.len() == BLOCK_SIZE {
+                break;
+            }
+        }
 
-                return Err(format!("Invalid token '{}'", unsafe { std::str::from_utf8_unchecked(c) }));
+        if block.is_empty() {
+            break;
+        }
 
-            },
-            TokenType::Error(slice, error) => {
-                return Err(format!("{} ({})", error, slice));
-            },
-            TokenType::Separator(_) => {
+        for outcome in process_block(&block, options, jobs, evaluate) {
+            attempted += 1;
+
+            if outcome.code != 0 {
+                failed += 1;
             }
+
+            code = balance_error::combine_exit_codes(code, outcome.code);
+
+            print!("{}", outcome.output);
+            eprint!("{}", outcome.errors);
         }
     }
 
-    if equals_count != 1 {
-        return Err(String::from("Equation must contain exactly one equals sign ('=')"));
-    }
+    Ok((attempted, failed, code))
+}
 
-    //if equation_asbytes.len() < {}
+//Arguments shared by chembal and chembal balance, which both balance equations
+fn balance_args() -> Vec<Arg<'static, 'static, 'static, 'static, 'static, 'static>> {
+    vec![
+        Arg::with_name("equation")
+            .short("e")
+            .long("equation")
+            .takes_value(true)
+            .help("Chemical equation to balance"),
+        Arg::with_name("input")
+            .short("i")
+            .long("input")
+            .takes_value(true)
+            .help("File of equations to balance, one per line. Use - to read from stdin"),
+        Arg::with_name("jobs")
+            .short("j")
+            .long("jobs")
+            .takes_value(true)
+            .help("Number of threads used to balance an --input file, 0 uses every CPU core (default 1)"),
+        Arg::with_name("verbose")
+            .short("v")
+            .long("verbose")
+            .takes_value(false)
+            .help("Displays intermediate steps"),
+        Arg::with_name("explain")
+            .short("x")
+            .long("explain")
+            .takes_value(false)
+            .help("Explains each step of the method used to balance the equation, for teaching"),
+        Arg::with_name("duration")
+            .short("d")
+            .long("duration")
+            .takes_value(false)
+            .help("Displays computation time"),
+        Arg::with_name("spellings")
+            .short("s")
+            .long("spellings")
+            .takes_value(false)
+            .help("Warns when the same molecular formula is written in two different ways"),
+        Arg::with_name("spectators")
+            .short("c")
+            .long("spectators")
+            .takes_value(false)
+            .help("Removes molecules found on both sides of the equation (catalysts and spectator ions) before balancing"),
+        Arg::with_name("type")
+            .short("t")
+            .long("type")
+            .takes_value(false)
+            .help("Also gives the type of reaction, i.e. combustion, synthesis, single displacement, precipitation or redox"),
+        Arg::with_name("output")
+            .short("o")
+            .long("output")
+            .takes_value(true)
+            .possible_values(&["text", "json", "latex"])
+            .help("Output format, text (default), json or latex (mhchem). With --input, json gives one object per line"),
+        Arg::with_name("export-matrix")
+            .short("m")
+            .long("export-matrix")
+            .takes_value(true)
+            .possible_values(&["csv", "mm", "latex"])
+            .help("Also gives the element by species matrix and its row reduced form, as csv, mm (Matrix Market) or latex (bmatrix)")
+    ]
+}
+
+//Exactly one of --equation and --input must be given
+fn source_group() -> ArgGroup<'static, 'static> {
+    ArgGroup::with_name("source")
+        .add_all(&["equation", "input"])
+        .required(true)
+}
+
+//The text or json output format, for subcommands without latex output
+fn output_arg() -> Arg<'static, 'static, 'static, 'static, 'static, 'static> {
+    Arg::with_name("output")
+        .short("o")
+        .long("output")
+        .takes_value(true)
+        .possible_values(&["text", "json"])
+        .help("Output format, text (default) or json")
+}
 
-    if verbose {
-        println!("Symbol table");
-        for element in master_table.keys() {
-            print!("{} ", unsafe { std::str::from_utf8_unchecked(element) });
+//Options for a command, anything the command does not accept is off
+fn options(matches: &ArgMatches) -> Options {
+    Options {
+        verbose: matches.is_present("verbose"),
+        explain: matches.is_present("explain"),
+        spellings: matches.is_present("spellings"),
+        spectators: matches.is_present("spectators"),
+        classify: matches.is_present("type"),
+        export: match matches.value_of("export-matrix") {
+            Some("csv") => Some(matrix::Format::Csv),
+            Some("mm") => Some(matrix::Format::MatrixMarket),
+            Some("latex") => Some(matrix::Format::Latex),
+            _ => None
+        },
+        format: match matches.value_of("output") {
+            Some("json") => Format::Json,
+            Some("latex") => Format::Latex,
+            _ => Format::Text
         }
-        println!("\n");
     }
+}
 
-    let mut symbol_table = master_table.clone();
+//Evaluate the --equation, or each line of the --input file, and print the results. Returns the exit code, which with
+//--input is the code shared by every failure, or EXIT_MIXED if they failed in different ways
+fn run_equations(matches: &ArgMatches, evaluate: LineFn, failure: &str) -> i32 {
 
-    let mut sign = Ratio::<i32>::one();
+    let start = Instant::now();
 
-    let mut matrix = Augmented::new(symbol_table.len() );
+    let options = options(matches);
 
-    let mut column = Vec::with_capacity(symbol_table.len() );
+    //Checked here rather than with requires, as clap accepts --jobs without --input when --equation is given
+    if matches.is_present("jobs") && !matches.is_present("input") {
+        eprintln!("--jobs can only be used with --input");
 
-    if verbose {
-        println!("Molecule matrix");
+        return ErrorKind::Input.exit_code();
     }
 
-    //Iterate over each token, adding columns to the matrix as we find new molecules
-    for token in TokenIterator::new(equation_asbytes) {
+    if let Some(input) = matches.value_of("input") {
 
+        let jobs = match matches.value_of("jobs") {
+            Some(jobs) => match jobs.parse::<usize>() {
+                Ok(0) => thread::available_parallelism().map(|cores| cores.get()).unwrap_or(1),
+                Ok(jobs) => jobs,
+                Err(_) => {
+                    eprintln!("'{}' is not a valid number of jobs", jobs);
 
-        match token {
-            TokenType::Symbol(element, _, quantity) => {
-                let num_ref = symbol_table.get_mut(element).unwrap();
-                *num_ref += quantity * sign;
-            },
-            TokenType::Group(group, _, quantity) => {
-                parse_group(group, & mut symbol_table, quantity * sign);
-            },
-            TokenType::Invalid(_) => {
+                    return ErrorKind::Input.exit_code();
+                }
             },
-            TokenType::Separator(sep) => {
+            None => 1
+        };
 
-                send_column(& mut symbol_table, & mut column, & mut matrix, master_table.clone(),verbose);
+        let result = if input == "-" {
+            let stdin = std::io::stdin();
+
+            process_lines(stdin.lock(), &options, jobs, evaluate)
+        }
+        else {
+            File::open(input)
+                .map_err(|error| BalanceError::new(ErrorKind::Input, format!("Could not open '{}' ({})", input, error)))
+                .and_then(|file| process_lines(BufReader::new(file), &options, jobs, evaluate))
+        };
+
+        return match result {
+            Ok((attempted, failed, code)) => {
+
+                //JSON Lines output must only contain results, so the summary goes to stderr
+                if matches.is_present("duration") {
+                    if options.format == Format::Json {
+                        eprintln!("Elapsed: {:?}", start.elapsed());
+                    }
+                    else {
+                        println!("\nElapsed: {:?}", start.elapsed());
+                    }
+                }
 
-                //If the separator is an equals, flip the sign
-                if sep == 61 {
-                    sign = -Ratio::<i32>::one();
+                if failed != 0 {
+                    eprintln!("{} of {} equations {}", failed, attempted, failure);
                 }
+
+                code
             },
-            TokenType::Error(_,_) => {
-            }
-        }
+            Err(e) => {
+                if options.format == Format::Json {
+                    println!("{}", json::error(None, input, &e, &[]));
+                }
+                else {
+                    eprintln!("{}", e);
+                }
 
+                e.kind.exit_code()
+            }
+        };
     }
 
-    send_column(& mut symbol_table, & mut column, & mut matrix, master_table.clone(),verbose);
+    let outcome = evaluate_line(evaluate, None, matches.value_of("equation").unwrap(), &options);
 
-    matrix.augment();
+    print!("{}", outcome.output);
+    eprint!("{}", outcome.errors);
 
-    if verbose {
-        println!("Augmented matrix");
-        matrix.print();
+    if matches.is_present("duration") {
+        if options.format == Format::Json {
+            eprintln!("Elapsed: {:?}", start.elapsed());
+        }
+        else {
+            println!("\nElapsed: {:?}", start.elapsed());
+        }
     }
 
-    matrix.row_reduce();
+    outcome.code
+}
 
-    if verbose {
-        println!("Row reduced matrix");
-        matrix.print();
-    }
+//Print the output of a command on a single equation or formula, or the reason it failed after the failure message.
+//Returns the exit code
+fn report(input: &str, result: Result<String, BalanceError>, options: &Options, failure: &str) -> i32 {
+    match result {
+        Ok(output) => {
+            print!("{}", output);
 
-    match matrix.solve() {
-        Ok(solution) => {
-            if verbose {
+            0
+        },
+        Err(e) => {
+            if options.format == Format::Json {
+                println!("{}", json::error(None, input, &e, &[]));
+            }
+            else {
+                eprintln!("{}. {}", failure, e);
+            }
 
-                println!("Solved matrix");
-                matrix.print();
+            e.kind.exit_code()
+        }
+    }
+}
 
-                println!("Solution vector");
-                for coeff in solution.iter() {
-                    print!("{} ", coeff);
-                }
-                println!("\n");
-            }
+//Register the user's abbreviations, from the file named by CHEMBAL_ABBREVIATIONS or else ~/.config/chembal/abbreviations
+fn load_abbreviations() -> Result<(), BalanceError> {
+    if let Ok(path) = env::var("CHEMBAL_ABBREVIATIONS") {
+        return abbreviations::load(&path);
+    }
 
-            let mut result = String::with_capacity(equation_asbytes.len());
+    if let Ok(home) = env::var("HOME") {
+        let path = format!("{}/.config/chembal/abbreviations", home);
 
-            if solution[0] != 1 {
-                result = format!("{}{}", result, solution[0])
-            }
+        if Path::new(&path).exists() {
+            return abbreviations::load(&path);
+        }
+    }
 
-            let mut solution_index = 1;
+    Ok(())
+}
 
-            for token in TokenIterator::new(equation_asbytes) {
+fn main() {
 
+    let matches = App::new("Chemical Equation Balancer")
+        .version("0.3.0")
+        .author("Will Cooper")
+        .about("Command line tool to balance chemical equations. Without a subcommand, equations are balanced as with chembal balance. Organic groups such as Me, Et and Ph may be abbreviated, and more are read from the file named by CHEMBAL_ABBREVIATIONS or ~/.config/chembal/abbreviations")
+        .args(balance_args())
+        .arg_group(source_group())
+        .subcommands_negate_reqs(true)
+        .subcommand(SubCommand::with_name("balance")
+            .about("Balances chemical equations")
+            .args(balance_args())
+            .arg_group(source_group()))
+        .subcommand(SubCommand::with_name("check")
+            .about("Checks chemical equations are balanced with the coefficients given")
+            .arg(Arg::with_name("equation")
+                .short("e")
+                .long("equation")
+                .takes_value(true)
+                .help("Chemical equation to check, i.e. 2H2 + O2 = 2H2O"))
+            .arg(Arg::with_name("input")
+                .short("i")
+                .long("input")
+                .takes_value(true)
+                .help("File of equations to check, one per line. Use - to read from stdin"))
+            .arg(Arg::with_name("jobs")
+                .short("j")
+                .long("jobs")
+                .takes_value(true)
+                .help("Number of threads used to check an --input file, 0 uses every CPU core (default 1)"))
+            .arg(Arg::with_name("duration")
+                .short("d")
+                .long("duration")
+                .takes_value(false)
+                .help("Displays computation time"))
+            .arg(output_arg())
+            .arg_group(source_group()))
+        .subcommand(SubCommand::with_name("mass")
+            .about("Finds the molar mass of each formula, with the contribution of each element")
+            .arg(Arg::with_name("formula")
+                .index(1)
+                .multiple(true)
+                .required(true)
+                .help("Formulas to find the molar mass of, i.e. H2SO4 CuSO4*5H2O"))
+            .arg(output_arg()))
+        .subcommand(SubCommand::with_name("combust")
+            .about("Gives the balanced combustion equation of each fuel, made of carbon, hydrogen, oxygen, nitrogen and sulfur")
+            .arg(Arg::with_name("formula")
+                .index(1)
+                .multiple(true)
+                .required(true)
+                .help("Formulas of the fuels to burn, i.e. C8H18 C2H5OH CH3(CH2)4CH3"))
+            .arg(Arg::with_name("nitrogen")
+                .short("n")
+                .long("nitrogen")
+                .takes_value(true)
+                .possible_values(&["N2", "NO", "NO2"])
+                .help("What nitrogen in the fuel is given off as (default N2)"))
+            .arg(Arg::with_name("incomplete")
+                .short("i")
+                .long("incomplete")
+                .takes_value(false)
+                .help("Burns the fuel incompletely, giving off carbon as CO instead of CO2"))
+            .arg(output_arg()))
+        .subcommand(SubCommand::with_name("redox")
+            .about("Balances an equation and finds which elements are oxidised and reduced, using the oxidation state of each element")
+            .arg(Arg::with_name("equation")
+                .short("e")
+                .long("equation")
+                .takes_value(true)
+                .required(true)
+                .help("Chemical equation to balance, i.e. KMnO4 + HCl = KCl + MnCl2 + Cl2 + H2O"))
+            .arg(Arg::with_name("spectators")
+                .short("c")
+                .long("spectators")
+                .takes_value(false)
+                .help("Removes molecules found on both sides of the equation (catalysts and spectator ions) before balancing"))
+            .arg(output_arg()))
+        .subcommand(SubCommand::with_name("stoich")
+            .about("Balances an equation and finds the amount of each species from the amounts of one or more of them")
+            .arg(Arg::with_name("equation")
+                .short("e")
+                .long("equation")
+                .takes_value(true)
+                .required(true)
+                .help("Chemical equation to balance, i.e. H2 + O2 = H2O"))
+            .arg(Arg::with_name("given")
+                .short("g")
+                .long("given")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .required(true)
+                .help("Amount of a species in mol, mmol, g, kg, mg, for a gas L, mL or m3, or for a solution its molarity and volume, i.e. \"4 g H2\" or \"0.1 M, 25 mL HCl\". Give more than one reactant to find the limiting reactant"))
+            .arg(Arg::with_name("temperature")
+                .short("t")
+                .long("temperature")
+                .takes_value(true)
+                .help("Temperature of gases given as a volume in K, C or degC, i.e. \"25 C\" (default 273.15 K)"))
+            .arg(Arg::with_name("pressure")
+                .short("p")
+                .long("pressure")
+                .takes_value(true)
+                .help("Pressure of gases given as a volume in Pa, kPa, bar, atm, mmHg or Torr, i.e. \"1 atm\" (default 1 atm)"))
+            .arg(Arg::with_name("report-temperature")
+                .long("report-temperature")
+                .takes_value(true)
+                .help("Temperature to give the volume of each gas at (default --temperature)"))
+            .arg(Arg::with_name("report-pressure")
+                .long("report-pressure")
+                .takes_value(true)
+                .help("Pressure to give the volume of each gas at (default --pressure)"))
+            .arg(Arg::with_name("spectators")
+                .short("c")
+                .long("spectators")
+                .takes_value(false)
+                .help("Removes molecules found on both sides of the equation (catalysts and spectator ions) before balancing"))
+            .arg(output_arg()))
+        .subcommand(SubCommand::with_name("thermo")
+            .about("Balances an equation and finds its standard enthalpy, entropy and Gibbs energy changes from formation data")
+            .arg(Arg::with_name("equation")
+                .short("e")
+                .long("equation")
+                .takes_value(true)
+                .required(true)
+                .help("Chemical equation to balance, i.e. CH4(g) + O2(g) = CO2(g) + H2O(l). Species without a state are taken in their standard state"))
+            .arg(Arg::with_name("data")
+                .long("data")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("File of extra formation data, one species per line as formula, state, dHf (kJ/mol), S (J/mol K) and dGf (kJ/mol)"))
+            .arg(Arg::with_name("temperature")
+                .short("t")
+                .long("temperature")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Temperature in K to find the Gibbs energy change and equilibrium constant at, using dG = dH - TdS and K = exp(-dG/RT)"))
+            .arg(Arg::with_name("sweep")
+                .long("sweep")
+                .takes_value(true)
+                .help("Range of temperatures in K as start:end:step, i.e. 300:1000:100, each given as with --temperature"))
+            .arg(Arg::with_name("spectators")
+                .short("c")
+                .long("spectators")
+                .takes_value(false)
+                .help("Removes molecules found on both sides of the equation (catalysts and spectator ions) before balancing"))
+            .arg(output_arg()))
+        .subcommand(SubCommand::with_name("equilibrium")
+            .about("Balances an equation and gives its equilibrium constant expression, and the equilibrium amounts if the constant is given")
+            .arg(Arg::with_name("equation")
+                .short("e")
+                .long("equation")
+                .takes_value(true)
+                .required(true)
+                .help("Chemical equation to balance, i.e. N2(g) + H2(g) = NH3(g). Pure solids and liquids are left out of the expression"))
+            .arg(Arg::with_name("constant")
+                .short("k")
+                .long("constant")
+                .takes_value(true)
+                .help("Value of the equilibrium constant, used to solve the ICE table"))
+            .arg(Arg::with_name("initial")
+                .short("g")
+                .long("initial")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .requires("constant")
+                .help("Initial concentration (or partial pressure with --pressure) of a species, i.e. \"0.5 M N2\" or \"2 atm N2\". Without a unit it is in mol/L (or bar). Species not given start at zero"))
+            .arg(Arg::with_name("pressure")
+                .short("p")
+                .long("pressure")
+                .takes_value(false)
+                .help("Uses partial pressures (Kp) instead of concentrations (Kc), leaving out everything that is not a gas"))
+            .arg(Arg::with_name("spectators")
+                .short("c")
+                .long("spectators")
+                .takes_value(false)
+                .help("Removes molecules found on both sides of the equation (catalysts and spectator ions) before balancing"))
+            .arg(output_arg()))
+        .subcommand(SubCommand::with_name("cell")
+            .about("Balances a redox reaction and finds the half-reactions that make it up, its standard cell potential and Gibbs energy change")
+            .arg(Arg::with_name("equation")
+                .short("e")
+                .long("equation")
+                .takes_value(true)
+                .required(true)
+                .help("Redox reaction written as an ionic equation, i.e. Zn(s) + Cu{2+}(aq) = Zn{2+}(aq) + Cu(s)"))
+            .arg(Arg::with_name("concentration")
+                .short("g")
+                .long("concentration")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Concentration (or partial pressure) of a species for the Nernst equation, i.e. \"0.1 M Cu{2+}\" or \"100 mM Cu{2+}\". Without a unit it is in mol/L (or bar). Species not given are at 1"))
+            .arg(Arg::with_name("temperature")
+                .short("t")
+                .long("temperature")
+                .takes_value(true)
+                .help("Temperature in K for the Nernst equation (default 298.15)"))
+            .arg(Arg::with_name("spectators")
+                .short("c")
+                .long("spectators")
+                .takes_value(false)
+                .help("Removes molecules found on both sides of the equation (catalysts and spectator ions) before balancing"))
+            .arg(output_arg()))
+        .subcommand(SubCommand::with_name("titrate")
+            .about("Balances an equation and finds the concentration or volume of a solution at the equivalence point of a titration")
+            .arg(Arg::with_name("equation")
+                .short("e")
+                .long("equation")
+                .takes_value(true)
+                .required(true)
+                .help("Chemical equation to balance, i.e. HCl + NaOH = NaCl + H2O"))
+            .arg(Arg::with_name("analyte")
+                .short("a")
+                .long("analyte")
+                .takes_value(true)
+                .required(true)
+                .help("Molarity and volume of the solution being titrated, either of which can be left out, i.e. \"0.1 M, 25 mL HCl\" or \"25 mL HCl\""))
+            .arg(Arg::with_name("titrant")
+                .short("t")
+                .long("titrant")
+                .takes_value(true)
+                .required(true)
+                .help("Molarity and volume of the solution added, either of which can be left out, i.e. \"0.2 M NaOH\""))
+            .arg(Arg::with_name("spectators")
+                .short("c")
+                .long("spectators")
+                .takes_value(false)
+                .help("Removes molecules found on both sides of the equation (catalysts and spectator ions) before balancing"))
+            .arg(output_arg()))
+        .subcommand(SubCommand::with_name("convert")
+            .about("Converts an amount of a substance between moles, mass and volume, or a quantity between units of the same kind")
+            .arg(Arg::with_name("amount")
+                .index(1)
+                .required(true)
+                .help("Amount to convert, i.e. \"12.5 g H2O\", or a quantity without a formula, i.e. \"1 atm\" or \"25 C\""))
+            .arg(Arg::with_name("to")
+                .short("t")
+                .long("to")
+                .takes_value(true)
+                .required(true)
+                .help("Unit to convert to, i.e. mol, g, mL, mmHg, K or mol/L"))
+            .arg(Arg::with_name("temperature")
+                .short("T")
+                .long("temperature")
+                .takes_value(true)
+                .help("Temperature of a gas in K, C or degC, for amounts in L, mL or m3 (default 273.15 K)"))
+            .arg(Arg::with_name("pressure")
+                .short("p")
+                .long("pressure")
+                .takes_value(true)
+                .help("Pressure of a gas in Pa, kPa, bar, atm, mmHg or Torr, for amounts in L, mL or m3 (default 1 atm)"))
+            .arg(output_arg()))
+        .subcommand(SubCommand::with_name("repl")
+            .about("Balances each line as it is typed. Type :help for a list of commands"))
+        .get_matches();
 
+    //A panic is reported once as an internal error, rather than also printing the panic message and a backtrace
+    balance_error::quiet_panics();
 
-                match token {
-                    TokenType::Symbol(symbol, string, _) => {
+    //Most commands never use an abbreviation, so a bad file should not stop them from running
+    if let Err(error) = load_abbreviations() {
+        eprintln!("Warning: {}. Only the built in abbreviations can be used", error);
+    }
 
-                        result.push_str(string);
+    //A panic is a bug, but scripts should still be able to tell it apart from a failure to balance
+    let code = panic::catch_unwind(AssertUnwindSafe(|| match matches.subcommand() {
+        ("balance", Some(sub)) => run_equations(sub, balance_line, "could not be solved"),
+        ("check", Some(sub)) => run_equations(sub, check_line, "are not balanced or could not be checked"),
+        ("mass", Some(sub)) => {
+            let options = options(sub);
 
-                        if unsafe { std::str::from_utf8_unchecked(symbol) } == "charge" && string != "e" {
-                            result.push_str("}");
-                        }
+            let mut code = 0;
 
-                    },
-                    TokenType::Group(_, string, _) => {
+            for formula in sub.values_of("formula").unwrap() {
+                let key = remove_whitespace(formula);
 
-                        result.push_str(string);
+                let result = Term::parse(key.as_bytes()).and_then(|term| mass_report(formula, &term, &options));
 
-                    },
-                    TokenType::Separator(sep) => {
+                code = balance_error::combine_exit_codes(code, report(formula, result, &options, "Cannot find molar mass"));
+            }
 
-                        result.push_str(
-                            if sep == 43 {
-                                "+"
-                            } else if sep == 61 {
-                                "="
-                            } else {
-                                ""
-                            }
-                        );
+            code
+        },
+        ("combust", Some(sub)) => {
+            let options = options(sub);
 
-                        if solution[solution_index] != 1 {
-                            result = format!("{}{}", result, solution[solution_index])
-                        }
+            let nitrogen = match sub.value_of("nitrogen") {
+                Some("NO") => Nitrogen::NO,
+                Some("NO2") => Nitrogen::NO2,
+                _ => Nitrogen::N2
+            };
 
-                        solution_index += 1;
+            let mut code = 0;
 
-                    }
-                    _ => {
+            for formula in sub.values_of("formula").unwrap() {
+                let key = remove_whitespace(formula);
 
-                    }
-                }
+                let result = Term::parse(key.as_bytes()).and_then(|fuel| combust_report(formula, &fuel, nitrogen, sub.is_present("incomplete"), &options));
 
+                code = balance_error::combine_exit_codes(code, report(formula, result, &options, "Cannot burn fuel"));
             }
 
-            Ok(result)
+            code
         },
-        Err(e) => {
-            Err(e)
-        }
-    }
-
-}
+        ("redox", Some(sub)) => {
+            let options = options(sub);
+            let equation = sub.value_of("equation").unwrap();
 
-fn remove_whitespace(string: & str) -> String {
-    let mut result = String::new();
+            report(equation, redox_report(equation, &options), &options, "Cannot find oxidation states")
+        },
+        ("stoich", Some(sub)) => {
+            let options = options(sub);
+            let equation = sub.value_of("equation").unwrap();
 
-    for ch in string.as_bytes() {
-        if *ch != 9 && *ch != 10 && *ch != 13 && *ch != 32 {
-            result.push(char::from(*ch));
-        }
-    }
+            let temperature = sub.value_of("temperature");
+            let pressure = sub.value_of("pressure");
 
-    result
-}
+            let result = Conditions::parse(temperature, pressure).and_then(|given| {
+                let reported = Conditions::parse(sub.value_of("report-temperature").or(temperature), sub.value_of("report-pressure").or(pressure))?;
 
-fn main() {
+                stoich_report(equation, &sub.values_of("given").unwrap(), (given, reported), &options)
+            });
 
-    let matches = App::new("Chemical Equation Balancer")
-        .version("0.2.3")
-        .author("Will Cooper")
-        .about("Command line tool to balance chemical equations")
-        .arg(Arg::with_name("equation")
-            .short("e")
-            .long("equation")
-            .takes_value(true)
-            .help("Chemical equation to balance")
-            .required(true))
-        .arg(Arg::with_name("verbose")
-            .short("v")
-            .long("verbose")
-            .takes_value(false)
-            .help("Displays intermediate steps"))
-        .arg(Arg::with_name("duration")
-            .short("d")
-            .long("duration")
-            .takes_value(false)
-            .help("Displays computation time"))
-        .get_matches();
-
-    let start = Instant::now();
+            report(equation, result, &options, "Cannot find amounts")
+        },
+        ("thermo", Some(sub)) => {
+            let options = options(sub);
+            let equation = sub.value_of("equation").unwrap();
+            let data = sub.values_of("data").unwrap_or_default();
 
-    let verbose = matches.is_present("verbose");
+            let result = temperatures(&sub.values_of("temperature").unwrap_or_default(), sub.value_of("sweep"))
+                .and_then(|temperatures| thermo_report(equation, &data, &temperatures, &options));
 
-    let cleaned_equation = remove_whitespace(matches.value_of("equation").unwrap());
+            report(equation, result, &options, "Cannot find reaction energies")
+        },
+        ("equilibrium", Some(sub)) => {
+            let options = options(sub);
+            let equation = sub.value_of("equation").unwrap();
+            let initial = sub.values_of("initial").unwrap_or_default();
+            let kind = if sub.is_present("pressure") { Kind::Pressure } else { Kind::Concentration };
 
-    let balance = solve_equation(
-        cleaned_equation.as_bytes(),
-        verbose);
+            report(equation, equilibrium_report(equation, &initial, sub.value_of("constant"), kind, &options), &options, "Cannot find equilibrium")
+        },
+        ("cell", Some(sub)) => {
+            let options = options(sub);
+            let equation = sub.value_of("equation").unwrap();
+            let concentrations = sub.values_of("concentration").unwrap_or_default();
 
+            report(equation, cell_report(equation, &concentrations, sub.value_of("temperature"), &options), &options, "Cannot find cell potential")
+        },
+        ("titrate", Some(sub)) => {
+            let options = options(sub);
+            let equation = sub.value_of("equation").unwrap();
 
-    match balance {
-        Ok(s) => {
+            let result = titrate_report(equation, sub.value_of("analyte").unwrap(), sub.value_of("titrant").unwrap(), &options);
 
+            report(equation, result, &options, "Cannot solve titration")
+        },
+        ("convert", Some(sub)) => {
+            let options = options(sub);
+            let amount = sub.value_of("amount").unwrap();
 
-            if verbose {
-                println!("Equation: {}", s);
-            }
-            else {
-                println!("{}", s);
-            }
+            let result = Conditions::parse(sub.value_of("temperature"), sub.value_of("pressure"))
+                .and_then(|conditions| convert_report(amount, sub.value_of("to").unwrap(), conditions, &options));
 
-            if matches.is_present("duration") {
-                println!("\nElapsed: {:?}", start.elapsed());
-            }
+            report(amount, result, &options, "Cannot convert amount")
+        },
+        ("repl", _) => {
+            Session::new(options(&matches)).run();
 
+            0
+        },
+        //Without a subcommand, the arguments are those of chembal balance
+        _ => run_equations(&matches, balance_line, "could not be solved")
+    })).unwrap_or_else(|_| {
+        eprintln!("{}", balance_error::internal("Internal error"));
 
-        }
-        ,
-        Err(e) => println!("Cannot solve equation. {}", e)
-    };
+        ErrorKind::Internal.exit_code()
+    });
 
+    std::process::exit(code);
 
-}
\ No newline at end of file
+}
@@ -0,0 +1,606 @@
+pub mod json {
+
+    use crate::ast::equation::{format_quantity, Equation, State, Term};
+    use crate::error::balance_error::BalanceError;
+    use crate::redox::oxidation::{Change, States};
+    use crate::stoich::amounts::{Conditions, Stoichiometry};
+    use crate::stoich::titration::{Sample, Titration, Unknown};
+    use crate::thermo::formation::{Reaction, Spontaneity};
+    use crate::equilibrium::constant::{Expression, Factor, Ice};
+    use crate::electro::cell::{Cell, HalfReaction};
+    use crate::units::quantity::{Quantity, Value};
+    use crate::classify::reaction::Class;
+
+    use num_rational::Ratio;
+
+    //Quote and escape a string
+    fn string(value: &str) -> String {
+        let mut result = String::with_capacity(value.len() + 2);
+
+        result.push('"');
+
+        for ch in value.chars() {
+            match ch {
+                '"' => result.push_str("\\\""),
+                '\\' => result.push_str("\\\\"),
+                '\n' => result.push_str("\\n"),
+                '\r' => result.push_str("\\r"),
+                '\t' => result.push_str("\\t"),
+                ch if (ch as u32) < 32 => result.push_str(&format!("\\u{:04x}", ch as u32)),
+                ch => result.push(ch)
+            }
+        }
+
+        result.push('"');
+
+        result
+    }
+
+    //A ratio as a JSON number, falling back on a float if it has no finite decimal form
+    fn number(value: Ratio<i32>) -> String {
+        let decimal = format_quantity(value);
+
+        if decimal.contains('/') {
+            format!("{}", *value.numer() as f64 / *value.denom() as f64)
+        }
+        else {
+            decimal
+        }
+    }
+
+    //A float as a JSON number, or null if it is not finite as JSON has no NaN or infinity
+    fn float(value: f64) -> String {
+        if value.is_finite() {
+            value.to_string()
+        }
+        else {
+            String::from("null")
+        }
+    }
+
+    fn array(values: &[String]) -> String {
+        format!("[{}]", values.join(","))
+    }
+
+    fn state(state: Option<State>) -> String {
+        match state {
+            Some(State::Solid) => string("s"),
+            Some(State::Liquid) => string("l"),
+            Some(State::Gas) => string("g"),
+            Some(State::Aqueous) => string("aq"),
+            None => String::from("null")
+        }
+    }
+
+    fn species(term: &Term, side: &str) -> String {
+        let elements = term.formula.elements();
+
+        let counts: Vec<String> = elements.iter().map(|(element, quantity)| format!("{}:{}", string(element), number(quantity))).collect();
+
+        format!("{{\"formula\":{},\"canonical\":{},\"side\":{},\"coefficient\":{},\"elements\":{{{}}},\"charge\":{},\"state\":{}}}",
+                string(&term.formula.to_string()), string(&term.canonical()), string(side), number(term.coefficient),
+                counts.join(","), number(term.charge), state(term.state))
+    }
+
+    //The fields common to every result, the line number (for --input), the input and any notes
+    fn header(line: Option<usize>, input: &str, notes: &[String]) -> String {
+        let notes: Vec<String> = notes.iter().map(|note| string(note)).collect();
+
+        match line {
+            Some(line) => format!("\"line\":{},\"input\":{},\"notes\":{}", line, string(input), array(&notes)),
+            None => format!("\"input\":{},\"notes\":{}", string(input), array(&notes))
+        }
+    }
+
+    // A single line JSON object describing a balanced equation. Steps are included if given (--verbose or --explain), as
+    // are the exported composition and row reduced matrices (--export-matrix) and the types of reaction (--type)
+    pub fn balanced(line: Option<usize>, input: &str, equation: &Equation, notes: &[String], steps: Option<&str>,
+                    matrices: Option<(&str, &str)>, classes: Option<&[Class]>) -> String {
+        let mut species_list: Vec<String> = equation.reactants.iter().map(|term| species(term, "reactant")).collect();
+
+        species_list.extend(equation.products.iter().map(|term| species(term, "product")));
+
+        let steps = match steps {
+            Some(steps) => format!(",\"steps\":{}", string(steps)),
+            None => String::new()
+        };
+
+        let matrices = match matrices {
+            Some((composition, reduced)) => format!(",\"matrices\":{{\"composition\":{},\"reduced\":{}}}", string(composition), string(reduced)),
+            None => String::new()
+        };
+
+        let classes = match classes {
+            Some(classes) => format!(",\"types\":{}", array(&classes.iter().map(|class| string(class.name())).collect::<Vec<String>>())),
+            None => String::new()
+        };
+
+        format!("{{{},\"equation\":{},\"species\":{}{}{}{}}}", header(line, input, notes), string(&equation.to_string()), array(&species_list),
+                steps, matrices, classes)
+    }
+
+    // A single line JSON object describing whether an equation with the user's coefficients is balanced. The imbalance
+    // is the reactant total minus the product total for each element (and the charge) that does not balance
+    pub fn checked(line: Option<usize>, input: &str, equation: &Equation, imbalance: &[(String, Ratio<i32>)], notes: &[String]) -> String {
+        let differences: Vec<String> = imbalance.iter().map(|(element, difference)| format!("{}:{}", string(element), number(*difference))).collect();
+
+        format!("{{{},\"equation\":{},\"balanced\":{},\"imbalance\":{{{}}}}}", header(line, input, notes), string(&equation.to_string()),
+                imbalance.is_empty(), differences.join(","))
+    }
+
+    // A single line JSON object describing why an equation could not be balanced
+    pub fn error(line: Option<usize>, input: &str, error: &BalanceError, notes: &[String]) -> String {
+        let span = match error.span {
+            Some((start, end)) => format!("{{\"start\":{},\"end\":{}}}", start, end),
+            None => String::from("null")
+        };
+
+        format!("{{{},\"error\":{{\"code\":{},\"message\":{},\"span\":{}}}}}", header(line, input, notes), string(error.code()), string(&error.message), span)
+    }
+
+    // A single line JSON object with the molar mass of a formula and the number of each element in it
+    pub fn mass(input: &str, term: &Term, molar_mass: f64) -> String {
+        let elements = term.formula.elements();
+
+        let mut counts: Vec<(&str, Ratio<i32>)> = elements.iter().collect();
+
+        counts.sort_by(|a, b| a.0.cmp(b.0));
+
+        let counts: Vec<String> = counts.iter().map(|(element, quantity)| format!("{}:{}", string(element), number(*quantity))).collect();
+
+        format!("{{{},\"formula\":{},\"molar_mass\":{},\"elements\":{{{}}}}}", header(None, input, &[]), string(&term.formula.to_string()),
+                float(molar_mass), counts.join(","))
+    }
+
+    // A single line JSON object with the oxidation state of each element in each species of a balanced equation, and
+    // the elements that are oxidised or reduced
+    pub fn redox(input: &str, equation: &Equation, states: &[States], changes: &[Change],
+                 unknown: &[String], electrons: Ratio<i32>) -> String {
+        let species_list: Vec<String> = equation.terms().zip(states.iter()).map(|(term, states)| {
+            let states: Vec<String> = states.iter()
+                .map(|(element, state)| format!("{}:{}", string(element), state.map(number).unwrap_or_else(|| String::from("null"))))
+                .collect();
+
+            format!("{{\"formula\":{},\"coefficient\":{},\"charge\":{},\"states\":{{{}}}}}", string(&term.formula.to_string()),
+                    number(term.coefficient), number(term.charge), states.join(","))
+        }).collect();
+
+        let change_list: Vec<String> = changes.iter().map(|change| {
+            let before: Vec<String> = change.before.iter().map(|state| number(*state)).collect();
+            let after: Vec<String> = change.after.iter().map(|state| number(*state)).collect();
+            let transitions: Vec<String> = change.transitions.iter()
+                .map(|(from, to)| format!("{{\"from\":{},\"to\":{}}}", number(*from), number(*to)))
+                .collect();
+
+            format!("{{\"element\":{},\"before\":{},\"after\":{},\"transitions\":{},\"lost\":{},\"gained\":{}}}", string(&change.element),
+                    array(&before), array(&after), array(&transitions), number(change.lost), number(change.gained))
+        }).collect();
+
+        let unknown: Vec<String> = unknown.iter().map(|element| string(element)).collect();
+
+        //The electrons transferred are only known if every oxidation state is
+        let electrons = if unknown.is_empty() { number(electrons) } else { String::from("null") };
+
+        format!("{{{},\"equation\":{},\"species\":{},\"changes\":{},\"unknown\":{},\"electrons\":{}}}", header(None, input, &[]),
+                string(&equation.to_string()), array(&species_list), array(&change_list), array(&unknown), electrons)
+    }
+
+    // A single line JSON object with the amount of each species in a balanced equation, and the volume of each gas at
+    // the conditions given (temperature in K and pressure in Pa)
+    pub fn stoichiometry(input: &str, equation: &Equation, result: &Stoichiometry, conditions: &Conditions) -> String {
+        let amounts: Vec<String> = result.amounts.iter().map(|amount| {
+            let excess = match amount.excess {
+                Some(excess) => format!(",\"excess\":{}", float(excess)),
+                None => String::new()
+            };
+
+            let litres = amount.litres.map(float).unwrap_or_else(|| String::from("null"));
+
+            format!("{{\"formula\":{},\"charge\":{},\"side\":{},\"moles\":{},\"grams\":{},\"litres\":{}{}}}", string(&amount.species.formula.to_string()),
+                    number(amount.species.charge), string(if amount.reactant { "reactant" } else { "product" }), float(amount.moles), float(amount.grams), litres, excess)
+        }).collect();
+
+        let limiting = match &result.limiting {
+            Some(term) => string(&term.to_string()),
+            None => String::from("null")
+        };
+
+        format!("{{{},\"equation\":{},\"extent\":{},\"limiting\":{},\"amounts\":{},\"conditions\":{{\"temperature\":{},\"pressure\":{}}}}}",
+                header(None, input, &[]), string(&equation.to_string()), float(result.extent), limiting, array(&amounts), float(conditions.temperature),
+                float(conditions.pressure))
+    }
+
+    // A single line JSON object with the standard enthalpy, entropy and Gibbs energy changes of a balanced equation,
+    // the formation data used for each species, when it is spontaneous and its Gibbs energy change and equilibrium
+    // constant at each temperature. The constant is null if it is too large for a double, but its log is always given
+    pub fn thermo(input: &str, equation: &Equation, notes: &[String], result: &Reaction, temperatures: &[f64]) -> String {
+        let species_list: Vec<String> = result.species.iter().map(|species| {
+            format!("{{\"formula\":{},\"charge\":{},\"state\":{},\"standard\":{},\"enthalpy\":{},\"entropy\":{},\"gibbs\":{}}}",
+                    string(&species.term.formula.to_string()), number(species.term.charge), state(species.term.state), species.standard,
+                    float(species.formation.enthalpy), float(species.formation.entropy), float(species.formation.gibbs))
+        }).collect();
+
+        let spontaneity = match result.spontaneity() {
+            Spontaneity::Always => String::from("{\"when\":\"always\",\"temperature\":null}"),
+            Spontaneity::Never => String::from("{\"when\":\"never\",\"temperature\":null}"),
+            Spontaneity::Below(temperature) => format!("{{\"when\":\"below\",\"temperature\":{}}}", float(temperature)),
+            Spontaneity::Above(temperature) => format!("{{\"when\":\"above\",\"temperature\":{}}}", float(temperature))
+        };
+
+        let temperature_list: Vec<String> = temperatures.iter().map(|temperature| {
+            format!("{{\"temperature\":{},\"gibbs\":{},\"ln_constant\":{},\"constant\":{}}}", float(*temperature),
+                    float(result.gibbs_at(*temperature)), float(result.ln_constant_at(*temperature)), float(result.constant_at(*temperature)))
+        }).collect();
+
+        format!("{{{},\"equation\":{},\"species\":{},\"enthalpy\":{},\"entropy\":{},\"gibbs\":{},\"spontaneity\":{},\"temperatures\":{}}}",
+                header(None, input, notes), string(&equation.to_string()), array(&species_list), float(result.enthalpy), float(result.entropy),
+                float(result.gibbs), spontaneity, array(&temperature_list))
+    }
+
+    fn factor(factor: &Factor) -> String {
+        format!("{{\"formula\":{},\"charge\":{},\"state\":{},\"power\":{}}}", string(&factor.species.formula.to_string()),
+                number(factor.species.charge), state(factor.species.state), number(factor.power))
+    }
+
+    // A single line JSON object with the equilibrium expression of a balanced equation, and the solved ICE table if the
+    // constant was given
+    pub fn equilibrium(input: &str, equation: &Equation, notes: &[String], expression: &Expression, ice: Option<&Ice>) -> String {
+        let numerator: Vec<String> = expression.numerator.iter().map(factor).collect();
+        let denominator: Vec<String> = expression.denominator.iter().map(factor).collect();
+        let excluded: Vec<String> = expression.excluded.iter().map(|term| string(&term.to_string())).collect();
+
+        let ice = match ice {
+            Some(ice) => {
+                let rows: Vec<String> = ice.rows.iter().map(|row| {
+                    format!("{{\"formula\":{},\"charge\":{},\"initial\":{},\"change\":{},\"equilibrium\":{}}}",
+                            string(&row.species.formula.to_string()), number(row.species.charge), float(row.initial), float(row.change), float(row.equilibrium))
+                }).collect();
+
+                format!("{{\"constant\":{},\"extent\":{},\"rows\":{}}}", float(ice.constant), float(ice.extent), array(&rows))
+            },
+            None => String::from("null")
+        };
+
+        format!("{{{},\"equation\":{},\"constant\":{},\"expression\":{},\"numerator\":{},\"denominator\":{},\"excluded\":{},\"ice\":{}}}",
+                header(None, input, notes), string(&equation.to_string()), string(expression.name()), string(&expression.to_string()),
+                array(&numerator), array(&denominator), array(&excluded), ice)
+    }
+
+    fn half_reaction(half: &HalfReaction) -> String {
+        format!("{{\"equation\":{},\"potential\":{},\"electrons\":{}}}", string(&half.equation.to_string()), float(half.potential), number(half.electrons))
+    }
+
+    // A single line JSON object with the half-reactions that make up a balanced redox reaction, its standard cell
+    // potential and Gibbs energy change, and the temperature and cell potential from the Nernst equation if found
+    pub fn cell(input: &str, equation: &Equation, notes: &[String], cell: &Cell, nernst: Option<(f64, f64)>) -> String {
+        let nernst = match nernst {
+            Some((temperature, potential)) => format!("{{\"temperature\":{},\"potential\":{}}}", float(temperature), float(potential)),
+            None => String::from("null")
+        };
+
+        format!("{{{},\"equation\":{},\"cathode\":{},\"anode\":{},\"electrons\":{},\"potential\":{},\"gibbs\":{},\"nernst\":{}}}",
+                header(None, input, notes), string(&equation.to_string()), half_reaction(&cell.cathode), half_reaction(&cell.anode),
+                number(cell.electrons), float(cell.potential), float(cell.gibbs()), nernst)
+    }
+
+    fn sample(sample: &Sample) -> String {
+        format!("{{\"formula\":{},\"charge\":{},\"molarity\":{},\"litres\":{},\"moles\":{}}}", string(&sample.species.formula.to_string()),
+                number(sample.species.charge), float(sample.molarity), float(sample.litres), float(sample.moles))
+    }
+
+    // A single line JSON object with both solutions of a titration at the equivalence point, and which value was found
+    pub fn titration(input: &str, equation: &Equation, result: &Titration) -> String {
+        let unknown = match result.unknown {
+            Unknown::AnalyteMolarity => "analyte molarity",
+            Unknown::AnalyteVolume => "analyte volume",
+            Unknown::TitrantMolarity => "titrant molarity",
+            Unknown::TitrantVolume => "titrant volume"
+        };
+
+        format!("{{{},\"equation\":{},\"analyte\":{},\"titrant\":{},\"unknown\":{}}}", header(None, input, &[]), string(&equation.to_string()),
+                sample(&result.analyte), sample(&result.titrant), string(unknown))
+    }
+
+    // A single line JSON object with an amount converted to another unit. The formula is null for a quantity converted
+    // without one, and the exact value is null unless every step of the conversion was exact
+    pub fn converted(input: &str, species: Option<&Term>, result: &Quantity) -> String {
+        let exact = match result.value {
+            Value::Exact(_) => string(&result.value.to_string()),
+            Value::Approximate(_) => String::from("null")
+        };
+
+        format!("{{{},\"formula\":{},\"value\":{},\"exact\":{},\"unit\":{}}}", header(None, input, &[]),
+                species.map(|species| string(&species.to_string())).unwrap_or_else(|| String::from("null")),
+                float(result.value.to_f64()), exact, string(&result.unit.to_string()))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        use crate::error::balance_error::ErrorKind;
+
+        #[test]
+        fn escapes_strings() {
+            assert_eq!(string("H2O"), "\"H2O\"");
+            assert_eq!(string("a \"b\"\\\n"), "\"a \\\"b\\\"\\\\\\n\"");
+            assert_eq!(string("\u{1}"), "\"\\u0001\"");
+        }
+
+        #[test]
+        fn writes_numbers() {
+            assert_eq!(number(Ratio::from_integer(-2)), "-2");
+            assert_eq!(number(Ratio::new(1, 4)), "0.25");
+            assert_eq!(number(Ratio::new(1, 3)), (1.0f64 / 3.0).to_string());
+            assert_eq!(float(2.5), "2.5");
+            assert_eq!(float(f64::NAN), "null");
+            assert_eq!(float(f64::NEG_INFINITY), "null");
+        }
+
+        #[test]
+        fn describes_balanced_equations() {
+            let equation = Equation::parse(b"2H2+O2=2H2O(l)").unwrap();
+
+            assert_eq!(balanced(Some(3), "H2 + O2 = H2O(l)", &equation, &[], None, None, Some(&[Class::Synthesis])),
+                       "{\"line\":3,\"input\":\"H2 + O2 = H2O(l)\",\"notes\":[],\"equation\":\"2H2+O2=2H2O(l)\",\"species\":[\
+                        {\"formula\":\"H2\",\"canonical\":\"H2\",\"side\":\"reactant\",\"coefficient\":2,\"elements\":{\"H\":2},\"charge\":0,\"state\":null},\
+                        {\"formula\":\"O2\",\"canonical\":\"O2\",\"side\":\"reactant\",\"coefficient\":1,\"elements\":{\"O\":2},\"charge\":0,\"state\":null},\
+                        {\"formula\":\"H2O\",\"canonical\":\"H2O\",\"side\":\"product\",\"coefficient\":2,\"elements\":{\"H\":2,\"O\":1},\"charge\":0,\"state\":\"l\"}],\
+                        \"types\":[\"synthesis\"]}");
+        }
+
+        #[test]
+        fn describes_errors() {
+            let parse = BalanceError { kind: ErrorKind::Parse, message: String::from("Unexpected ')'"), span: Some((2, 3)) };
+
+            assert_eq!(error(None, "H2)", &parse, &[]),
+                       "{\"input\":\"H2)\",\"notes\":[],\"error\":{\"code\":\"parse\",\"message\":\"Unexpected ')'\",\"span\":{\"start\":2,\"end\":3}}}");
+
+            let impossible = BalanceError::new(ErrorKind::Impossible, String::from("No solution"));
+
+            assert!(error(Some(1), "H2=O2", &impossible, &[]).ends_with("\"error\":{\"code\":\"impossible\",\"message\":\"No solution\",\"span\":null}}"));
+        }
+
+        #[test]
+        fn describes_checked_equations() {
+            let unbalanced = Equation::parse(b"H2+O2=H2O").unwrap();
+
+            assert_eq!(checked(None, "H2+O2=H2O", &unbalanced, &unbalanced.imbalance().unwrap(), &[String::from("Note")]),
+                       "{\"input\":\"H2+O2=H2O\",\"notes\":[\"Note\"],\"equation\":\"H2+O2=H2O\",\"balanced\":false,\"imbalance\":{\"O\":1}}");
+        }
+    }
+}
+
+pub mod latex {
+
+    use crate::ast::equation::{format_quantity, Equation, Term};
+
+    use num_rational::Ratio;
+
+    use num_traits::identities::{One, Zero};
+
+    //A term in mhchem notation, i.e. 2Fe^{3+}(aq) or e^{-}
+    fn term(term: &Term) -> String {
+        let mut result = String::new();
+
+        if term.coefficient != Ratio::one() {
+            result.push_str(&format_quantity(term.coefficient));
+        }
+
+        if term.formula.parts.is_empty() {
+            //A lone charge is an electron
+            result.push('e');
+        }
+        else {
+            result.push_str(&term.formula.to_string());
+        }
+
+        if term.charge != Ratio::zero() {
+            let (sign, magnitude) = if term.charge < Ratio::zero() { ("-", -term.charge) } else { ("+", term.charge) };
+
+            if magnitude == Ratio::one() {
+                result.push_str(&format!("^{{{}}}", sign));
+            }
+            else {
+                result.push_str(&format!("^{{{}{}}}", format_quantity(magnitude), sign));
+            }
+        }
+
+        if let Some(state) = term.state {
+            result.push_str(&state.to_string());
+        }
+
+        result
+    }
+
+    // The equation as an mhchem \ce command, i.e. \ce{2H2 + O2 -> 2H2O}. Hydrates use mhchem's * for the dot
+    pub fn equation(equation: &Equation) -> String {
+        let reactants: Vec<String> = equation.reactants.iter().map(term).collect();
+        let products: Vec<String> = equation.products.iter().map(term).collect();
+
+        format!("\\ce{{{} -> {}}}", reactants.join(" + "), products.join(" + "))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn parse(text: &str) -> String {
+            equation(&Equation::parse(text.as_bytes()).unwrap())
+        }
+
+        #[test]
+        fn writes_mhchem() {
+            assert_eq!(parse("2H2+O2=2H2O(l)"), "\\ce{2H2 + O2 -> 2H2O(l)}");
+            assert_eq!(parse("Fe{3+}(aq)+{-}=Fe{2+}(aq)"), "\\ce{Fe^{3+}(aq) + e^{-} -> Fe^{2+}(aq)}");
+            assert_eq!(parse("CuSO4*5H2O=CuSO4+5H2O"), "\\ce{CuSO4*5H2O -> CuSO4 + 5H2O}");
+            assert_eq!(parse("0.5O2+H2=H2O"), "\\ce{0.5O2 + H2 -> H2O}");
+        }
+    }
+}
+
+pub mod matrix {
+
+    use crate::solve::matrices::Augmented;
+
+    use num_rational::Ratio;
+
+    use num_traits::identities::Zero;
+
+    use std::fmt::Write;
+
+    // The formats a matrix can be exported in
+    #[derive(Clone, Copy, PartialEq)]
+    pub enum Format {
+        Csv,
+        MatrixMarket,
+        Latex
+    }
+
+    //A CSV field, quoted if it contains a comma, quote or line break
+    fn field(value: &str) -> String {
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        }
+        else {
+            String::from(value)
+        }
+    }
+
+    //The name of a row or column, or its number (from one) if it has no name
+    fn label(labels: &[String], index: usize) -> String {
+        labels.get(index).filter(|label| !label.is_empty()).cloned().unwrap_or_else(|| (index + 1).to_string())
+    }
+
+    // Comma separated values, with a header of species and each row starting with its element. Every entry is written
+    // as a decimal so spreadsheets read it as a number, i.e. -0.25, with fractions that do not terminate rounded
+    pub fn csv(matrix: &Augmented) -> String {
+        let coefficients = matrix.coefficients();
+
+        let width = coefficients.first().map(|row| row.len()).unwrap_or(0);
+
+        let mut result = String::from("element");
+
+        for column in 0..width {
+            write!(result, ",{}", field(&label(matrix.column_labels(), column))).unwrap();
+        }
+
+        result.push('\n');
+
+        for (index, row) in coefficients.iter().enumerate() {
+            result.push_str(&field(&label(matrix.row_labels(), index)));
+
+            for element in row {
+                write!(result, ",{}", *element.numer() as f64 / *element.denom() as f64).unwrap();
+            }
+
+            result.push('\n');
+        }
+
+        result
+    }
+
+    // Matrix Market coordinate format, listing each non zero entry as a real number. The title and the row and column
+    // names are written as comments
+    pub fn matrix_market(matrix: &Augmented, title: &str) -> String {
+        let coefficients = matrix.coefficients();
+
+        let width = coefficients.first().map(|row| row.len()).unwrap_or(0);
+
+        let mut entries = String::new();
+        let mut count = 0;
+
+        for (i, row) in coefficients.iter().enumerate() {
+            for (j, element) in row.iter().enumerate() {
+                if *element != Ratio::zero() {
+                    writeln!(entries, "{} {} {}", i + 1, j + 1, *element.numer() as f64 / *element.denom() as f64).unwrap();
+                    count += 1;
+                }
+            }
+        }
+
+        let rows: Vec<String> = (0..coefficients.len()).map(|index| label(matrix.row_labels(), index)).collect();
+        let columns: Vec<String> = (0..width).map(|index| label(matrix.column_labels(), index)).collect();
+
+        format!("%%MatrixMarket matrix coordinate real general\n% {}\n% Rows: {}\n% Columns: {}\n{} {} {}\n{}",
+                title, rows.join(" "), columns.join(" "), coefficients.len(), width, count, entries)
+    }
+
+    //A ratio in LaTeX, i.e. 2 or -\frac{1}{4}
+    fn latex_number(value: Ratio<i32>) -> String {
+        if value.is_integer() {
+            value.to_string()
+        }
+        else if value < Ratio::zero() {
+            format!("-\\frac{{{}}}{{{}}}", -*value.numer(), value.denom())
+        }
+        else {
+            format!("\\frac{{{}}}{{{}}}", value.numer(), value.denom())
+        }
+    }
+
+    // A LaTeX bmatrix (from amsmath), preceded by comments naming the rows and columns
+    pub fn latex(matrix: &Augmented) -> String {
+        let coefficients = matrix.coefficients();
+
+        let width = coefficients.first().map(|row| row.len()).unwrap_or(0);
+
+        let rows: Vec<String> = (0..coefficients.len()).map(|index| label(matrix.row_labels(), index)).collect();
+        let columns: Vec<String> = (0..width).map(|index| label(matrix.column_labels(), index)).collect();
+
+        let lines: Vec<String> = coefficients.iter()
+            .map(|row| row.iter().map(|element| latex_number(*element)).collect::<Vec<String>>().join(" & "))
+            .collect();
+
+        format!("% Rows: {}\n% Columns: {}\n\\begin{{bmatrix}}\n{}\n\\end{{bmatrix}}\n", rows.join(", "), columns.join(", "), lines.join(" \\\\\n"))
+    }
+
+    // The matrix in any of the formats. The title is only used by formats that have a place for it
+    pub fn export(matrix: &Augmented, format: Format, title: &str) -> String {
+        match format {
+            Format::Csv => csv(matrix),
+            Format::MatrixMarket => matrix_market(matrix, title),
+            Format::Latex => latex(matrix)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::ast::equation::Equation;
+        use crate::balance::balancer::composition_matrix;
+
+        fn reduced(equation: &str) -> Augmented {
+            let mut matrix = composition_matrix(&Equation::parse(equation.as_bytes()).unwrap(), false, & mut String::new()).unwrap();
+
+            matrix.row_reduce(None).unwrap();
+
+            matrix
+        }
+
+        #[test]
+        fn csv_writes_decimals() {
+            assert_eq!(csv(&reduced("Fe+O2=Fe2O3")), "element,Fe,O2,Fe2O3\nFe,1,0,-2\nO,0,1,-1.5\n");
+        }
+
+        #[test]
+        fn csv_quotes_labels_with_commas() {
+            assert_eq!(field("a,b"), "\"a,b\"");
+            assert_eq!(field("say \"hi\""), "\"say \"\"hi\"\"\"");
+            assert_eq!(field("H2O"), "H2O");
+        }
+
+        #[test]
+        fn latex_keeps_fractions() {
+            assert_eq!(latex(&reduced("Fe+O2=Fe2O3")), "% Rows: Fe, O\n% Columns: Fe, O2, Fe2O3\n\\begin{bmatrix}\n1 & 0 & -2 \\\\\n0 & 1 & -\\frac{3}{2}\n\\end{bmatrix}\n");
+        }
+
+        #[test]
+        fn matrix_market_lists_non_zero_entries() {
+            let exported = matrix_market(&reduced("Fe+O2=Fe2O3"), "title");
+
+            assert!(exported.starts_with("%%MatrixMarket matrix coordinate real general\n% title\n"));
+            assert!(exported.contains("\n2 3 4\n1 1 1\n1 3 -2\n2 2 1\n2 3 -1.5\n"));
+        }
+    }
+}
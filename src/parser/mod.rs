@@ -7,11 +7,11 @@ pub mod equation_parser {
 
     use num_rational::{Ratio, Rational32};
 
-    use num_traits::One;
+    use num_traits::{One, Zero};
 
     //Converts a string floating point number into a rational number
     fn float_string_to_ratio(st: &[u8]) -> Result<Rational32, String> {
-        if st.len() == 0 {
+        if st.is_empty() {
             Ok(Ratio::one())
         } else {
 
@@ -23,13 +23,18 @@ pub mod equation_parser {
                 Err(_) => {
 
                     match String::from_utf8_lossy(st).parse::<f64>() {
-                        Ok(decimal) => {
+                        Ok(_) => {
+
+                            //A valid float without a decimal point is an integer too large to fit in the ratio
+                            if !st.contains(&b'.') {
+                                return Err(format!("'{}' is too large", unsafe { std::str::from_utf8_unchecked(st) }));
+                            }
 
                             let mut decimal_point_index = 0;
 
                             // Get the index of the floating point
                             for ch in st {
-                                if *ch == '.' as u8 {
+                                if *ch == b'.' {
                                     break;
                                 }
                                 decimal_point_index += 1;
@@ -37,17 +42,27 @@ pub mod equation_parser {
 
                             let exponent = st.len() - decimal_point_index - 1;
 
-                            let denom = 10i32.pow(exponent as u32);
+                            let denom = match 10i32.checked_pow(exponent as u32) {
+                                Some(denom) => denom,
+                                None => return Err(format!("'{}' has too many decimal places", unsafe { std::str::from_utf8_unchecked(st) }))
+                            };
 
-                            let numer: i32 = (denom as f64 * decimal) as i32;
+                            //The digits either side of the point make the numerator exactly, where multiplying the float by the
+                            //denominator would truncate i.e. 0.29 to 28/100
+                            let mut numer: i32 = 0;
 
-                            //println!("Numer: {}, Demon: {}", numer, denom);
+                            for ch in st.iter().filter(|ch| **ch != b'.') {
+                                numer = match numer.checked_mul(10).and_then(|numer| numer.checked_add((*ch - b'0') as i32)) {
+                                    Some(numer) => numer,
+                                    None => return Err(format!("'{}' is too large", unsafe { std::str::from_utf8_unchecked(st) }))
+                                };
+                            }
 
                             Ok(Ratio::new(numer, denom))
                         },
                         Err(_) => {
                             //Invalid number
-                            Err(format!("'{}' is not a valid number", unsafe { std::str::from_utf8_unchecked(&st) }))
+                            Err(format!("'{}' is not a valid number", unsafe { std::str::from_utf8_unchecked(st) }))
                         }
                     }
                 }
@@ -63,7 +78,7 @@ pub mod equation_parser {
         //Locate first character in number
         for ch in tok_str {
 
-            if (*ch >= 48 && *ch <= 57) || *ch == '.' as u8 {
+            if (*ch >= 48 && *ch <= 57) || *ch == b'.' {
                 break;
             }
 
@@ -73,7 +88,7 @@ pub mod equation_parser {
         //Locate final character in number
         for ch in &tok_str[start..] {
 
-            if (*ch < 48 || *ch > 57) && *ch != '.' as u8 {
+            if (*ch < 48 || *ch > 57) && *ch != b'.' {
                 break;
             }
 
@@ -88,10 +103,16 @@ pub mod equation_parser {
 
     }
 
+    //An error for a quantity of zero, which would leave out the element or group it belongs to
+    fn zero_quantity(number: &[u8]) -> TokenType<'_> {
+        TokenType::Error(unsafe { std::str::from_utf8_unchecked(number) }, String::from("Quantities cannot be zero"))
+    }
+
     // Possible tokens returned when parsing a molecule
     pub enum TokenType<'a> {
         Symbol(& 'a [u8], & 'a str, Ratio<i32>), // A symbol, followed by an optional quantity, i.e. H, Na2, Mg3, etc.
-        Group(& 'a [u8], & 'a str, Ratio<i32>), // A series of tokens within brackets, with optional quantity, i.e. (OH)2, (CH3), (SO4)2, etc.
+        Group(& 'a [u8], & 'a str, Ratio<i32>), // A series of tokens within round or square brackets, with optional quantity, i.e. (OH)2, (CH3), [Fe(CN)6], etc.
+        Coefficient(& 'a str, Ratio<i32>), // A number at the start of a molecule, i.e. the 2 in 2H2O
         Separator(u8), //Molecule separator, either a plus or an equals
         Invalid(& 'a [u8]),
         Error(& 'a str, String)
@@ -125,22 +146,46 @@ pub mod equation_parser {
 
         fn next(&mut self) -> Option<Self::Item> {
 
-            if self._index == self._formula.len() {
+            if self._index >= self._formula.len() {
                 return None;
             }
 
             #[allow(non_snake_case)]
-            let COMPONENT_LIST: [TokenComponent; 4] = [
+            let COMPONENT_LIST: [TokenComponent; 5] = [
                 TokenComponent { //Symbol
                     _start_condition: |ch| *ch >= 65 && *ch <= 90,
-                    _end_condition: |ch| (*ch < 48 || *ch > 57) && (*ch < 97 || *ch > 122) && *ch != '.' as u8,
+                    _end_condition: |ch| (*ch < 48 || *ch > 57) && (*ch < 97 || *ch > 122) && *ch != b'.',
                     _parse: |tok_str: & 'a [u8]| {
 
                         match get_num_index(tok_str) {
+                            //Anything after the quantity would be lost, i.e. the e5 in H1e5
+                            Ok((num_start, num_len, _)) if num_start + num_len != tok_str.len() => {
+                                let rest = &tok_str[num_start+num_len..];
+
+                                TokenType::Error(unsafe { std::str::from_utf8_unchecked(rest) }, format!("Unexpected '{}' after a quantity", unsafe { std::str::from_utf8_unchecked(rest) }))
+                            },
+                            Ok((num_start, num_len, ratio)) if ratio.is_zero() =>
+                                zero_quantity(&tok_str[num_start..num_start+num_len]),
                             Ok((num_start, _, ratio)) =>
-                                TokenType::Symbol(&tok_str[..num_start], unsafe { std::str::from_utf8_unchecked(&tok_str) },ratio),
+                                TokenType::Symbol(&tok_str[..num_start], unsafe { std::str::from_utf8_unchecked(tok_str) },ratio),
                             Err(error) =>
-                                TokenType::Error(unsafe { std::str::from_utf8_unchecked(&tok_str) }, error)
+                                TokenType::Error(unsafe { std::str::from_utf8_unchecked(tok_str) }, error)
+                        }
+                    },
+                    _ignore_end: false
+                },
+                TokenComponent { //Coefficient
+                    _start_condition: |ch| (*ch >= 48 && *ch <= 57) || *ch == b'.',
+                    _end_condition: |ch| (*ch < 48 || *ch > 57) && *ch != b'.',
+                    _parse: |tok_str: & 'a [u8]| {
+
+                        match float_string_to_ratio(tok_str) {
+                            Ok(ratio) if ratio.is_zero() =>
+                                TokenType::Error(unsafe { std::str::from_utf8_unchecked(tok_str) }, String::from("Coefficients cannot be zero")),
+                            Ok(ratio) =>
+                                TokenType::Coefficient(unsafe { std::str::from_utf8_unchecked(tok_str) }, ratio),
+                            Err(error) =>
+                                TokenType::Error(unsafe { std::str::from_utf8_unchecked(tok_str) }, error)
                         }
                     },
                     _ignore_end: false
@@ -149,7 +194,13 @@ pub mod equation_parser {
                     _start_condition: |ch| *ch == 43 || *ch == 61,
                     _end_condition: |ch| *ch != 43 && *ch != 61,
                     _parse: |tok_str: & 'a [u8]| {
-                        TokenType::Separator(tok_str[0])
+                        if tok_str.len() > 1 {
+                            //Two operators in a row, i.e. H2++O2 or H2+=H2O
+                            TokenType::Error(unsafe { std::str::from_utf8_unchecked(tok_str) }, String::from("Operators must be separated by a molecule"))
+                        }
+                        else {
+                            TokenType::Separator(tok_str[0])
+                        }
                     },
                     _ignore_end: false
                 },
@@ -158,11 +209,22 @@ pub mod equation_parser {
                     _end_condition: |ch| *ch == 43 || *ch == 61,
                     _parse: |tok_str: & 'a [u8]| {
 
+                        if tok_str.len() == 1 {
+                            return TokenType::Error(unsafe { std::str::from_utf8_unchecked(tok_str) }, String::from("Empty hydrate"));
+                        }
+
+                        //The quantity must come straight after the *, otherwise it belongs to an element, i.e. *H2O
+                        if !(48..=57).contains(&tok_str[1]) && tok_str[1] != b'.' {
+                            return TokenType::Group(&tok_str[1..], unsafe { std::str::from_utf8_unchecked(tok_str) }, Ratio::one());
+                        }
+
                         match get_num_index(&tok_str[1..]) {
+                            Ok((num_start, num_len, ratio)) if ratio.is_zero() =>
+                                zero_quantity(&tok_str[num_start+1..num_start+num_len+1]),
                             Ok((_, num_end, ratio)) =>
-                                TokenType::Group(&tok_str[num_end+1..], unsafe { std::str::from_utf8_unchecked(&tok_str) },ratio),
+                                TokenType::Group(&tok_str[num_end+1..], unsafe { std::str::from_utf8_unchecked(tok_str) },ratio),
                             Err(error) =>
-                                TokenType::Error(unsafe { std::str::from_utf8_unchecked(&tok_str) }, error)
+                                TokenType::Error(unsafe { std::str::from_utf8_unchecked(tok_str) }, error)
                         }
 
 
@@ -176,6 +238,10 @@ pub mod equation_parser {
 
                         let truncated = &tok_str[1..];
 
+                        if truncated.is_empty() {
+                            return TokenType::Error("{}", String::from("Empty charge"));
+                        }
+
                         match get_num_index(truncated) {
                             Ok((start, end, mut ratio)) => {
 
@@ -193,6 +259,10 @@ pub mod equation_parser {
                                     0..start
                                 };
 
+                                if ratio.is_zero() {
+                                    return TokenType::Error(unsafe { std::str::from_utf8_unchecked(&truncated[start..start+end]) }, String::from("Charges cannot be zero"));
+                                }
+
                                 for ch in &truncated[sign_range] {
                                     if *ch == 45 {
                                         ratio *= -1;
@@ -204,13 +274,13 @@ pub mod equation_parser {
 
                                 }
 
-                                //println!("charge: {}", unsafe { std::str::from_utf8_unchecked(&tok_str) });
+                                //println!("charge: {}", unsafe { std::str::from_utf8_unchecked(tok_str) });
 
 
-                                TokenType::Symbol("charge".as_bytes(), unsafe { std::str::from_utf8_unchecked(&tok_str) },ratio)
+                                TokenType::Symbol("charge".as_bytes(), unsafe { std::str::from_utf8_unchecked(tok_str) },ratio)
                             }
                             Err(error) =>
-                                TokenType::Error(unsafe { std::str::from_utf8_unchecked(&tok_str) }, error)
+                                TokenType::Error(unsafe { std::str::from_utf8_unchecked(tok_str) }, error)
                         }
 
 
@@ -225,32 +295,61 @@ pub mod equation_parser {
 
             let mut index = self._index+1;
 
-            if first_char == 40 {
-                //If the first character is an open parenthesis, we search for the closing parenthesis and the optional quantity
-                let mut matching = 1;
+            if first_char == 40 || first_char == 91 {
+                //If the first character is an opening bracket, we search for the matching closing bracket and the optional quantity
+                //Round and square brackets may be nested inside each other, so we keep a stack of the open brackets
+                let mut matching = vec![first_char];
 
-                //Search for the closing parenthesis
+                //Search for the closing bracket
                 for ch in &self._formula[index..] {
-                    if *ch == 40 {
-                        matching += 1;
+                    if *ch == 40 || *ch == 91 {
+                        matching.push(*ch);
                     }
-                    else if *ch == 41 {
-                        matching -= 1;
+                    else if *ch == 41 || *ch == 93 {
+                        let open = matching.pop().unwrap();
+
+                        //A closing bracket must be the same shape as the most recently opened bracket
+                        if (open == 40 && *ch != 41) || (open == 91 && *ch != 93) {
+                            let str_slice = unsafe { std::str::from_utf8_unchecked(&self._formula[self._index..index+1]) };
+
+                            //Nothing after a mismatched bracket can be trusted, so end the iteration here
+                            self._index = self._formula.len();
+
+                            return Some(TokenType::Error(str_slice, format!("Mismatched brackets, '{}' closed by '{}'", open as char, *ch as char)));
+                        }
                     }
-                    if matching == 0 {
+                    if matching.is_empty() {
                         break
                     }
                     index += 1;
                 }
 
-                //println!("Par index: {}", index);
+                //If we ran off the end of the formula, the opening bracket was never closed
+                if !matching.is_empty() {
+                    let str_slice = unsafe { std::str::from_utf8_unchecked(&self._formula[self._index..]) };
+
+                    self._index = self._formula.len();
 
-                let next_char = self._formula[index+1];
+                    return Some(TokenType::Error(str_slice, format!("No closing bracket for '{}'", first_char as char)));
+                }
 
                 let slice = &self._formula[self._index+1..index];
 
+                if slice.is_empty() {
+                    let str_slice = unsafe { std::str::from_utf8_unchecked(&self._formula[self._index..index+1]) };
+
+                    self._index = self._formula.len();
+
+                    return Some(TokenType::Error(str_slice, String::from("Empty brackets")));
+                }
 
-                if (next_char < 48 || next_char > 57) && next_char != '.' as u8 {
+                //The closing bracket may be the last character in the formula, in which case there is no quantity
+                let next_char = match self._formula.get(index+1) {
+                    Some(ch) => *ch,
+                    None => 0
+                };
+
+                if !(48..=57).contains(&next_char) && next_char != b'.' {
                     let str_slice = unsafe { std::str::from_utf8_unchecked(&self._formula[self._index..index+1]) };
 
                     //Advance the iterator forward
@@ -260,6 +359,13 @@ pub mod equation_parser {
                 }
                 else {
                     match get_num_index(&self._formula[index+1..]) {
+                        Ok((_, last, ratio)) if ratio.is_zero() => {
+                            let number = &self._formula[index+1..index+last+1];
+
+                            self._index = self._formula.len();
+
+                            Some(zero_quantity(number))
+                        },
                         Ok((_, last, ratio)) => {
 
                             let str_slice = unsafe { std::str::from_utf8_unchecked(&self._formula[self._index..index+last+1]) };
@@ -270,7 +376,14 @@ pub mod equation_parser {
                             Some(TokenType::Group(slice, str_slice,ratio))
                         },
                         Err(error) => {
-                            Some(TokenType::Error(unsafe { std::str::from_utf8_unchecked(&self._formula[index+1..]) }, error))
+                            //Only report the group and its quantity, not the rest of the formula
+                            let num_len = self._formula[index+1..].iter().take_while(|ch| (**ch >= 48 && **ch <= 57) || **ch == b'.').count();
+
+                            let str_slice = unsafe { std::str::from_utf8_unchecked(&self._formula[self._index..index+num_len+1]) };
+
+                            self._index = self._formula.len();
+
+                            Some(TokenType::Error(str_slice, error))
                         }
                     }
                 }
@@ -294,8 +407,24 @@ pub mod equation_parser {
 
                         //println!("Index: {}", std::str::from_utf8(&self._formula[self._index..index]).unwrap());
 
+                        //If the component has ignore end set, the terminating character is required
+                        if component._ignore_end && index == self._formula.len() {
+                            let str_slice = unsafe { std::str::from_utf8_unchecked(&self._formula[self._index..]) };
+
+                            self._index = self._formula.len();
+
+                            return Some(TokenType::Error(str_slice, format!("No closing '}}' for '{}'", first_char as char)));
+                        }
+
                         result = Some((component._parse)(&self._formula[self._index..index]));
 
+                        //Operators must sit between two molecules
+                        if let Some(TokenType::Separator(sep)) = result {
+                            if self._index == 0 || index == self._formula.len() {
+                                result = Some(TokenType::Error(unsafe { std::str::from_utf8_unchecked(&self._formula[self._index..index]) }, format!("'{}' is missing a molecule on one side", sep as char)));
+                            }
+                        }
+
                         //If the component has ignore end set, increment index to push past ignroed character
                         if component._ignore_end {
                             index += 1;
@@ -310,6 +439,10 @@ pub mod equation_parser {
                 //If we haven't identified the token, return it as an invalid token
                 let result = if matched {
                     result
+                } else if first_char == 41 || first_char == 93 {
+                    index = self._formula.len();
+
+                    Some(TokenType::Error(unsafe { std::str::from_utf8_unchecked(&self._formula[self._index..self._index+1]) }, format!("No opening bracket for '{}'", first_char as char)))
                 } else {
                     Some(TokenType::Invalid(&self._formula[self._index..self._index+1]))
                 };
@@ -329,4 +462,79 @@ pub mod equation_parser {
 
     }
 
-}
\ No newline at end of file
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        //The text of every token, with errors and invalid tokens marked so they can be told apart
+        fn tokens(formula: &str) -> Vec<String> {
+            TokenIterator::new(formula.as_bytes()).map(|token| match token {
+                TokenType::Symbol(_, string, _) | TokenType::Group(_, string, _) | TokenType::Coefficient(string, _) => String::from(string),
+                TokenType::Separator(sep) => (sep as char).to_string(),
+                TokenType::Invalid(c) => format!("invalid {}", String::from_utf8_lossy(c)),
+                TokenType::Error(slice, error) => format!("error {} ({})", slice, error)
+            }).collect()
+        }
+
+        #[test]
+        fn splits_an_equation_into_tokens() {
+            assert_eq!(tokens("2H2O+[Fe(CN)6]{4-}=Na2"), vec!["2", "H2", "O", "+", "[Fe(CN)6]", "{4-", "=", "Na2"]);
+        }
+
+        #[test]
+        fn parses_quantities() {
+            match TokenIterator::new(b"Mg0.5").next() {
+                Some(TokenType::Symbol(symbol, _, quantity)) => {
+                    assert_eq!(symbol, b"Mg");
+                    assert_eq!(quantity, Ratio::new(1, 2));
+                },
+                _ => panic!("Expected a symbol")
+            }
+        }
+
+        #[test]
+        fn parses_decimal_quantities_exactly() {
+            let quantity = |formula: &str| match TokenIterator::new(formula.as_bytes()).next() {
+                Some(TokenType::Symbol(_, _, quantity)) => quantity,
+                _ => panic!("Expected a symbol")
+            };
+
+            assert_eq!(quantity("H0.29"), Ratio::new(29, 100));
+            assert_eq!(quantity("H0.57"), Ratio::new(57, 100));
+            assert_eq!(quantity("H1.1"), Ratio::new(11, 10));
+            assert_eq!(quantity("H.5"), Ratio::new(1, 2));
+            assert_eq!(quantity("H99999999.5"), Ratio::new(199999999, 2));
+            assert_eq!(tokens("H9999999999.5"), vec!["error H9999999999.5 ('9999999999.5' is too large)"]);
+        }
+
+        #[test]
+        fn reports_unmatched_brackets() {
+            assert_eq!(tokens("Fe(OH+H2"), vec!["Fe", "error (OH+H2 (No closing bracket for '(')"]);
+            assert_eq!(tokens("H2O)"), vec!["H2", "O", "error ) (No opening bracket for ')')"]);
+            assert_eq!(tokens("[Fe(CN]6)"), vec!["error [Fe(CN] (Mismatched brackets, '(' closed by ']')"]);
+            assert_eq!(tokens("()"), vec!["error () (Empty brackets)"]);
+        }
+
+        #[test]
+        fn reports_misplaced_operators() {
+            assert_eq!(tokens("H2++O2"), vec!["H2", "error ++ (Operators must be separated by a molecule)", "O2"]);
+            assert_eq!(tokens("H2+"), vec!["H2", "error + ('+' is missing a molecule on one side)"]);
+            assert_eq!(tokens("=H2"), vec!["error = ('=' is missing a molecule on one side)", "H2"]);
+        }
+
+        #[test]
+        fn reports_bad_numbers() {
+            assert_eq!(tokens("H99999999999"), vec!["error H99999999999 ('99999999999' is too large)"]);
+            assert_eq!(tokens("H2.5.5"), vec!["error H2.5.5 ('2.5.5' is not a valid number)"]);
+            assert_eq!(tokens("H{2+"), vec!["H", "error {2+ (No closing '}' for '{')"]);
+        }
+
+        #[test]
+        fn rejects_zero_quantities() {
+            assert_eq!(tokens("H0"), vec!["error 0 (Quantities cannot be zero)"]);
+            assert_eq!(tokens("(OH)0"), vec!["error 0 (Quantities cannot be zero)"]);
+            assert_eq!(tokens("*0H2O"), vec!["error 0 (Quantities cannot be zero)"]);
+            assert_eq!(tokens("0H2"), vec!["error 0 (Coefficients cannot be zero)", "H2"]);
+        }
+    }
+}
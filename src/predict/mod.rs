@@ -0,0 +1,434 @@
+pub mod combustion {
+
+    use crate::ast::equation::{parse_group, ElementTable, Equation, Term};
+    use crate::error::balance_error::{BalanceError, ErrorKind};
+
+    use num_rational::Ratio;
+
+    use num_traits::identities::{One, Zero};
+
+    // What nitrogen in a fuel is given off as
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Nitrogen {
+        N2,
+        NO,
+        NO2
+    }
+
+    impl Nitrogen {
+        pub fn formula(&self) -> &'static str {
+            match self {
+                Nitrogen::N2 => "N2",
+                Nitrogen::NO => "NO",
+                Nitrogen::NO2 => "NO2"
+            }
+        }
+    }
+
+    //The product each element of a fuel burns to, with carbon given off as CO if the combustion is incomplete
+    fn product(element: &str, nitrogen: Nitrogen, incomplete: bool) -> Option<&'static str> {
+        match element {
+            "C" if incomplete => Some("CO"),
+            "C" => Some("CO2"),
+            "H" => Some("H2O"),
+            "S" => Some("SO2"),
+            "N" => Some(nitrogen.formula()),
+            _ => None
+        }
+    }
+
+    // The products of burning a fuel with the given quantity of each element (and charge) from parse_group, i.e. CO2,
+    // H2O, SO2 and N2. The fuel must be neutral, contain carbon or hydrogen, and contain nothing but carbon, hydrogen,
+    // oxygen, nitrogen and sulfur
+    pub fn products(elements: &ElementTable, nitrogen: Nitrogen, incomplete: bool) -> Option<Vec<Term>> {
+        let present: Vec<&str> = elements.iter().filter(|(_, quantity)| *quantity != Ratio::zero()).map(|(element, _)| element).collect();
+
+        let burns = (present.contains(&"C") || present.contains(&"H"))
+            && present.iter().all(|element| *element == "O" || product(element, nitrogen, incomplete).is_some());
+
+        if !burns {
+            return None;
+        }
+
+        //Products in a fixed order, whatever order the elements are written in
+        Some(["C", "H", "S", "N"].iter()
+            .filter(|element| present.contains(element))
+            //Every product is a valid formula
+            .map(|element| Term::parse(product(element, nitrogen, incomplete).unwrap().as_bytes()).unwrap())
+            .collect())
+    }
+
+    // The unbalanced combustion equation of a fuel written as any formula, i.e. C6H14 or CH3(CH2)4CH3, burning in O2
+    pub fn equation(fuel: &Term, nitrogen: Nitrogen, incomplete: bool) -> Result<Equation, BalanceError> {
+        let mut elements = ElementTable::new();
+
+        parse_group(fuel, & mut elements, Ratio::one())?;
+
+        let products = products(&elements, nitrogen, incomplete)
+            .ok_or_else(|| BalanceError::new(ErrorKind::Input, format!("{} cannot be burned, fuels must be neutral and contain carbon or hydrogen, \
+                and only carbon, hydrogen, oxygen, nitrogen and sulfur", fuel)))?;
+
+        let mut fuel = fuel.clone();
+
+        fuel.coefficient = Ratio::one();
+
+        //O2 is always a valid formula
+        Ok(Equation {
+            reactants: vec![fuel, Term::parse(b"O2").unwrap()],
+            products
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn burn(fuel: &str, nitrogen: Nitrogen, incomplete: bool) -> Result<String, BalanceError> {
+            equation(&Term::parse(fuel.as_bytes()).unwrap(), nitrogen, incomplete).map(|equation| equation.to_string())
+        }
+
+        #[test]
+        fn predicts_combustion_products() {
+            assert_eq!(burn("CH3(CH2)4CH3", Nitrogen::N2, false).unwrap(), "CH3(CH2)4CH3+O2=CO2+H2O");
+            assert_eq!(burn("2C2H5SH", Nitrogen::N2, true).unwrap(), "C2H5SH+O2=CO+H2O+SO2");
+            assert_eq!(burn("CH3NH2", Nitrogen::NO2, false).unwrap(), "CH3NH2+O2=CO2+H2O+NO2");
+            assert_eq!(burn("H2", Nitrogen::N2, false).unwrap(), "H2+O2=H2O");
+        }
+
+        #[test]
+        fn rejects_fuels_that_cannot_burn() {
+            assert_eq!(burn("NaCl", Nitrogen::N2, false).unwrap_err().kind, ErrorKind::Input);
+            assert_eq!(burn("CH3COO{-}", Nitrogen::N2, false).unwrap_err().kind, ErrorKind::Input);
+            assert_eq!(burn("N2", Nitrogen::N2, false).unwrap_err().kind, ErrorKind::Input);
+        }
+    }
+}
+
+pub mod products {
+
+    use crate::ast::equation::{parse_group, ElementTable, Equation, State, Term};
+    use crate::classify::ions::{self, Salt, CATIONS};
+    use crate::classify::reaction::{acid, base};
+    use crate::error::balance_error::{BalanceError, ErrorKind};
+    use crate::predict::combustion::{self, Nitrogen};
+
+    use num_rational::Ratio;
+
+    use num_traits::identities::{One, Zero};
+
+    // The kind of reaction that products were predicted for
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Rule {
+        Combustion,
+        Neutralisation,
+        SingleDisplacement,
+        DoubleDisplacement
+    }
+
+    impl Rule {
+        // What was assumed to predict the products
+        pub fn description(&self) -> &'static str {
+            match self {
+                Rule::Combustion => "complete combustion",
+                Rule::Neutralisation => "acid-base neutralisation",
+                Rule::SingleDisplacement => "single displacement, using the activity series",
+                Rule::DoubleDisplacement => "double displacement, using the solubility rules"
+            }
+        }
+    }
+
+    // An equation with predicted products, which has not been balanced
+    pub struct Prediction {
+        pub rule: Rule,
+        pub equation: Equation
+    }
+
+    //Metals and hydrogen, most reactive first. Each displaces those after it from their compounds
+    const ACTIVITY: [&str; 22] = [
+        "Li", "K", "Ba", "Sr", "Ca", "Na", "Mg", "Al", "Mn", "Zn", "Cr", "Fe", "Cd", "Co", "Ni", "Sn", "Pb", "H", "Cu", "Hg", "Ag", "Au"
+    ];
+
+    //Halogens, most reactive first
+    const HALOGENS: [&str; 4] = ["F", "Cl", "Br", "I"];
+
+    // The reactants of an equation written without products, i.e. CH4+O2=? or CH4+O2=, with whitespace removed
+    pub fn unfinished(equation: &str) -> Option<&str> {
+        let reactants = equation.strip_suffix("=?").or_else(|| equation.strip_suffix('='))?;
+
+        if reactants.contains('=') { None } else { Some(reactants) }
+    }
+
+    //A term from a formula that is known to be valid
+    fn term(formula: &str) -> Term {
+        Term::parse(formula.as_bytes()).unwrap()
+    }
+
+    //The symbol of a term made of a single element, i.e. Zn, Cl2 or Cu{2+}
+    fn symbol(term: &Term) -> Option<String> {
+        let elements = term.formula.elements();
+
+        if elements.len() == 1 { elements.keys().next().map(String::from) } else { None }
+    }
+
+    //Complete combustion of a fuel made of carbon, hydrogen, oxygen, nitrogen and sulfur, to CO2, H2O, SO2 and N2
+    fn complete_combustion(reactants: &[Term]) -> Option<Vec<Term>> {
+        let fuel = match reactants {
+            [oxygen, fuel] | [fuel, oxygen] if oxygen.canonical() == "O2" && fuel.canonical() != "O2" => fuel,
+            _ => return None
+        };
+
+        let mut elements = ElementTable::new();
+
+        parse_group(fuel, & mut elements, Ratio::one()).ok()?;
+
+        combustion::products(&elements, Nitrogen::N2, false)
+    }
+
+    //The anion left when a neutral acid gives up all of its hydrogen, with its charge, i.e. H2SO4 gives SO4 and -2
+    fn conjugate(acid: &Term) -> Option<(&'static str, i32)> {
+        let elements = acid.formula.elements();
+
+        ions::ANIONS.iter()
+            .filter(|(anion, charge)| {
+                let anion = term(anion);
+                let anion = anion.formula.elements();
+
+                let hydrogen = |element: &str| if element == "H" { Ratio::from_integer(-charge) } else { Ratio::zero() };
+
+                let extra = if anion.contains("H") { 0 } else { 1 };
+
+                elements.len() == anion.len() + extra
+                    && elements.iter().all(|(element, quantity)| quantity == anion.get(element).unwrap_or_else(Ratio::zero) + hydrogen(element))
+            })
+            .min_by_key(|(_, charge)| *charge)
+            .map(|(anion, charge)| (*anion, *charge))
+    }
+
+    //The cation a base gives and whether water is formed, i.e. NaOH gives Na{+} and water, NH3 gives NH4{+} and OH{-}
+    //gives water alone
+    fn base_cation(base: &Term) -> Option<(Option<(&'static str, i32)>, bool)> {
+        match base.canonical().as_str() {
+            "HO{-}" => Some((None, true)),
+            "H3N" => Some((Some(("NH4", 1)), false)),
+            _ => ions::salt(base).filter(|salt| salt.anion == "OH").map(|salt| (Some((salt.cation, salt.cation_charge)), true))
+        }
+    }
+
+    //An acid and a base give a salt and water, i.e. HCl and NaOH give NaCl and H2O. Ions are kept as ions, so H{+} and
+    //OH{-} give H2O
+    fn neutralisation(reactants: &[Term]) -> Option<Vec<Term>> {
+        let (proton, hydroxide) = match reactants {
+            [first, second] | [second, first] if acid(first) && base(second) => (first, second),
+            _ => return None
+        };
+
+        let anion = if proton.charge == Ratio::zero() { Some(conjugate(proton)?) } else { None };
+
+        let (cation, water) = base_cation(hydroxide)?;
+
+        let mut products = Vec::with_capacity(2);
+
+        match (cation, anion) {
+            (Some((cation, cation_charge)), Some((anion, anion_charge))) => products.push(term(&Salt { cation, cation_charge, anion, anion_charge }.formula())),
+            (Some((cation, charge)), None) => products.push(term(&ions::ion(cation, charge))),
+            (None, Some((anion, charge))) => products.push(term(&ions::ion(anion, charge))),
+            (None, None) => {}
+        }
+
+        if water || proton.canonical() == "H3O{+}" {
+            products.push(term("H2O"));
+        }
+
+        Some(products)
+    }
+
+    //The error for reactants that are predicted not to react
+    fn no_reaction(message: String) -> BalanceError {
+        BalanceError::new(ErrorKind::Impossible, format!("No reaction is predicted, {}", message))
+    }
+
+    //A free element displaces a less reactive one from a compound or ion. Metals (and hydrogen) follow the activity
+    //series, i.e. Zn and CuSO4 give ZnSO4 and Cu, and halogens displace heavier halogens, i.e. Cl2 and NaBr give NaCl
+    //and Br2
+    fn single_displacement(reactants: &[Term]) -> Result<Option<Vec<Term>>, BalanceError> {
+        let (free, compound) = match reactants {
+            [free, compound] | [compound, free] if free.charge == Ratio::zero() && symbol(free).is_some() && symbol(compound).is_none() => (free, compound),
+            [free, compound] | [compound, free] if free.charge == Ratio::zero() && symbol(free).is_some() && compound.charge != Ratio::zero() => (free, compound),
+            _ => return Ok(None)
+        };
+
+        //Both are single elements here
+        let element = symbol(free).unwrap();
+        let ion = if compound.charge != Ratio::zero() { symbol(compound) } else { None };
+
+        let rank = |series: &[&str], symbol: &str| series.iter().position(|found| *found == symbol);
+
+        if let Some(free_rank) = rank(&HALOGENS, &element) {
+            //The halide taken from the compound, and the cation it was with if it was not a lone ion
+            let (target, cation) = match (&ion, ions::salt(compound)) {
+                (Some(target), _) if compound.charge < Ratio::zero() => (target.clone(), None),
+                (None, Some(salt)) if HALOGENS.contains(&salt.anion) => (String::from(salt.anion), Some((salt.cation, salt.cation_charge))),
+                _ => return Ok(None)
+            };
+
+            let target_rank = match rank(&HALOGENS, &target) {
+                Some(target_rank) => target_rank,
+                None => return Ok(None)
+            };
+
+            if free_rank >= target_rank {
+                return Err(no_reaction(format!("{} is not more reactive than {}", free, term(&format!("{}2", target)))));
+            }
+
+            //Every halogen in the list is a common anion
+            let anion = ions::ANIONS.iter().find(|(anion, _)| *anion == element).unwrap().0;
+
+            let salt = match cation {
+                Some((cation, cation_charge)) => Salt { cation, cation_charge, anion, anion_charge: -1 }.formula(),
+                None => ions::ion(anion, -1)
+            };
+
+            return Ok(Some(vec![term(&salt), term(&format!("{}2", target))]));
+        }
+
+        let (metal, charge) = match CATIONS.iter().find(|(cation, _)| *cation == element) {
+            Some(found) if rank(&ACTIVITY, &element).is_some() => *found,
+            _ => return Ok(None)
+        };
+
+        //The cation taken from the compound, and the anion it was with if it was not a lone ion
+        let (target, anion) = match (&ion, ions::salt(compound)) {
+            (Some(target), _) if compound.charge > Ratio::zero() => (target.clone(), None),
+            (None, Some(salt)) => (String::from(salt.cation), Some((salt.anion, salt.anion_charge))),
+            (None, None) if acid(compound) => match conjugate(compound) {
+                Some(anion) => (String::from("H"), Some(anion)),
+                None => return Ok(None)
+            },
+            _ => return Ok(None)
+        };
+
+        let (free_rank, target_rank) = match (rank(&ACTIVITY, &element), rank(&ACTIVITY, &target)) {
+            (Some(free_rank), Some(target_rank)) => (free_rank, target_rank),
+            _ => return Ok(None)
+        };
+
+        let displaced = if target == "H" { String::from("H2") } else { target };
+
+        if free_rank >= target_rank {
+            return Err(no_reaction(format!("{} is less reactive than {} in the activity series", free, displaced)));
+        }
+
+        let product = match anion {
+            Some((anion, anion_charge)) => Salt { cation: metal, cation_charge: charge, anion, anion_charge }.formula(),
+            None => ions::ion(metal, charge)
+        };
+
+        Ok(Some(vec![term(&product), term(&displaced)]))
+    }
+
+    //Two salts swap ions, which only happens if one of the new salts is insoluble and comes out of solution as a
+    //solid, i.e. AgNO3 and NaCl give AgCl(s) and NaNO3
+    fn double_displacement(reactants: &[Term]) -> Result<Option<Vec<Term>>, BalanceError> {
+        let (first, second) = match reactants {
+            [first, second] => match (ions::salt(first), ions::salt(second)) {
+                (Some(first), Some(second)) if first.cation != second.cation && first.anion != second.anion => (first, second),
+                _ => return Ok(None)
+            },
+            _ => return Ok(None)
+        };
+
+        let swapped = [
+            Salt { anion: second.anion, anion_charge: second.anion_charge, ..first },
+            Salt { anion: first.anion, anion_charge: first.anion_charge, ..second }
+        ];
+
+        if swapped.iter().all(ions::soluble) {
+            return Err(no_reaction(format!("{} and {} are both soluble", term(&swapped[0].formula()), term(&swapped[1].formula()))));
+        }
+
+        Ok(Some(swapped.iter().map(|salt| {
+            let mut product = term(&salt.formula());
+
+            if !ions::soluble(salt) {
+                product.state = Some(State::Solid);
+            }
+
+            product
+        }).collect()))
+    }
+
+    // Predict the products of a reaction from its reactants, for complete combustion, acid-base neutralisation, single
+    // displacement and double displacement. Reactants that fit none of these, or are predicted not to react, are an
+    // Impossible error
+    pub fn predict(reactants: Vec<Term>) -> Result<Prediction, BalanceError> {
+        let reactants: Vec<Term> = reactants.into_iter().map(|mut term| {
+            term.coefficient = Ratio::one();
+            term
+        }).collect();
+
+        let (rule, products) = if let Some(products) = complete_combustion(&reactants) {
+            (Rule::Combustion, products)
+        }
+        else if let Some(products) = neutralisation(&reactants) {
+            (Rule::Neutralisation, products)
+        }
+        else if let Some(products) = single_displacement(&reactants)? {
+            (Rule::SingleDisplacement, products)
+        }
+        else if let Some(products) = double_displacement(&reactants)? {
+            (Rule::DoubleDisplacement, products)
+        }
+        else {
+            let written: Vec<String> = reactants.iter().map(|term| term.to_string()).collect();
+
+            //As far as these rules can tell, the reactants do not react
+            return Err(BalanceError::new(ErrorKind::Impossible, format!("The products of {} cannot be predicted, only complete combustion, \
+                acid-base neutralisation, single displacement and double displacement are supported", written.join(" + "))));
+        };
+
+        Ok(Prediction {
+            rule,
+            equation: Equation {
+                reactants,
+                products
+            }
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn products(equation: &str) -> Result<(Rule, String), BalanceError> {
+            let reactants = Equation::parse_side(unfinished(equation).unwrap().as_bytes())?;
+
+            predict(reactants).map(|prediction| (prediction.rule, prediction.equation.to_string()))
+        }
+
+        #[test]
+        fn finds_unfinished_equations() {
+            assert_eq!(unfinished("CH4+O2=?"), Some("CH4+O2"));
+            assert_eq!(unfinished("CH4+O2="), Some("CH4+O2"));
+            assert_eq!(unfinished("CH4+O2=CO2+H2O"), None);
+            assert_eq!(unfinished("CH4=O2=?"), None);
+        }
+
+        #[test]
+        fn predicts_products() {
+            assert_eq!(products("CH4+O2=?").unwrap(), (Rule::Combustion, String::from("CH4+O2=CO2+H2O")));
+            assert_eq!(products("HCl+NaOH=?").unwrap(), (Rule::Neutralisation, String::from("HCl+NaOH=NaCl+H2O")));
+            assert_eq!(products("H2SO4+NH3=?").unwrap(), (Rule::Neutralisation, String::from("H2SO4+NH3=(NH4)2SO4")));
+            assert_eq!(products("Zn+CuSO4=?").unwrap(), (Rule::SingleDisplacement, String::from("Zn+CuSO4=ZnSO4+Cu")));
+            assert_eq!(products("Cl2+NaBr=?").unwrap(), (Rule::SingleDisplacement, String::from("Cl2+NaBr=NaCl+Br2")));
+            assert_eq!(products("AgNO3+NaCl=?").unwrap(), (Rule::DoubleDisplacement, String::from("AgNO3+NaCl=AgCl(s)+NaNO3")));
+        }
+
+        #[test]
+        fn reports_no_reaction_as_impossible() {
+            assert_eq!(products("Cu+ZnSO4=?").unwrap_err().kind, ErrorKind::Impossible);
+            assert_eq!(products("I2+NaCl=?").unwrap_err().kind, ErrorKind::Impossible);
+            assert_eq!(products("NaCl+KNO3=?").unwrap_err().kind, ErrorKind::Impossible);
+            assert_eq!(products("He+Ne=?").unwrap_err().kind, ErrorKind::Impossible);
+        }
+    }
+}
@@ -0,0 +1,281 @@
+pub mod oxidation {
+
+    use crate::ast::equation::{Equation, Term};
+
+    use num_rational::Ratio;
+
+    use num_traits::identities::Zero;
+
+    // How an element's oxidation state changes across an equation. Lost and gained are the electrons (per equation as
+    // written) lost by atoms of the element that are oxidised, and gained by atoms that are reduced
+    pub struct Change {
+        pub element: String,
+        pub before: Vec<Ratio<i32>>, // Oxidation states of the element in the reactants, smallest first
+        pub after: Vec<Ratio<i32>>, // Oxidation states of the element in the products, smallest first
+        pub transitions: Vec<(Ratio<i32>, Ratio<i32>)>, // Each state atoms go from and to, leaving out those that stay the same
+        pub lost: Ratio<i32>,
+        pub gained: Ratio<i32>
+    }
+
+    // The oxidation state of each element in a molecule, None if it could not be found
+    pub type States = Vec<(String, Option<Ratio<i32>>)>;
+
+    //Elements with a fixed oxidation state in compounds, in the order the rules are applied. The element whose rule
+    //comes last (or which has no rule) takes whatever state is needed to give the charge, so i.e. O is -1 in H2O2
+    const RULES: [(&str, i32); 19] = [
+        ("F", -1),
+        ("Li", 1), ("Na", 1), ("K", 1), ("Rb", 1), ("Cs", 1), ("Fr", 1),
+        ("Be", 2), ("Mg", 2), ("Ca", 2), ("Sr", 2), ("Ba", 2), ("Ra", 2),
+        ("Al", 3),
+        ("H", 1),
+        ("O", -2),
+        ("Cl", -1), ("Br", -1), ("I", -1)
+    ];
+
+    fn rule(element: &str) -> Option<(usize, i32)> {
+        RULES.iter().position(|(symbol, _)| *symbol == element).map(|index| (index, RULES[index].1))
+    }
+
+    // The oxidation state of each element in a molecule or ion, in alphabetical order. A state is None if it cannot be
+    // found with the simple rules, i.e. when more than one element has no rule, as with C and N in HCN
+    pub fn states(term: &Term) -> States {
+        let elements = term.formula.elements();
+
+        let mut symbols: Vec<&str> = elements.iter().filter(|(_, quantity)| *quantity != Ratio::zero()).map(|(element, _)| element).collect();
+
+        //Elements in the order their rules are applied, with elements without a rule last
+        symbols.sort_by_key(|element| (rule(element).map(|(index, _)| index).unwrap_or(RULES.len()), *element));
+
+        let unknown = symbols.iter().filter(|element| rule(element).is_none()).count();
+
+        let mut result = Vec::with_capacity(symbols.len());
+
+        let mut remaining = term.charge;
+
+        for (index, element) in symbols.iter().enumerate() {
+            let state = match rule(element) {
+                //The last element balances the charge, everything before it follows its rule
+                _ if index + 1 == symbols.len() && unknown <= 1 => Some(remaining / elements[element]),
+                Some((_, state)) => Some(Ratio::from_integer(state)),
+                None => None
+            };
+
+            if let Some(state) = state {
+                remaining -= state * elements[element];
+            }
+
+            result.push((String::from(*element), state));
+        }
+
+        result.sort_by(|a, b| a.0.cmp(&b.0));
+
+        result
+    }
+
+    //The oxidation state and number of atoms of an element, for each molecule on one side it appears in
+    fn atoms(terms: &[Term], element: &str) -> Vec<(Ratio<i32>, Ratio<i32>)> {
+        let mut found = Vec::new();
+
+        for term in terms {
+            let elements = term.formula.elements();
+
+            if let Some(count) = elements.get(element) {
+                for (symbol, state) in states(term) {
+                    if symbol == element {
+                        if let Some(state) = state {
+                            found.push((state, count * term.coefficient));
+                        }
+                    }
+                }
+            }
+        }
+
+        found
+    }
+
+    //The total number of atoms in each state, smallest state first
+    fn tally(atoms: &[(Ratio<i32>, Ratio<i32>)]) -> Vec<(Ratio<i32>, Ratio<i32>)> {
+        let mut result: Vec<(Ratio<i32>, Ratio<i32>)> = Vec::new();
+
+        for (state, count) in atoms {
+            match result.iter_mut().find(|(found, _)| found == state) {
+                Some((_, total)) => *total += *count,
+                None => result.push((*state, *count))
+            }
+        }
+
+        result.sort();
+
+        result
+    }
+
+    // The elements whose oxidation state changes in a balanced equation, and the elements whose oxidation state could
+    // not be found. Atoms that keep their state are ignored, then if the rest of an element's atoms have a single state
+    // on one side, every atom on the other side is compared with it. This finds disproportionation (i.e. Cl2 to Cl{-}
+    // and ClO3{-}) and comproportionation (i.e. NaH and H2O to H2). Otherwise only the overall change is known
+    pub fn changes(equation: &Equation) -> (Vec<Change>, Vec<String>) {
+        let mut symbols: Vec<String> = Vec::new();
+
+        for term in equation.terms() {
+            for element in term.formula.elements().keys() {
+                if !symbols.iter().any(|symbol| symbol == element) {
+                    symbols.push(String::from(element));
+                }
+            }
+        }
+
+        symbols.sort();
+
+        let mut unknown = Vec::new();
+        let mut result = Vec::new();
+
+        for element in symbols {
+            let undetermined = equation.terms()
+                .any(|term| states(term).iter().any(|(symbol, state)| *symbol == element && state.is_none()));
+
+            if undetermined {
+                unknown.push(element);
+                continue;
+            }
+
+            let mut before = tally(&atoms(&equation.reactants, &element));
+            let mut after = tally(&atoms(&equation.products, &element));
+
+            let before_states: Vec<Ratio<i32>> = before.iter().map(|(state, _)| *state).collect();
+            let after_states: Vec<Ratio<i32>> = after.iter().map(|(state, _)| *state).collect();
+
+            //Atoms in the same state on both sides take no part in the reaction
+            for (state, count) in before.iter_mut() {
+                if let Some((_, other)) = after.iter_mut().find(|(found, _)| found == state) {
+                    let common = std::cmp::min(*count, *other);
+
+                    *count -= common;
+                    *other -= common;
+                }
+            }
+
+            before.retain(|(_, count)| *count != Ratio::zero());
+            after.retain(|(_, count)| *count != Ratio::zero());
+
+            let mut lost = Ratio::zero();
+            let mut gained = Ratio::zero();
+
+            let mut transitions = Vec::new();
+
+            //Compare every atom on one side with the single state on the other
+            let (single, others, sign) = if before.len() == 1 {
+                (Some(before[0].0), &after, 1)
+            }
+            else if after.len() == 1 {
+                (Some(after[0].0), &before, -1)
+            }
+            else {
+                (None, &after, 1)
+            };
+
+            match single {
+                Some(single) => {
+                    for (state, count) in others.iter() {
+                        transitions.push(if sign == 1 { (single, *state) } else { (*state, single) });
+
+                        let difference = (*state - single) * Ratio::from_integer(sign) * *count;
+
+                        if difference > Ratio::zero() {
+                            lost += difference;
+                        }
+                        else {
+                            gained -= difference;
+                        }
+                    }
+                },
+                None => {
+                    //Which atoms go to which state is not known, so every pair of states is given
+                    for (from, _) in before.iter() {
+                        transitions.extend(after.iter().map(|(to, _)| (*from, *to)));
+                    }
+
+                    let total = |atoms: &[(Ratio<i32>, Ratio<i32>)]| atoms.iter().fold(Ratio::zero(), |sum, (state, count)| sum + *state * *count);
+
+                    let difference = total(&after) - total(&before);
+
+                    if difference > Ratio::zero() {
+                        lost = difference;
+                    }
+                    else {
+                        gained = -difference;
+                    }
+                }
+            }
+
+            if lost != Ratio::zero() || gained != Ratio::zero() {
+                result.push(Change {
+                    element,
+                    before: before_states,
+                    after: after_states,
+                    transitions,
+                    lost,
+                    gained
+                });
+            }
+        }
+
+        (result, unknown)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn parse(formula: &str) -> States {
+            states(&Term::parse(formula.as_bytes()).unwrap())
+        }
+
+        fn state(value: i32) -> Option<Ratio<i32>> {
+            Some(Ratio::from_integer(value))
+        }
+
+        fn electrons(equation: &str) -> Vec<(String, i32, i32)> {
+            changes(&Equation::parse(equation.as_bytes()).unwrap()).0.into_iter()
+                .map(|change| (change.element, change.lost.to_integer(), change.gained.to_integer()))
+                .collect()
+        }
+
+        #[test]
+        fn finds_oxidation_states() {
+            assert_eq!(parse("H2O2"), vec![(String::from("H"), state(1)), (String::from("O"), state(-1))]);
+            assert_eq!(parse("Cr2O7{2-}"), vec![(String::from("Cr"), state(6)), (String::from("O"), state(-2))]);
+            assert_eq!(parse("NaH"), vec![(String::from("H"), state(-1)), (String::from("Na"), state(1))]);
+            assert_eq!(parse("Fe3O4"), vec![(String::from("Fe"), Some(Ratio::new(8, 3))), (String::from("O"), state(-2))]);
+            assert_eq!(parse("O2"), vec![(String::from("O"), state(0))]);
+        }
+
+        #[test]
+        fn leaves_unknown_states_out() {
+            assert_eq!(parse("HCN"), vec![(String::from("C"), None), (String::from("H"), state(1)), (String::from("N"), None)]);
+
+            let (_, unknown) = changes(&Equation::parse(b"HCN+NaOH=NaCN+H2O").unwrap());
+
+            assert_eq!(unknown, vec!["C", "N"]);
+        }
+
+        #[test]
+        fn finds_electrons_lost_and_gained() {
+            assert_eq!(electrons("2Na+Cl2=2NaCl"), vec![(String::from("Cl"), 0, 2), (String::from("Na"), 2, 0)]);
+            assert_eq!(electrons("3Cl2+6OH{-}=5Cl{-}+ClO3{-}+3H2O"), vec![(String::from("Cl"), 5, 5)]);
+            assert_eq!(electrons("NaH+H2O=NaOH+H2"), vec![(String::from("H"), 1, 1)]);
+            assert!(electrons("HCl+NaOH=NaCl+H2O").is_empty());
+        }
+
+        #[test]
+        fn leaves_unchanged_states_out_of_transitions() {
+            let transitions = |equation: &str| -> Vec<(i32, i32)> {
+                changes(&Equation::parse(equation.as_bytes()).unwrap()).0.iter()
+                    .flat_map(|change| change.transitions.iter().map(|(from, to)| (from.to_integer(), to.to_integer())))
+                    .collect()
+            };
+
+            assert_eq!(transitions("3Cl2+6NaOH=5NaCl+NaClO3+3H2O"), vec![(0, -1), (0, 5)]);
+            assert_eq!(transitions("4HCl+MnO2=MnCl2+Cl2+2H2O"), vec![(-1, 0), (4, 2)]);
+        }
+    }
+}
@@ -0,0 +1,232 @@
+pub mod session {
+
+    use crate::balance::balancer::{parse_cached, remove_whitespace, Format, Options};
+    use crate::balance::report::{balance_line_with, check_line_with, mass_report, Outcome};
+    use crate::ast::equation::TermCache;
+    use crate::error::balance_error;
+
+    use rustyline::Editor;
+    use rustyline::error::ReadlineError;
+
+    use std::panic::{self, AssertUnwindSafe};
+
+    const HELP: &str = "Type an equation to balance it, or one of the following commands
+    :help                       Show this message
+    :quit                       Leave the session (Ctrl-D also works)
+    :balance                    Balance each equation typed (default)
+    :check                      Check each equation typed is balanced with the coefficients given
+    :verbose on|off             Show intermediate steps when balancing
+    :explain on|off             Explain each step of the method used to balance
+    :spellings on|off           Warn when the same formula is written in two ways
+    :spectators on|off          Remove molecules found on both sides before balancing
+    :type on|off                Show the type of each reaction balanced
+    :format text|json|latex     Change how results are shown
+    :mass FORMULA               Show the molar mass of a formula, i.e. :mass H2SO4";
+
+    //What each equation typed into the session is used for
+    #[derive(Clone, Copy, PartialEq)]
+    enum Mode {
+        Balance,
+        Check
+    }
+
+    // An interactive session, where each line is balanced (or checked) as soon as it is entered
+    pub struct Session {
+        options: Options,
+        mode: Mode,
+        cache: TermCache //Formulas parsed by earlier lines, shared by every equation balanced or checked and by :mass
+    }
+
+    //Parse on or off for a command that toggles an option
+    fn switch(argument: &str) -> Result<bool, String> {
+        match argument {
+            "on" => Ok(true),
+            "off" => Ok(false),
+            _ => Err(format!("Expected on or off, found '{}'", argument))
+        }
+    }
+
+    impl Session {
+        pub fn new(options: Options) -> Self {
+            Session {
+                options,
+                mode: Mode::Balance,
+                cache: TermCache::new()
+            }
+        }
+
+        // Read and evaluate lines until the user quits or input ends
+        pub fn run(& mut self) {
+            let mut editor = Editor::<()>::new();
+
+            println!("Chemical Equation Balancer. Type :help for a list of commands");
+
+            loop {
+                match editor.readline("chembal> ") {
+                    Ok(line) => {
+                        let line = line.trim();
+
+                        if line.is_empty() {
+                            continue;
+                        }
+
+                        editor.add_history_entry(line);
+
+                        if line == ":quit" || line == ":exit" {
+                            break;
+                        }
+
+                        //Whatever happens while evaluating a line, the session must carry on
+                        match panic::catch_unwind(AssertUnwindSafe(|| self.evaluate(line))) {
+                            Ok((output, errors)) => {
+                                print!("{}", output);
+                                eprint!("{}", errors);
+                            },
+                            Err(_) => eprintln!("{}", balance_error::internal(&format!("Internal error, '{}' could not be processed", line)))
+                        }
+                    },
+                    Err(ReadlineError::Interrupted) => {
+                        continue;
+                    },
+                    Err(ReadlineError::Eof) => {
+                        break;
+                    },
+                    Err(error) => {
+                        eprintln!("Could not read line ({})", error);
+                        break;
+                    }
+                }
+            }
+        }
+
+        //Evaluate a command or equation, returning the text to show the user and any errors
+        fn evaluate(& mut self, line: &str) -> (String, String) {
+            if !line.starts_with(':') {
+                let outcome = self.equation(line);
+
+                return (outcome.output, outcome.errors);
+            }
+
+            let (command, argument) = match line.find(char::is_whitespace) {
+                Some(index) => (&line[..index], line[index..].trim()),
+                None => (line, "")
+            };
+
+            let result = match command {
+                ":help" => Ok(String::from(HELP)),
+                ":balance" => {
+                    self.mode = Mode::Balance;
+                    Ok(String::from("Balancing equations"))
+                },
+                ":check" => {
+                    self.mode = Mode::Check;
+                    Ok(String::from("Checking equations are balanced"))
+                },
+                ":verbose" => switch(argument).map(|on| {
+                    self.options.verbose = on;
+                    format!("Verbose {}", argument)
+                }),
+                ":explain" => switch(argument).map(|on| {
+                    self.options.explain = on;
+                    format!("Explanations {}", argument)
+                }),
+                ":spellings" => switch(argument).map(|on| {
+                    self.options.spellings = on;
+                    format!("Spelling warnings {}", argument)
+                }),
+                ":spectators" => switch(argument).map(|on| {
+                    self.options.spectators = on;
+                    format!("Spectator removal {}", argument)
+                }),
+                ":type" => switch(argument).map(|on| {
+                    self.options.classify = on;
+                    format!("Reaction types {}", argument)
+                }),
+                ":format" => {
+                    match argument {
+                        "text" => Ok(Format::Text),
+                        "json" => Ok(Format::Json),
+                        "latex" => Ok(Format::Latex),
+                        _ => Err(format!("Expected text, json or latex, found '{}'", argument))
+                    }.map(|format| {
+                        self.options.format = format;
+                        format!("Showing results as {}", argument)
+                    })
+                },
+                ":mass" => self.mass(argument),
+                _ => Err(format!("Unknown command '{}', type :help for a list of commands", command))
+            };
+
+            match result {
+                Ok(message) => (format!("{}\n", message.trim_end()), String::new()),
+                Err(message) => (String::new(), format!("{}\n", message))
+            }
+        }
+
+        //Balance or check an equation, parsing only the formulas not seen in an earlier line
+        fn equation(& mut self, line: &str) -> Outcome {
+            let cache = & mut self.cache;
+            let parse_equation = |equation: &str| parse_cached(equation, cache);
+
+            match self.mode {
+                Mode::Balance => balance_line_with(None, line, &self.options, parse_equation),
+                Mode::Check => check_line_with(None, line, &self.options, parse_equation)
+            }
+        }
+
+        //The molar mass of a formula, parsing it only if it has not been seen in an earlier line
+        fn mass(& mut self, argument: &str) -> Result<String, String> {
+            let term = self.cache.term(remove_whitespace(argument).as_bytes()).map_err(|error| format!("Cannot find molar mass. {}", error))?;
+
+            mass_report(argument, &term, &self.options).map_err(|error| format!("Cannot find molar mass. {}", error))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn session() -> Session {
+            Session::new(Options { verbose: false, explain: false, spellings: false, spectators: false, classify: false, format: Format::Text, export: None })
+        }
+
+        #[test]
+        fn balances_and_checks_equations() {
+            let mut session = session();
+
+            assert_eq!(session.evaluate("H2+O2=H2O"), (String::from("2H2+O2=2H2O\n"), String::new()));
+            assert_eq!(session.evaluate(":check").0, "Checking equations are balanced\n");
+            assert!(session.evaluate("H2+O2=H2O").0.starts_with("H2+O2=H2O is not balanced\n"));
+            assert!(!session.evaluate("2H2+O2=2H2O").0.contains("not balanced"));
+        }
+
+        #[test]
+        fn reuses_formulas_across_lines() {
+            let mut session = session();
+
+            session.evaluate("H2+O2=H2O");
+            assert_eq!(session.cache.len(), 3);
+
+            session.evaluate(":check");
+            session.evaluate("2H2 + O2 = 2H2O");
+            session.evaluate(":mass H2O");
+            assert_eq!(session.cache.len(), 3);
+
+            session.evaluate("H2O2=H2O+O2");
+            assert_eq!(session.cache.len(), 4);
+
+            session.evaluate(":format json");
+            assert_eq!(session.cache.len(), 4);
+            assert!(session.evaluate("H2+O2=H2O").0.starts_with('{'));
+        }
+
+        #[test]
+        fn reports_bad_commands() {
+            let mut session = session();
+
+            assert_eq!(session.evaluate(":verbose maybe").1, "Expected on or off, found 'maybe'\n");
+            assert_eq!(session.evaluate(":frobnicate").1, "Unknown command ':frobnicate', type :help for a list of commands\n");
+            assert!(session.evaluate(":mass H2)").1.starts_with("Cannot find molar mass."));
+        }
+    }
+}
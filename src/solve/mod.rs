@@ -3,12 +3,24 @@ pub mod matrices
     use num_rational::Ratio;
     use num_traits::identities::Zero;
     use num_traits::identities::One;
+    use num_traits::{CheckedAdd, CheckedMul};
+
+    use crate::error::balance_error::{BalanceError, ErrorKind};
 
     use std::collections::HashSet;
+    use std::fmt;
+    use std::fmt::Write;
+
+    //An error for a row operation whose result is too large for the ratio, i.e. from quantities with large denominators
+    fn too_large() -> BalanceError {
+        BalanceError::new(ErrorKind::Parse, String::from("The quantities in the equation are too large to balance"))
+    }
 
     // Represents an augmented matrix
     pub struct Augmented {
-        matrix: Vec<Vec<Ratio<i32>>>
+        matrix: Vec<Vec<Ratio<i32>>>,
+        rows: Vec<String>, // Name of each row (the element), which moves with the row when rows are swapped
+        columns: Vec<String> // Name of each column (the species)
     }
 
     impl Augmented {
@@ -20,31 +32,123 @@ pub mod matrices
             }
 
             Augmented {
-                matrix: mat
+                matrix: mat,
+                rows: vec![String::new(); n],
+                columns: Vec::new()
+            }
+        }
+
+        // Name the rows and columns, used when explaining each step
+        pub fn label(&mut self, rows: Vec<String>, columns: Vec<String>) {
+            self.rows = rows;
+            self.columns = columns;
+        }
+
+        //A row by number (from one) and name, i.e. row 2 (O)
+        fn row_name(&self, index: usize) -> String {
+            match self.rows.get(index) {
+                Some(name) if !name.is_empty() => format!("row {} ({})", index + 1, name),
+                _ => format!("row {}", index + 1)
+            }
+        }
+
+        //A column by name if it has one, otherwise by number (from one)
+        fn column_name(&self, index: usize) -> String {
+            match self.columns.get(index) {
+                Some(name) => name.clone(),
+                None => format!("column {}", index + 1)
+            }
+        }
+
+        // The matrix without the augmented column
+        pub fn coefficients(&self) -> Vec<Vec<Ratio<i32>>> {
+            self.matrix.iter().map(|row| row[..row.len() - 1].to_vec()).collect()
+        }
+
+        // The name of each row, empty if the rows have not been labelled
+        pub fn row_labels(&self) -> &[String] {
+            &self.rows
+        }
+
+        // The name of each column, empty if the columns have not been labelled
+        pub fn column_labels(&self) -> &[String] {
+            &self.columns
+        }
+
+        //A multiple of a row, i.e. 3 x row 1 (H), or just the row if the multiple is one
+        fn multiple(&self, factor: Ratio<i32>, index: usize) -> String {
+            if factor == Ratio::one() {
+                self.row_name(index)
+            }
+            else {
+                format!("{} x {}", factor, self.row_name(index))
+            }
+        }
+
+        // The matrix without the augmented column, with each row and column named
+        pub fn labelled(&self) -> String {
+            let width = self.matrix.first().map(|row| row.len() - 1).unwrap_or(0);
+
+            let mut cells: Vec<Vec<String>> = Vec::with_capacity(self.matrix.len() + 1);
+
+            let mut header = vec![String::new()];
+            header.extend((0..width).map(|column| self.column_name(column)));
+            cells.push(header);
+
+            for (index, row) in self.matrix.iter().enumerate() {
+                let mut line = vec![self.rows.get(index).cloned().unwrap_or_default()];
+                line.extend(row[..width].iter().map(|element| element.to_string()));
+                cells.push(line);
+            }
+
+            let widths: Vec<usize> = (0..=width).map(|column| cells.iter().map(|line| line[column].len()).max().unwrap_or(0)).collect();
+
+            let mut result = String::new();
+
+            for line in cells {
+                result.push_str("   ");
+
+                for (cell, width) in line.iter().zip(widths.iter()) {
+                    write!(result, " {:>width$}", cell, width = width).unwrap();
+                }
+
+                result.push('\n');
             }
+
+            result
         }
 
         // Add column to matrix
-        pub fn add_column(&mut self, column: &Vec<Ratio<i32>>) {
-            for i in 0..self.matrix.len() {
-                self.matrix[i].push(column[i])
+        pub fn add_column(&mut self, column: &[Ratio<i32>]) {
+            for (row, element) in self.matrix.iter_mut().zip(column.iter()) {
+                row.push(*element)
             }
         }
 
         // Multiply a row by scalar (use multiply trait instead)
-        fn scalar(&mut self, scale: Ratio<i32>, index: usize) {
+        fn scalar(&mut self, scale: Ratio<i32>, index: usize) -> Result<(), BalanceError> {
             for element in self.matrix[index].iter_mut() {
-                *element = *element * scale;
+                *element = element.checked_mul(&scale).ok_or_else(too_large)?;
             }
+
+            Ok(())
         }
 
-        fn addmultiple(&mut self, destination: usize, source: usize, scalar: Ratio<i32>) {
+        fn addmultiple(&mut self, destination: usize, source: usize, scalar: Ratio<i32>) -> Result<(), BalanceError> {
             for i in 0..self.matrix[0].len() {
-                self.matrix[destination][i] = self.matrix[destination][i] + self.matrix[source][i] * scalar;
+                self.matrix[destination][i] = self.matrix[source][i].checked_mul(&scalar)
+                    .and_then(|multiple| self.matrix[destination][i].checked_add(&multiple))
+                    .ok_or_else(too_large)?;
             }
+
+            Ok(())
         }
 
         fn swap(&mut self, a: usize, b: usize) {
+            if a < self.rows.len() && b < self.rows.len() {
+                self.rows.swap(a, b);
+            }
+
             if a > b {
                 self.swap(b, a);
             } else {
@@ -65,19 +169,14 @@ pub mod matrices
             }
         }
 
-        // Use the debug trait instead of this function
-        pub fn print(&self) {
-            for row in self.matrix.iter() {
-                for element in row {
-                    print!("{} ", *element);
-                }
-                println!();
+        // Gaussian elimination using elementary row operations. If a log is given, each operation is described in it.
+        // Fails if an entry becomes too large for the ratio
+        pub fn row_reduce(&mut self, mut log: Option<& mut String>) -> Result<(), BalanceError> {
+            //Without any rows there is nothing to reduce
+            if self.matrix.is_empty() {
+                return Ok(());
             }
-            println!();
-        }
 
-        // Gaussian elimination using elementary row operations
-        pub fn row_reduce(&mut self) {
             let row_count = if self.matrix.len() < self.matrix[0].len() {
                 self.matrix.len()
             }
@@ -94,6 +193,10 @@ pub mod matrices
                     let mut found = false;
                     for j in i + 1..self.matrix.len() {
                         if self.matrix[j][i] != Ratio::zero() {
+                            if let Some(log) = log.as_mut() {
+                                writeln!(log, "    Swap {} and {}, so the pivot for {} is not zero", self.row_name(i), self.row_name(j), self.column_name(i)).unwrap();
+                            }
+
                             self.swap(i, j);
                             found = true;
                             break;
@@ -101,24 +204,53 @@ pub mod matrices
                     }
                     pivot = self.matrix[i][i];
                     //println!("({})", found);
-                    if found == false {
+                    if !found {
+                        if let Some(log) = log.as_mut() {
+                            writeln!(log, "    {} has no pivot, it is skipped\n", self.column_name(i)).unwrap();
+                        }
+
                         continue;
                     }
                 }
 
+                if let Some(log) = log.as_mut() {
+                    if pivot != Ratio::one() {
+                        writeln!(log, "    Divide {} by {}, so the pivot for {} is 1", self.row_name(i), pivot, self.column_name(i)).unwrap();
+                    }
+                }
+
                 //self.print();
-                self.scalar(Ratio::new(1, 1) / pivot, i);
+                self.scalar(Ratio::new(1, 1) / pivot, i)?;
 
                 for j in 0..self.matrix.len() {
                     if i != j {
-                        self.addmultiple(j, i, self.matrix[j][i] * -Ratio::one());
+                        let factor = self.matrix[j][i];
+
+                        if let Some(log) = log.as_mut() {
+                            if factor > Ratio::zero() {
+                                writeln!(log, "    Subtract {} from {}", self.multiple(factor, i), self.row_name(j)).unwrap();
+                            }
+                            else if factor < Ratio::zero() {
+                                writeln!(log, "    Add {} to {}", self.multiple(-factor, i), self.row_name(j)).unwrap();
+                            }
+                        }
+
+                        self.addmultiple(j, i, factor * -Ratio::one())?;
                     }
                 }
                 //self.print();
+
+                if let Some(log) = log.as_mut() {
+                    writeln!(log, "{}", self.labelled()).unwrap();
+                }
             }
+
+            Ok(())
         }
 
-        pub fn solve(& mut self) -> Result<Vec<i32>, String> {
+        // Find the smallest whole number solution. If a log is given, the choice of free variable and the scaling to
+        // whole numbers is described in it
+        pub fn solve(& mut self, mut log: Option<& mut String>) -> Result<Vec<i32>, BalanceError> {
             //Assumes the matrix is row reduced
 
             //Basically here we compare the number of rows and columns to determine if the system of equations has
@@ -145,7 +277,7 @@ pub mod matrices
 
                     let element = self.matrix[i][j];
 
-                    if leading_found == true && element != Ratio::zero() {
+                    if leading_found && element != Ratio::zero() {
                         independent_set.insert(j);
                     }
 
@@ -155,7 +287,7 @@ pub mod matrices
                 }
 
                 // If no leading one is found, the row is empty and should be marked for removal
-                if leading_found == false {
+                if !leading_found {
                     remove_set.insert(0, i);
                 }
             }
@@ -163,19 +295,49 @@ pub mod matrices
             // Remove empty rows
             for kill in remove_set.iter() {
                 self.matrix.remove(*kill);
+
+                if *kill < self.rows.len() {
+                    self.rows.remove(*kill);
+                }
+            }
+
+            //Every row was empty, so nothing constrains the coefficients, i.e. every element cancels out
+            if self.matrix.is_empty() {
+                if let Some(log) = log.as_mut() {
+                    writeln!(log, "Every row is zero, so any coefficients balance the equation").unwrap();
+                }
+
+                return Err(BalanceError::new(ErrorKind::Ambiguous, String::from("Nothing to balance, every element cancels out")));
             }
 
             //At this point, if the matrix without the augment is square, we have a unique solution
             if self.matrix.len() == self.matrix[0].len() - 1 {
-                Err(String::from("Trivial solution detected, impossible chemical equation"))
+                if let Some(log) = log.as_mut() {
+                    writeln!(log, "Every species has a pivot, so the only solution is every coefficient being zero").unwrap();
+                }
+
+                Err(BalanceError::new(ErrorKind::Impossible, String::from("Trivial solution detected, impossible chemical equation")))
             }
             else {
 
                 if independent_set.len() > 1 {
-                    return Err(String::from("Infinite standard forms detected. Hint: This could be caused by two separate equations merged together."))
+                    if let Some(log) = log.as_mut() {
+                        let mut free: Vec<&usize> = independent_set.iter().collect();
+                        free.sort();
+
+                        let names: Vec<String> = free.iter().map(|index| self.column_name(**index)).collect();
+
+                        writeln!(log, "{} have no pivot, so there is more than one free variable and no single solution", names.join(", ")).unwrap();
+                    }
+
+                    return Err(BalanceError::new(ErrorKind::Ambiguous, String::from("Infinite standard forms detected. Hint: This could be caused by two separate equations merged together.")))
                 }
 
                 for index in independent_set.iter() {
+                    if let Some(log) = log.as_mut() {
+                        writeln!(log, "{} has no pivot, so its coefficient is the free variable. Every other coefficient depends on it, so it is set to 1", self.column_name(*index)).unwrap();
+                    }
+
                     let mut row = Vec::new();
 
                     for i in 0..self.matrix[0].len() {
@@ -188,25 +350,50 @@ pub mod matrices
                     }
 
                     self.matrix.push(row);
+                    self.rows.push(String::new());
 
                 }
 
-                self.row_reduce();
+                self.row_reduce(None)?;
 
                 let mut result : Vec<i32> = Vec::new();
 
-                let mut lm = 1;
+                let mut lm: i32 = 1;
 
                 for i in 0..self.matrix.len() {
                     let ratio = self.matrix[i][self.matrix[0].len() - 1];
-                    lm = num::integer::lcm(lm, *ratio.denom());
+                    lm = (lm / num::integer::gcd(lm, *ratio.denom())).checked_mul(*ratio.denom()).ok_or_else(too_large)?;
                 }
 
-                for i in 0..self.matrix.len() {
-                    let ratio = self.matrix[i][self.matrix[0].len() - 1] * lm;
+                let last = self.matrix[0].len() - 1;
+
+                let scaled = (0..self.matrix.len())
+                    .map(|i| self.matrix[i][last].checked_mul(&Ratio::from_integer(lm)).ok_or_else(too_large))
+                    .collect::<Result<Vec<Ratio<i32>>, BalanceError>>()?;
+
+                if let Some(log) = log.as_mut() {
+                    let values: Vec<String> = (0..self.matrix.len())
+                        .map(|i| format!("{} = {}", self.column_name(i), self.matrix[i][last]))
+                        .collect();
+
+                    writeln!(log, "Substituting back gives {}", values.join(", ")).unwrap();
+
+                    if lm == 1 {
+                        writeln!(log, "These are already whole numbers").unwrap();
+                    }
+                    else {
+                        let denominators: Vec<String> = (0..self.matrix.len()).map(|i| self.matrix[i][last].denom().to_string()).collect();
 
+                        let scaled: Vec<String> = scaled.iter().map(|ratio| ratio.to_string()).collect();
+
+                        writeln!(log, "The lowest common multiple of the denominators ({}) is {}, multiplying by it gives the whole numbers {}",
+                                 denominators.join(", "), lm, scaled.join(", ")).unwrap();
+                    }
+                }
+
+                for ratio in scaled {
                     if ratio < Ratio::zero() {
-                        return Err(format!("Negative solution. Hint: This is could be the result of reactants/products on the wrong side of an equation.\nConsider moving either all negative or all positive molecules to the other side"));
+                        return Err(BalanceError::new(ErrorKind::Negative, String::from("Negative solution. Hint: This is could be the result of reactants/products on the wrong side of an equation.\nConsider moving either all negative or all positive molecules to the other side")));
                     }
 
                     result.push(*(ratio.numer()));
@@ -221,4 +408,80 @@ pub mod matrices
 
         }
     }
-}
\ No newline at end of file
+
+    impl fmt::Display for Augmented {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            for row in self.matrix.iter() {
+                for element in row {
+                    write!(f, "{} ", *element)?;
+                }
+                writeln!(f)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn matrix(columns: &[&[i32]]) -> Augmented {
+            let mut matrix = Augmented::new(columns[0].len());
+
+            for column in columns {
+                matrix.add_column(&column.iter().map(|element| Ratio::from_integer(*element)).collect::<Vec<Ratio<i32>>>());
+            }
+
+            matrix.augment();
+            matrix.row_reduce(None).unwrap();
+
+            matrix
+        }
+
+        #[test]
+        fn finds_the_smallest_whole_number_solution() {
+            //H2 + O2 = H2O
+            assert_eq!(matrix(&[&[2, 0], &[0, 2], &[-2, -1]]).solve(None), Ok(vec![2, 1, 2]));
+        }
+
+        #[test]
+        fn rejects_the_trivial_solution() {
+            //H2 = O2
+            assert_eq!(matrix(&[&[2, 0], &[0, -2]]).solve(None).unwrap_err().kind, ErrorKind::Impossible);
+        }
+
+        #[test]
+        fn rejects_negative_solutions() {
+            //H2O + O2 = H2
+            assert_eq!(matrix(&[&[2, 1], &[0, 2], &[-2, 0]]).solve(None).unwrap_err().kind, ErrorKind::Negative);
+        }
+
+        #[test]
+        fn rejects_a_matrix_without_rows() {
+            let mut matrix = Augmented::new(0);
+
+            matrix.add_column(&[]);
+            matrix.augment();
+            matrix.row_reduce(None).unwrap();
+
+            assert_eq!(matrix.solve(None).unwrap_err().kind, ErrorKind::Ambiguous);
+        }
+
+        #[test]
+        fn explains_each_row_operation() {
+            let mut matrix = Augmented::new(2);
+
+            matrix.add_column(&[Ratio::zero(), Ratio::from_integer(2)]);
+            matrix.add_column(&[Ratio::from_integer(2), Ratio::zero()]);
+            matrix.augment();
+            matrix.label(vec![String::from("O"), String::from("H")], vec![String::from("a"), String::from("b")]);
+
+            let mut log = String::new();
+
+            matrix.row_reduce(Some(& mut log)).unwrap();
+
+            assert!(log.starts_with("    Swap row 1 (O) and row 2 (H), so the pivot for a is not zero\n    Divide row 1 (H) by 2, so the pivot for a is 1\n"));
+        }
+    }
+}
@@ -0,0 +1,580 @@
+pub mod amounts {
+
+    use crate::ast::equation::{Equation, State, Term};
+    use crate::error::balance_error::{BalanceError, ErrorKind};
+    use crate::thermo::formation::GAS_CONSTANT;
+    use crate::units::quantity::{Dimension, Quantity, Unit, Value};
+
+    use num_rational::Ratio;
+
+    use num_traits::identities::One;
+
+    use std::fmt;
+
+    //Whether text starts with a number, so begins with a quantity rather than a formula
+    fn starts_with_number(text: &str) -> bool {
+        text.trim_start().starts_with(|ch: char| ch.is_ascii_digit() || ch == '.' || ch == '-')
+    }
+
+    // The temperature and pressure of a gas
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Conditions {
+        pub temperature: f64, // In K
+        pub pressure: f64 // In Pa
+    }
+
+    impl Conditions {
+        // Standard temperature and pressure, 273.15 K and 1 atm
+        pub const STANDARD: Conditions = Conditions {
+            temperature: 273.15,
+            pressure: 101325.0
+        };
+
+        // A temperature in K, C or degC, i.e. "25 C" or "298.15 K"
+        pub fn parse_temperature(text: &str) -> Result<f64, BalanceError> {
+            let kelvin = Quantity::parse(text)?.expect(Dimension::TEMPERATURE)?;
+
+            if kelvin <= 0.0 {
+                return Err(BalanceError::new(ErrorKind::Input, format!("'{}' is not above absolute zero", text)));
+            }
+
+            Ok(kelvin)
+        }
+
+        // A pressure in Pa, kPa, bar, atm, mmHg or Torr, i.e. "1 atm" or "101.325 kPa"
+        pub fn parse_pressure(text: &str) -> Result<f64, BalanceError> {
+            let pascals = Quantity::parse(text)?.expect(Dimension::PRESSURE)?;
+
+            if pascals <= 0.0 {
+                return Err(BalanceError::new(ErrorKind::Input, format!("'{}' must be more than zero", text)));
+            }
+
+            Ok(pascals)
+        }
+
+        // Conditions from an optional temperature and pressure, with the standard value for any not given
+        pub fn parse(temperature: Option<&str>, pressure: Option<&str>) -> Result<Conditions, BalanceError> {
+            Ok(Conditions {
+                temperature: match temperature {
+                    Some(text) => Conditions::parse_temperature(text)?,
+                    None => Conditions::STANDARD.temperature
+                },
+                pressure: match pressure {
+                    Some(text) => Conditions::parse_pressure(text)?,
+                    None => Conditions::STANDARD.pressure
+                }
+            })
+        }
+
+        // The volume of one mole of an ideal gas in L, from PV = nRT
+        pub fn molar_volume(&self) -> f64 {
+            GAS_CONSTANT * self.temperature / self.pressure * 1000.0
+        }
+    }
+
+    impl fmt::Display for Conditions {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{:.2} K and {:.3} kPa", self.temperature, self.pressure / 1000.0)
+        }
+    }
+
+    // The parts of an amount of a solution, any of which may be missing, i.e. "0.1 M, 25 mL HCl" or "25 mL HCl"
+    pub struct Solution {
+        pub molarity: Option<Quantity>,
+        pub volume: Option<Quantity>,
+        pub species: Term
+    }
+
+    impl Solution {
+        // Parse a concentration and a volume, either of which may be left out, followed by a formula, i.e.
+        // "0.1 M, 25 mL HCl", "0.1 mol/L NaOH" or "25 mL HCl"
+        pub fn parse(text: &str) -> Result<Solution, BalanceError> {
+            let text = text.trim();
+
+            let invalid = || BalanceError::new(ErrorKind::Input, format!("'{}' must be a concentration, volume and formula, i.e. 0.1 M, 25 mL HCl", text));
+
+            let mut rest = text;
+            let mut molarity = None;
+            let mut volume = None;
+
+            while starts_with_number(rest) {
+                let (quantity, after) = Quantity::parse_prefix(rest)?;
+
+                if quantity.value.to_f64() < 0.0 {
+                    return Err(BalanceError::new(ErrorKind::Input, format!("'{}' must not be negative", text)));
+                }
+
+                match quantity.unit.dimension {
+                    Dimension::CONCENTRATION if molarity.is_none() && volume.is_none() => molarity = Some(quantity),
+                    Dimension::VOLUME if volume.is_none() => volume = Some(quantity),
+                    Dimension::CONCENTRATION | Dimension::VOLUME => return Err(invalid()),
+                    dimension => return Err(BalanceError::new(ErrorKind::Input, format!("'{}' is {}, but a concentration or volume is needed",
+                                                                                        quantity, dimension.name())))
+                }
+
+                rest = after;
+            }
+
+            let formula: String = rest.chars().filter(|ch| !ch.is_whitespace()).collect();
+
+            if formula.is_empty() || (molarity.is_none() && volume.is_none()) {
+                return Err(invalid());
+            }
+
+            Ok(Solution {
+                molarity,
+                volume,
+                species: Term::parse(formula.as_bytes())?
+            })
+        }
+
+        // The concentration in mol/L, if it was given
+        pub fn molarity(&self) -> Option<f64> {
+            self.molarity.as_ref().map(|molarity| molarity.base().to_f64() / 1000.0)
+        }
+
+        // The volume in L, if it was given
+        pub fn litres(&self) -> Option<f64> {
+            self.volume.as_ref().map(|volume| volume.base().to_f64() * 1000.0)
+        }
+    }
+
+    // An amount of a particular substance, i.e. 12.5 g H2O or 2.4 L O2(g). Volumes are at the given conditions, unless
+    // a molarity is given, in which case they are the volume of a solution of that concentration, i.e. 0.1 M, 25 mL HCl
+    #[derive(Clone, Debug)]
+    pub struct Given {
+        pub amount: Quantity, // An amount, mass or volume
+        pub species: Term,
+        pub conditions: Conditions,
+        pub molarity: Option<Quantity>
+    }
+
+    impl Given {
+        // Parse a value, unit and formula, i.e. "12.5 g H2O", "0.2mol O2" or "0.1 M, 25 mL HCl". Volumes of gases are at
+        // standard conditions until the conditions are changed
+        pub fn parse(text: &str) -> Result<Given, BalanceError> {
+            let text = text.trim();
+
+            let (amount, rest) = Quantity::parse_prefix(text)?;
+
+            if amount.value.to_f64() < 0.0 {
+                return Err(BalanceError::new(ErrorKind::Input, format!("'{}' must not be negative", text)));
+            }
+
+            match amount.unit.dimension {
+                Dimension::CONCENTRATION => {
+                    let solution = Solution::parse(text)?;
+
+                    let volume = solution.volume
+                        .ok_or_else(|| BalanceError::new(ErrorKind::Input, format!("'{}' must give the volume of solution, i.e. 0.1 M, 25 mL HCl", text)))?;
+
+                    return Ok(Given {
+                        amount: volume,
+                        species: solution.species,
+                        conditions: Conditions::STANDARD,
+                        molarity: solution.molarity
+                    });
+                },
+                Dimension::AMOUNT | Dimension::MASS | Dimension::VOLUME => {},
+                dimension => return Err(BalanceError::new(ErrorKind::Input, format!("'{}' is {}, but an amount, mass or volume is needed",
+                                                                                    amount, dimension.name())))
+            }
+
+            let formula: String = rest.chars().filter(|ch| !ch.is_whitespace()).collect();
+
+            if formula.is_empty() {
+                return Err(BalanceError::new(ErrorKind::Input, format!("'{}' must end with a formula, i.e. 12.5 g H2O", text)));
+            }
+
+            let species = Term::parse(formula.as_bytes())?;
+
+            Ok(Given {
+                amount,
+                species,
+                conditions: Conditions::STANDARD,
+                molarity: None
+            })
+        }
+
+        //The molar mass of the substance in g/mol, which must not be zero if it is needed to convert to or from unit
+        fn molar_mass(&self, unit: &Unit) -> Result<f64, BalanceError> {
+            let molar_mass = self.species.formula.molar_mass()?;
+
+            if unit.dimension == Dimension::MASS && molar_mass == 0.0 {
+                return Err(BalanceError::new(ErrorKind::Input, format!("{} has no mass, so cannot be given in {}", self.species, unit)));
+            }
+
+            Ok(molar_mass)
+        }
+
+        // The number of moles of the substance
+        pub fn moles(&self) -> Result<f64, BalanceError> {
+            let molar_mass = self.molar_mass(&self.amount.unit)?;
+
+            let base = self.amount.base().to_f64();
+
+            Ok(match (self.amount.unit.dimension, &self.molarity) {
+                (Dimension::VOLUME, Some(molarity)) => base * molarity.base().to_f64(),
+                (Dimension::VOLUME, None) => self.conditions.pressure * base / (GAS_CONSTANT * self.conditions.temperature),
+                (Dimension::MASS, _) => base * 1000.0 / molar_mass,
+                _ => base
+            })
+        }
+
+        // The amount in another unit, with volumes at the conditions given. Amounts converted to a unit of the same
+        // kind are kept exact
+        pub fn convert(&self, unit: &Unit, conditions: &Conditions) -> Result<Quantity, BalanceError> {
+            let dimension = unit.dimension;
+
+            if dimension == self.amount.unit.dimension && self.molarity.is_none() && (dimension != Dimension::VOLUME || *conditions == self.conditions) {
+                return self.amount.convert(unit);
+            }
+
+            let molar_mass = self.molar_mass(unit)?;
+
+            let moles = self.moles()?;
+
+            let base = match dimension {
+                Dimension::AMOUNT => moles,
+                Dimension::MASS => moles * molar_mass / 1000.0,
+                Dimension::VOLUME => moles * GAS_CONSTANT * conditions.temperature / conditions.pressure,
+                _ => return Err(BalanceError::new(ErrorKind::Input, format!("Cannot convert {} to {}, which is {}, expected an amount, mass or volume",
+                                                                            self.species, unit, dimension.name())))
+            };
+
+            Ok(Quantity::from_base(Value::Approximate(base), unit))
+        }
+    }
+
+    impl fmt::Display for Given {
+        // The amount as it would be written, i.e. 12.5 g H2O or 0.1 M, 25 mL HCl
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            if let Some(molarity) = &self.molarity {
+                write!(f, "{}, ", molarity)?;
+            }
+
+            write!(f, "{} {}", self.amount, self.species)
+        }
+    }
+
+    // The amount of one species taking part in a reaction
+    pub struct Amount {
+        pub species: Term,
+        pub reactant: bool,
+        pub moles: f64,
+        pub grams: f64,
+        pub litres: Option<f64>, // Volume of a gas at the conditions asked for
+        pub excess: Option<f64> // Moles of a given reactant left over once the limiting reactant is used up
+    }
+
+    // The amounts of every species in a balanced equation, and the limiting reactant if more than one was given
+    pub struct Stoichiometry {
+        pub extent: f64, // The number of times the equation as written takes place, in moles
+        pub limiting: Option<Term>,
+        pub amounts: Vec<Amount>
+    }
+
+    //The index of the term in the equation that is the same species as the given amount, ignoring state. A volume can
+    //only be given for a species marked as a gas, here or in the equation
+    fn find(equation: &Equation, given: &Given) -> Result<usize, BalanceError> {
+        let index = equation.terms()
+            .position(|term| term.canonical() == given.species.canonical())
+            .ok_or_else(|| BalanceError::new(ErrorKind::Input, format!("{} is not in the equation", given.species)))?;
+
+        let gas = given.species.state == Some(State::Gas) || equation.terms().nth(index).unwrap().state == Some(State::Gas);
+
+        if given.amount.unit.dimension == Dimension::VOLUME && given.molarity.is_none() && !gas {
+            return Err(BalanceError::new(ErrorKind::Input, format!("{} is not marked as a gas, so cannot be given in {}. Write it as {}(g)",
+                                                                   given.species, given.amount.unit, given.species)));
+        }
+
+        Ok(index)
+    }
+
+    // The amount of every species in a balanced equation, from the amounts of some of them. If several reactants are
+    // given, the one that runs out first limits the reaction. Products may only be given on their own, as a target.
+    // Species marked as a gas also have their volume at the conditions given
+    pub fn solve(equation: &Equation, given: &[Given], conditions: &Conditions) -> Result<Stoichiometry, BalanceError> {
+        let reactant_count = equation.reactants.len();
+
+        let mut found = Vec::with_capacity(given.len());
+
+        for amount in given {
+            let moles = amount.moles()?;
+
+            if moles <= 0.0 {
+                return Err(BalanceError::new(ErrorKind::Input, format!("'{}' must be more than zero", amount)));
+            }
+
+            found.push((find(equation, amount)?, moles));
+        }
+
+        let reactants = found.iter().filter(|(index, _)| *index < reactant_count).count();
+        let products = found.len() - reactants;
+
+        if reactants != 0 && products != 0 {
+            return Err(BalanceError::new(ErrorKind::Input, String::from("Amounts can be given for reactants or for a product, but not both")));
+        }
+
+        if products > 1 {
+            return Err(BalanceError::new(ErrorKind::Input, String::from("Only one product amount can be given")));
+        }
+
+        let terms: Vec<&Term> = equation.terms().collect();
+
+        let coefficient = |index: usize| *terms[index].coefficient.numer() as f64 / *terms[index].coefficient.denom() as f64;
+
+        //The extent is limited by whichever given amount allows the fewest reactions
+        let mut extent = f64::INFINITY;
+        let mut limiting = None;
+
+        for (index, moles) in found.iter() {
+            let possible = moles / coefficient(*index);
+
+            if possible < extent {
+                extent = possible;
+                limiting = Some(*index);
+            }
+        }
+
+        if limiting.is_none() {
+            return Err(BalanceError::new(ErrorKind::Input, String::from("An amount must be given for at least one species")));
+        }
+
+        //A species is a gas if it is marked as one in the equation or in a given amount
+        let gas = |index: usize| terms[index].state == Some(State::Gas) || given.iter().zip(found.iter())
+            .any(|(amount, (found, _))| *found == index && amount.species.state == Some(State::Gas));
+
+        let mut amounts = Vec::with_capacity(terms.len());
+
+        for (index, term) in terms.iter().enumerate() {
+            let moles = extent * coefficient(index);
+
+            let excess = found.iter()
+                .find(|(given, _)| *given == index && index < reactant_count)
+                .map(|(_, supplied)| supplied - moles);
+
+            //The coefficient is already in the equation, so the species is given without it
+            let mut species = (*term).clone();
+
+            species.coefficient = Ratio::one();
+
+            amounts.push(Amount {
+                species,
+                reactant: index < reactant_count,
+                moles,
+                grams: moles * term.formula.molar_mass()?,
+                litres: if gas(index) { Some(moles * conditions.molar_volume()) } else { None },
+                excess
+            });
+        }
+
+        //Like the amounts, the limiting reactant is given without its coefficient
+        let limiting = if reactants > 1 { limiting.map(|index| amounts[index].species.clone()) } else { None };
+
+        Ok(Stoichiometry {
+            extent,
+            limiting,
+            amounts
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn given(texts: &[&str]) -> Vec<Given> {
+            texts.iter().map(|text| Given::parse(text).unwrap()).collect()
+        }
+
+        #[test]
+        fn finds_the_molar_volume() {
+            assert!((Conditions::STANDARD.molar_volume() - 22.414).abs() < 0.001);
+            assert!((Conditions::parse(Some("25 C"), Some("1 bar")).unwrap().molar_volume() - 24.790).abs() < 0.001);
+            assert!(Conditions::parse(Some("-300 C"), None).is_err());
+        }
+
+        #[test]
+        fn converts_given_amounts_to_moles() {
+            assert!((Given::parse("36.03 g H2O").unwrap().moles().unwrap() - 2.0).abs() < 0.001);
+            assert!((Given::parse("22.414 L O2(g)").unwrap().moles().unwrap() - 1.0).abs() < 0.001);
+            assert!((Given::parse("0.1 M, 25 mL HCl").unwrap().moles().unwrap() - 0.0025).abs() < 1e-9);
+            assert!(Given::parse("-1 mol H2").is_err());
+            assert!(Given::parse("2 mol").is_err());
+        }
+
+        #[test]
+        fn finds_the_limiting_reactant() {
+            let equation = Equation::parse(b"2H2+O2=2H2O").unwrap();
+
+            let result = solve(&equation, &given(&["2 mol H2", "2 mol O2"]), &Conditions::STANDARD).unwrap();
+
+            assert_eq!(result.extent, 1.0);
+            assert_eq!(result.limiting.unwrap().to_string(), "H2");
+            assert_eq!(result.amounts[1].excess, Some(1.0));
+            assert!((result.amounts[2].grams - 36.03).abs() < 0.001);
+        }
+
+        #[test]
+        fn only_names_a_limiting_reactant_between_several() {
+            let equation = Equation::parse(b"2H2+O2=2H2O(g)").unwrap();
+
+            let result = solve(&equation, &given(&["1 mol O2"]), &Conditions::STANDARD).unwrap();
+
+            assert!(result.limiting.is_none());
+            assert_eq!(result.amounts[2].litres.map(|litres| litres.round()), Some(45.0));
+
+            assert!(solve(&equation, &given(&["1 mol O2", "1 mol H2O"]), &Conditions::STANDARD).is_err());
+            assert!(solve(&equation, &given(&["1 mol N2"]), &Conditions::STANDARD).is_err());
+            assert!(solve(&equation, &given(&["1 L O2"]), &Conditions::STANDARD).is_err());
+        }
+
+        #[test]
+        fn rejects_amounts_of_nothing() {
+            let equation = Equation::parse(b"2H2+O2=2H2O").unwrap();
+
+            let error = solve(&equation, &given(&["0 g H2"]), &Conditions::STANDARD).err().unwrap();
+
+            assert_eq!((error.kind, error.message.as_str()), (ErrorKind::Input, "'0 g H2' must be more than zero"));
+            assert!(solve(&equation, &given(&["1 mol H2", "0 M, 1 L O2"]), &Conditions::STANDARD).is_err());
+        }
+    }
+}
+
+pub mod titration {
+
+    use crate::ast::equation::{Equation, Term};
+    use crate::error::balance_error::{BalanceError, ErrorKind};
+    use crate::stoich::amounts::Solution;
+
+    // The concentration and volume of one of the solutions in a titration, at the equivalence point
+    pub struct Sample {
+        pub species: Term,
+        pub molarity: f64, // In mol/L
+        pub litres: f64,
+        pub moles: f64
+    }
+
+    // Which of the four concentrations and volumes was found
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Unknown {
+        AnalyteMolarity,
+        AnalyteVolume,
+        TitrantMolarity,
+        TitrantVolume
+    }
+
+    // A solved titration, with both solutions at the equivalence point
+    pub struct Titration {
+        pub analyte: Sample,
+        pub titrant: Sample,
+        pub unknown: Unknown
+    }
+
+    //The coefficient of a species in the reactants of an equation
+    fn coefficient(equation: &Equation, species: &Term) -> Result<f64, BalanceError> {
+        equation.reactants.iter()
+            .find(|term| term.canonical() == species.canonical())
+            .map(|term| *term.coefficient.numer() as f64 / *term.coefficient.denom() as f64)
+            .ok_or_else(|| BalanceError::new(ErrorKind::Input, format!("{} is not a reactant in the equation", species)))
+    }
+
+    // Find the missing concentration or volume of a titration from the balanced equation, so that the analyte and
+    // titrant are in the ratio of their coefficients. Exactly one of the four must be missing
+    pub fn solve(equation: &Equation, analyte: &Solution, titrant: &Solution) -> Result<Titration, BalanceError> {
+        let analyte_coefficient = coefficient(equation, &analyte.species)?;
+        let titrant_coefficient = coefficient(equation, &titrant.species)?;
+
+        let values = [analyte.molarity(), analyte.litres(), titrant.molarity(), titrant.litres()];
+
+        let unknown = match values.iter().position(|value| value.is_none()) {
+            Some(index) if values.iter().filter(|value| value.is_none()).count() == 1 => {
+                [Unknown::AnalyteMolarity, Unknown::AnalyteVolume, Unknown::TitrantMolarity, Unknown::TitrantVolume][index]
+            },
+            _ => return Err(BalanceError::new(ErrorKind::Input, String::from("Exactly one concentration or volume must be left out, \
+                i.e. an analyte of \"0.1 M, 25 mL HCl\" and a titrant of \"0.2 M NaOH\"")))
+        };
+
+        let zero = |solution: &Solution| BalanceError::new(ErrorKind::Input, format!("The concentration and volume of {} must be more than zero", solution.species));
+
+        //A known solution with nothing in it would give an equivalence point of nothing, as would an unknown one
+        if values[..2].iter().flatten().any(|value| *value <= 0.0) {
+            return Err(zero(analyte));
+        }
+
+        if values[2..].iter().flatten().any(|value| *value <= 0.0) {
+            return Err(zero(titrant));
+        }
+
+        //The known solution gives the moles of the other, in the ratio of their coefficients
+        let moles = match unknown {
+            Unknown::AnalyteMolarity | Unknown::AnalyteVolume => values[2].unwrap() * values[3].unwrap() * analyte_coefficient / titrant_coefficient,
+            Unknown::TitrantMolarity | Unknown::TitrantVolume => values[0].unwrap() * values[1].unwrap() * titrant_coefficient / analyte_coefficient
+        };
+
+        let complete = |solution: &Solution, molarity: Option<f64>, litres: Option<f64>, moles: f64| -> Result<Sample, BalanceError> {
+            let (molarity, litres) = match (molarity, litres) {
+                (Some(molarity), Some(litres)) => (molarity, litres),
+                (Some(molarity), None) => (molarity, moles / molarity),
+                (None, Some(litres)) => (moles / litres, litres),
+                _ => return Err(zero(solution))
+            };
+
+            Ok(Sample {
+                species: solution.species.clone(),
+                molarity,
+                litres,
+                moles: molarity * litres
+            })
+        };
+
+        Ok(match unknown {
+            Unknown::AnalyteMolarity | Unknown::AnalyteVolume => Titration {
+                analyte: complete(analyte, values[0], values[1], moles)?,
+                titrant: complete(titrant, values[2], values[3], 0.0)?,
+                unknown
+            },
+            Unknown::TitrantMolarity | Unknown::TitrantVolume => Titration {
+                analyte: complete(analyte, values[0], values[1], 0.0)?,
+                titrant: complete(titrant, values[2], values[3], moles)?,
+                unknown
+            }
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn finds_the_missing_concentration() {
+            let equation = Equation::parse(b"H2SO4+2NaOH=Na2SO4+2H2O").unwrap();
+
+            let result = solve(&equation, &Solution::parse("25 mL H2SO4").unwrap(), &Solution::parse("0.1 M, 20 mL NaOH").unwrap()).unwrap();
+
+            assert_eq!(result.unknown, Unknown::AnalyteMolarity);
+            assert!((result.analyte.molarity - 0.04).abs() < 1e-9);
+            assert!((result.analyte.moles - 0.001).abs() < 1e-9);
+        }
+
+        #[test]
+        fn needs_exactly_one_unknown() {
+            let equation = Equation::parse(b"HCl+NaOH=NaCl+H2O").unwrap();
+
+            assert!(solve(&equation, &Solution::parse("25 mL HCl").unwrap(), &Solution::parse("20 mL NaOH").unwrap()).is_err());
+            assert!(solve(&equation, &Solution::parse("0.1 M, 25 mL HCl").unwrap(), &Solution::parse("0.1 M, 20 mL NaOH").unwrap()).is_err());
+            assert!(solve(&equation, &Solution::parse("25 mL KCl").unwrap(), &Solution::parse("0.1 M, 20 mL NaOH").unwrap()).is_err());
+        }
+
+        #[test]
+        fn rejects_empty_solutions() {
+            let equation = Equation::parse(b"HCl+NaOH=NaCl+H2O").unwrap();
+
+            let error = solve(&equation, &Solution::parse("0 M, 25 mL HCl").unwrap(), &Solution::parse("0.2 M NaOH").unwrap()).err().unwrap();
+
+            assert_eq!((error.kind, error.message.as_str()), (ErrorKind::Input, "The concentration and volume of HCl must be more than zero"));
+            assert!(solve(&equation, &Solution::parse("0.1 M HCl").unwrap(), &Solution::parse("0.2 M, 0 mL NaOH").unwrap()).is_err());
+            assert!(solve(&equation, &Solution::parse("25 mL HCl").unwrap(), &Solution::parse("0.2 M, 0 mL NaOH").unwrap()).is_err());
+        }
+    }
+}
@@ -0,0 +1,451 @@
+pub mod formation {
+
+    use crate::ast::equation::{Equation, State, Term};
+    use crate::error::balance_error::{BalanceError, ErrorKind};
+
+    use num_rational::Ratio;
+
+    use num_traits::identities::One;
+
+    use std::fs;
+
+    // Standard formation data for a species at 298.15 K and 1 bar
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Formation {
+        pub enthalpy: f64, // Standard enthalpy of formation, in kJ/mol
+        pub entropy: f64, // Standard molar entropy, in J/(mol K)
+        pub gibbs: f64 // Standard Gibbs energy of formation, in kJ/mol
+    }
+
+    //Formation data for common species as (formula, state, enthalpy, entropy, Gibbs energy). The first state listed for
+    //a formula is its standard state, which is used when an equation does not give one
+    const FORMATION: [(&str, &str, f64, f64, f64); 129] = [
+        //Elements
+        ("H2", "g", 0.0, 130.68, 0.0),
+        ("O2", "g", 0.0, 205.15, 0.0),
+        ("N2", "g", 0.0, 191.61, 0.0),
+        ("F2", "g", 0.0, 202.79, 0.0),
+        ("Cl2", "g", 0.0, 223.08, 0.0),
+        ("Br2", "l", 0.0, 152.21, 0.0),
+        ("Br2", "g", 30.91, 245.46, 3.11),
+        ("I2", "s", 0.0, 116.14, 0.0),
+        ("I2", "g", 62.44, 260.69, 19.33),
+        ("C", "s", 0.0, 5.74, 0.0),
+        ("S", "s", 0.0, 32.05, 0.0),
+        ("P", "s", 0.0, 41.09, 0.0),
+        ("Si", "s", 0.0, 18.83, 0.0),
+        ("Li", "s", 0.0, 29.12, 0.0),
+        ("Na", "s", 0.0, 51.30, 0.0),
+        ("K", "s", 0.0, 64.68, 0.0),
+        ("Mg", "s", 0.0, 32.67, 0.0),
+        ("Ca", "s", 0.0, 41.59, 0.0),
+        ("Ba", "s", 0.0, 62.50, 0.0),
+        ("Al", "s", 0.0, 28.30, 0.0),
+        ("Cr", "s", 0.0, 23.77, 0.0),
+        ("Mn", "s", 0.0, 32.01, 0.0),
+        ("Fe", "s", 0.0, 27.28, 0.0),
+        ("Ni", "s", 0.0, 29.87, 0.0),
+        ("Cu", "s", 0.0, 33.15, 0.0),
+        ("Zn", "s", 0.0, 41.63, 0.0),
+        ("Ag", "s", 0.0, 42.55, 0.0),
+        ("Hg", "l", 0.0, 76.02, 0.0),
+        ("Pb", "s", 0.0, 64.81, 0.0),
+        ("H", "g", 217.97, 114.71, 203.25),
+        ("O3", "g", 142.70, 238.93, 163.20),
+
+        //Inorganic compounds
+        ("H2O", "l", -285.83, 69.95, -237.13),
+        ("H2O", "g", -241.82, 188.83, -228.57),
+        ("H2O2", "l", -187.78, 109.60, -120.35),
+        ("CO", "g", -110.53, 197.67, -137.17),
+        ("CO2", "g", -393.51, 213.79, -394.36),
+        ("CO2", "aq", -413.80, 117.60, -385.98),
+        ("NH3", "g", -46.11, 192.45, -16.45),
+        ("NH3", "aq", -80.29, 111.30, -26.50),
+        ("NO", "g", 90.25, 210.76, 86.55),
+        ("NO2", "g", 33.18, 240.06, 51.31),
+        ("N2O", "g", 82.05, 219.85, 104.20),
+        ("N2O4", "g", 9.16, 304.29, 97.89),
+        ("HNO3", "l", -174.10, 155.60, -80.71),
+        ("HNO3", "aq", -207.36, 146.40, -111.25),
+        ("HCN", "g", 135.10, 201.78, 124.70),
+        ("HF", "g", -271.10, 173.78, -273.20),
+        ("HCl", "g", -92.31, 186.91, -95.30),
+        ("HCl", "aq", -167.16, 56.50, -131.23),
+        ("HBr", "g", -36.40, 198.70, -53.45),
+        ("HI", "g", 26.48, 206.59, 1.70),
+        ("H2S", "g", -20.63, 205.79, -33.56),
+        ("SO2", "g", -296.83, 248.22, -300.19),
+        ("SO3", "g", -395.72, 256.76, -371.06),
+        ("H2SO4", "l", -813.99, 156.90, -690.00),
+        ("H2SO4", "aq", -909.27, 20.10, -744.53),
+        ("P4O10", "s", -2984.00, 228.86, -2697.70),
+        ("SiO2", "s", -910.94, 41.84, -856.64),
+        ("NaCl", "s", -411.15, 72.13, -384.14),
+        ("NaCl", "aq", -407.27, 115.50, -393.13),
+        ("NaOH", "s", -425.61, 64.46, -379.49),
+        ("NaOH", "aq", -470.11, 48.10, -419.15),
+        ("NaHCO3", "s", -950.81, 101.70, -851.00),
+        ("Na2CO3", "s", -1130.68, 134.98, -1044.44),
+        ("KCl", "s", -436.75, 82.59, -409.14),
+        ("KOH", "s", -424.76, 78.90, -379.08),
+        ("KClO3", "s", -397.73, 143.10, -296.25),
+        ("MgO", "s", -601.70, 26.94, -569.43),
+        ("MgCl2", "s", -641.32, 89.62, -591.79),
+        ("MgCO3", "s", -1095.80, 65.70, -1012.10),
+        ("CaO", "s", -635.09, 39.75, -604.03),
+        ("Ca(OH)2", "s", -986.09, 83.39, -898.49),
+        ("CaCO3", "s", -1206.90, 92.90, -1128.80),
+        ("CaCl2", "s", -795.80, 104.60, -748.10),
+        ("CaSO4", "s", -1434.11, 106.70, -1321.79),
+        ("BaSO4", "s", -1473.20, 132.20, -1362.20),
+        ("Al2O3", "s", -1675.70, 50.92, -1582.30),
+        ("FeO", "s", -272.00, 60.75, -251.40),
+        ("Fe2O3", "s", -824.20, 87.40, -742.20),
+        ("Fe3O4", "s", -1118.40, 146.40, -1015.40),
+        ("CuO", "s", -157.30, 42.63, -129.70),
+        ("Cu2O", "s", -168.60, 93.14, -146.00),
+        ("ZnO", "s", -348.28, 43.64, -318.30),
+        ("AgCl", "s", -127.07, 96.20, -109.79),
+        ("NH4Cl", "s", -314.43, 94.60, -202.87),
+        ("NH4NO3", "s", -365.56, 151.08, -183.87),
+
+        //Organic compounds
+        ("CH4", "g", -74.81, 186.26, -50.72),
+        ("C2H6", "g", -84.68, 229.60, -32.82),
+        ("C2H4", "g", 52.26, 219.56, 68.15),
+        ("C2H2", "g", 226.73, 200.94, 209.20),
+        ("C3H8", "g", -103.85, 270.02, -23.49),
+        ("C4H10", "g", -126.15, 310.23, -17.03),
+        ("C8H18", "l", -249.90, 361.10, 6.40),
+        ("C6H6", "l", 49.00, 173.30, 124.30),
+        ("CCl4", "l", -135.44, 216.40, -65.21),
+        ("CH3OH", "l", -238.66, 126.80, -166.27),
+        ("CH3OH", "g", -200.66, 239.81, -161.96),
+        ("C2H5OH", "l", -277.69, 160.70, -174.78),
+        ("C2H5OH", "g", -235.10, 282.70, -168.49),
+        ("CH3COOH", "l", -484.50, 159.80, -389.90),
+        ("C6H12O6", "s", -1268.00, 212.00, -910.00),
+        ("C12H22O11", "s", -2222.00, 360.20, -1543.00),
+
+        //Aqueous ions
+        ("H{+}", "aq", 0.0, 0.0, 0.0),
+        ("OH{-}", "aq", -229.99, -10.75, -157.24),
+        ("Na{+}", "aq", -240.12, 59.00, -261.91),
+        ("K{+}", "aq", -252.38, 102.50, -283.27),
+        ("NH4{+}", "aq", -132.51, 113.40, -79.31),
+        ("Ag{+}", "aq", 105.58, 72.68, 77.11),
+        ("Mg{2+}", "aq", -466.85, -138.10, -454.80),
+        ("Ca{2+}", "aq", -542.83, -53.10, -553.58),
+        ("Ba{2+}", "aq", -537.64, 9.60, -560.77),
+        ("Mn{2+}", "aq", -220.75, -73.60, -228.10),
+        ("Fe{2+}", "aq", -89.10, -137.70, -78.90),
+        ("Fe{3+}", "aq", -48.50, -315.90, -4.70),
+        ("Cu{2+}", "aq", 64.77, -99.60, 65.49),
+        ("Zn{2+}", "aq", -153.89, -112.10, -147.06),
+        ("Pb{2+}", "aq", -1.70, 10.50, -24.43),
+        ("Al{3+}", "aq", -531.00, -321.70, -485.00),
+        ("F{-}", "aq", -332.63, -13.80, -278.79),
+        ("Cl{-}", "aq", -167.16, 56.50, -131.23),
+        ("Br{-}", "aq", -121.55, 82.40, -103.96),
+        ("I{-}", "aq", -55.19, 111.30, -51.57),
+        ("NO3{-}", "aq", -205.00, 146.40, -108.74),
+        ("HCO3{-}", "aq", -691.99, 91.20, -586.77),
+        ("CO3{2-}", "aq", -677.14, -56.90, -527.81),
+        ("SO4{2-}", "aq", -909.27, 20.10, -744.53),
+        ("PO4{3-}", "aq", -1277.40, -222.00, -1018.70),
+        ("MnO4{-}", "aq", -541.40, 191.20, -447.20),
+        ("Cr2O7{2-}", "aq", -1490.30, 261.90, -1301.10)
+    ];
+
+    // Formation data keyed by canonical formula and state
+    pub struct Table {
+        entries: Vec<(String, State, Formation)>
+    }
+
+    impl Table {
+        // The data embedded in chembal for common species
+        pub fn embedded() -> Self {
+            let mut table = Table {
+                entries: Vec::with_capacity(FORMATION.len())
+            };
+
+            for (formula, state, enthalpy, entropy, gibbs) in FORMATION.iter() {
+                //Every embedded formula and state is valid
+                let term = Term::parse(formula.as_bytes()).unwrap();
+
+                table.insert(term.canonical(), State::from_symbol(state.as_bytes()).unwrap(), Formation {
+                    enthalpy: *enthalpy,
+                    entropy: *entropy,
+                    gibbs: *gibbs
+                });
+            }
+
+            table
+        }
+
+        // Add the data for a species, replacing any data already given for the same species in the same state
+        pub fn insert(& mut self, canonical: String, state: State, formation: Formation) {
+            match self.entries.iter_mut().find(|(found, found_state, _)| *found == canonical && *found_state == state) {
+                Some((_, _, existing)) => *existing = formation,
+                None => self.entries.push((canonical, state, formation))
+            }
+        }
+
+        // Add the data in a file. Each line is a formula, its state (s, l, g or aq), enthalpy of formation in kJ/mol,
+        // entropy in J/(mol K) and Gibbs energy of formation in kJ/mol, separated by whitespace. Blank lines and lines
+        // starting with # are skipped. As with the embedded data, the first state given for a new formula is its standard
+        // state
+        pub fn load(& mut self, path: &str) -> Result<(), BalanceError> {
+            let text = fs::read_to_string(path)
+                .map_err(|e| BalanceError::new(ErrorKind::Input, format!("Could not read formation data from '{}' ({})", path, e)))?;
+
+            for (index, line) in text.lines().enumerate() {
+                let line = line.trim();
+
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+
+                let invalid = |reason: &str| BalanceError::new(ErrorKind::Input, format!("Line {} of '{}' {}", index + 1, path, reason));
+
+                let fields: Vec<&str> = line.split_whitespace().collect();
+
+                if fields.len() != 5 {
+                    return Err(invalid("must be a formula, state, enthalpy, entropy and Gibbs energy, i.e. H2O l -285.83 69.95 -237.13"));
+                }
+
+                let term = Term::parse(fields[0].as_bytes()).map_err(|e| invalid(&format!("has an invalid formula ({})", e)))?;
+
+                if term.coefficient != Ratio::one() || term.state.is_some() {
+                    return Err(invalid("must give the formula without a coefficient or state"));
+                }
+
+                let state = State::from_symbol(fields[1].as_bytes()).ok_or_else(|| invalid("has an unknown state, expected s, l, g or aq"))?;
+
+                let mut values = [0.0; 3];
+
+                for (value, field) in values.iter_mut().zip(fields[2..].iter()) {
+                    *value = field.parse::<f64>().ok().filter(|value| value.is_finite())
+                        .ok_or_else(|| invalid(&format!("has an invalid number '{}'", field)))?;
+                }
+
+                self.insert(term.canonical(), state, Formation {
+                    enthalpy: values[0],
+                    entropy: values[1],
+                    gibbs: values[2]
+                });
+            }
+
+            Ok(())
+        }
+
+        // The data for a species, with the state it is for. Without a state, the standard state of the species is used
+        pub fn find(&self, term: &Term) -> Option<(State, Formation)> {
+            let canonical = term.canonical();
+
+            self.entries.iter()
+                .find(|(found, state, _)| *found == canonical && term.state.map(|wanted| wanted == *state).unwrap_or(true))
+                .map(|(_, state, formation)| (*state, *formation))
+        }
+    }
+
+    // The formation data used for one species in a reaction
+    pub struct Species {
+        pub term: Term, // The species as in the equation, with the state the data is for
+        pub formation: Formation,
+        pub standard: bool // The equation gives no state, so the standard state was used
+    }
+
+    // The standard enthalpy, entropy and Gibbs energy changes of a reaction, per equation as written
+    pub struct Reaction {
+        pub enthalpy: f64, // In kJ/mol
+        pub entropy: f64, // In J/(mol K)
+        pub gibbs: f64, // In kJ/mol
+        pub species: Vec<Species>
+    }
+
+    // The molar gas constant, in J/(mol K)
+    pub const GAS_CONSTANT: f64 = 8.314462618;
+
+    // The temperatures at which a reaction is spontaneous, assuming its enthalpy and entropy changes do not depend on
+    // temperature
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Spontaneity {
+        Always,
+        Never,
+        Below(f64), // Spontaneous below this temperature in K, where the enthalpy and entropy changes are both negative
+        Above(f64) // Spontaneous above this temperature in K, where the enthalpy and entropy changes are both positive
+    }
+
+    impl Reaction {
+        // The Gibbs energy change in kJ/mol at a temperature in K, from dG = dH - TdS
+        pub fn gibbs_at(&self, temperature: f64) -> f64 {
+            self.enthalpy - temperature * self.entropy / 1000.0
+        }
+
+        // The natural log of the equilibrium constant at a temperature in K, from ln K = -dG/RT. Unlike the constant
+        // itself, this does not overflow for very favourable reactions
+        pub fn ln_constant_at(&self, temperature: f64) -> f64 {
+            -self.gibbs_at(temperature) * 1000.0 / (GAS_CONSTANT * temperature)
+        }
+
+        // The equilibrium constant at a temperature in K, from K = exp(-dG/RT)
+        pub fn constant_at(&self, temperature: f64) -> f64 {
+            self.ln_constant_at(temperature).exp()
+        }
+
+        // When the reaction is spontaneous. The temperature it changes at is where dG = 0, so T = dH/dS
+        pub fn spontaneity(&self) -> Spontaneity {
+            let crossover = self.enthalpy * 1000.0 / self.entropy;
+
+            match (self.enthalpy < 0.0, self.entropy > 0.0) {
+                (true, true) => Spontaneity::Always,
+                (false, false) => Spontaneity::Never,
+                (true, false) if self.entropy == 0.0 => Spontaneity::Always,
+                (true, false) => Spontaneity::Below(crossover),
+                (false, true) if self.enthalpy == 0.0 => Spontaneity::Always,
+                (false, true) => Spontaneity::Above(crossover)
+            }
+        }
+    }
+
+    // The changes for a balanced equation, products minus reactants, each multiplied by its coefficient. Electrons are
+    // left out, as by convention they have no formation energy. If any species has no data, they are all returned
+    pub fn reaction(equation: &Equation, table: &Table) -> Result<Reaction, Vec<Term>> {
+        let mut result = Reaction {
+            enthalpy: 0.0,
+            entropy: 0.0,
+            gibbs: 0.0,
+            species: Vec::with_capacity(equation.reactants.len() + equation.products.len())
+        };
+
+        let mut missing = Vec::new();
+
+        let sides = equation.reactants.iter().map(|term| (term, -1.0)).chain(equation.products.iter().map(|term| (term, 1.0)));
+
+        for (term, sign) in sides {
+            if term.formula.parts.is_empty() {
+                continue;
+            }
+
+            match table.find(term) {
+                Some((state, formation)) => {
+                    let scale = sign * *term.coefficient.numer() as f64 / *term.coefficient.denom() as f64;
+
+                    result.enthalpy += scale * formation.enthalpy;
+                    result.entropy += scale * formation.entropy;
+                    result.gibbs += scale * formation.gibbs;
+
+                    let mut species = term.clone();
+
+                    species.state = Some(state);
+
+                    result.species.push(Species {
+                        term: species,
+                        formation,
+                        standard: term.state.is_none()
+                    });
+                },
+                None => {
+                    let mut species = term.clone();
+
+                    species.coefficient = Ratio::one();
+
+                    missing.push(species);
+                }
+            }
+        }
+
+        if missing.is_empty() {
+            Ok(result)
+        }
+        else {
+            Err(missing)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        use std::env;
+
+        fn changes(equation: &str) -> Result<Reaction, Vec<Term>> {
+            reaction(&Equation::parse(equation.as_bytes()).unwrap(), &Table::embedded())
+        }
+
+        #[test]
+        fn finds_reaction_changes() {
+            let combustion = changes("CH4+2O2=CO2+2H2O").unwrap();
+
+            assert!((combustion.enthalpy + 890.36).abs() < 1e-9);
+            assert!((combustion.entropy + 242.87).abs() < 1e-9);
+            assert!((combustion.gibbs + 817.90).abs() < 1e-9);
+            assert!(combustion.species.iter().all(|species| species.standard));
+            assert_eq!(combustion.species[3].term.state, Some(State::Liquid));
+
+            let steam = changes("2H2+O2=2H2O(g)").unwrap();
+
+            assert!((steam.enthalpy + 483.64).abs() < 1e-9);
+            assert!(!steam.species[2].standard);
+        }
+
+        #[test]
+        fn lists_species_without_data() {
+            let missing = changes("Xe+2F2=XeF4").err().unwrap();
+
+            let written: Vec<String> = missing.iter().map(|term| term.to_string()).collect();
+
+            assert_eq!(written, vec!["Xe", "XeF4"]);
+        }
+
+        #[test]
+        fn loads_formation_data() {
+            let path = env::temp_dir().join(format!("chembal-formation-{}", std::process::id()));
+
+            fs::write(&path, "# Noble gas compounds\nXe g 0 169.68 0\nXeF4 s -251 146 -121\n").unwrap();
+
+            let mut table = Table::embedded();
+
+            let loaded = table.load(path.to_str().unwrap());
+
+            fs::write(&path, "XeF4 s -251 146\n").unwrap();
+
+            let invalid = table.load(path.to_str().unwrap());
+
+            fs::write(&path, "XeF4 s nan 146 -121\n").unwrap();
+
+            let not_finite = table.load(path.to_str().unwrap());
+
+            fs::remove_file(&path).unwrap();
+
+            loaded.unwrap();
+            assert!(invalid.is_err());
+            assert_eq!(not_finite.unwrap_err().message, format!("Line 1 of '{}' has an invalid number 'nan'", path.display()));
+
+            let xenon = reaction(&Equation::parse(b"Xe+2F2=XeF4").unwrap(), &table).unwrap();
+
+            assert!((xenon.enthalpy + 251.0).abs() < 1e-9);
+        }
+
+        #[test]
+        fn finds_when_a_reaction_is_spontaneous() {
+            let ammonia = changes("N2+3H2=2NH3").unwrap();
+
+            match ammonia.spontaneity() {
+                Spontaneity::Below(temperature) => assert!((temperature - 464.0).abs() < 0.1),
+                other => panic!("Expected spontaneous below a temperature, found {:?}", other)
+            }
+
+            assert!(ammonia.gibbs_at(300.0) < 0.0 && ammonia.gibbs_at(600.0) > 0.0);
+            assert!((ammonia.constant_at(298.15).ln() - ammonia.ln_constant_at(298.15)).abs() < 1e-9);
+
+            let reaction = |enthalpy: f64, entropy: f64| Reaction { enthalpy, entropy, gibbs: 0.0, species: Vec::new() };
+
+            assert_eq!(reaction(-10.0, 5.0).spontaneity(), Spontaneity::Always);
+            assert_eq!(reaction(10.0, -5.0).spontaneity(), Spontaneity::Never);
+            assert_eq!(reaction(10.0, 20.0).spontaneity(), Spontaneity::Above(500.0));
+        }
+    }
+}
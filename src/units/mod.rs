@@ -0,0 +1,465 @@
+pub mod quantity {
+
+    use crate::error::balance_error::{BalanceError, ErrorKind};
+
+    use num_rational::Ratio;
+
+    use num_traits::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub};
+    use num_traits::identities::{One, Zero};
+
+    use std::fmt;
+    use std::ops::{Add, Div, Mul, Sub};
+
+    // The powers of the base dimensions of a unit, i.e. mol/L is amount over length cubed
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct Dimension {
+        pub amount: i8,
+        pub mass: i8,
+        pub length: i8,
+        pub time: i8,
+        pub temperature: i8
+    }
+
+    impl Dimension {
+        pub const NONE: Dimension = Dimension { amount: 0, mass: 0, length: 0, time: 0, temperature: 0 };
+        pub const AMOUNT: Dimension = Dimension { amount: 1, ..Dimension::NONE };
+        pub const MASS: Dimension = Dimension { mass: 1, ..Dimension::NONE };
+        pub const VOLUME: Dimension = Dimension { length: 3, ..Dimension::NONE };
+        pub const PRESSURE: Dimension = Dimension { mass: 1, length: -1, time: -2, ..Dimension::NONE };
+        pub const TEMPERATURE: Dimension = Dimension { temperature: 1, ..Dimension::NONE };
+        pub const CONCENTRATION: Dimension = Dimension { amount: 1, length: -3, ..Dimension::NONE };
+        pub const MOLAR_MASS: Dimension = Dimension { amount: -1, mass: 1, ..Dimension::NONE };
+
+        //The dimension of a product of two units, or of a quotient if power is -1
+        fn combine(self, other: Dimension, power: i8) -> Dimension {
+            Dimension {
+                amount: self.amount + power * other.amount,
+                mass: self.mass + power * other.mass,
+                length: self.length + power * other.length,
+                time: self.time + power * other.time,
+                temperature: self.temperature + power * other.temperature
+            }
+        }
+
+        // What the dimension measures, i.e. "a volume", or its powers of the base dimensions if it has no name
+        pub fn name(&self) -> String {
+            let names = [
+                (Dimension::NONE, "a number"),
+                (Dimension::AMOUNT, "an amount"),
+                (Dimension::MASS, "a mass"),
+                (Dimension::VOLUME, "a volume"),
+                (Dimension::PRESSURE, "a pressure"),
+                (Dimension::TEMPERATURE, "a temperature"),
+                (Dimension::CONCENTRATION, "a concentration"),
+                (Dimension::MOLAR_MASS, "a molar mass")
+            ];
+
+            if let Some((_, name)) = names.iter().find(|(dimension, _)| dimension == self) {
+                return String::from(*name);
+            }
+
+            let powers = [("amount", self.amount), ("mass", self.mass), ("length", self.length), ("time", self.time), ("temperature", self.temperature)];
+
+            let written: Vec<String> = powers.iter().filter(|(_, power)| *power != 0).map(|(name, power)| format!("{}^{}", name, power)).collect();
+
+            format!("a quantity of {}", written.join(" "))
+        }
+    }
+
+    // A number, kept as an exact fraction while the input and every conversion of it is exact
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Value {
+        Exact(Ratio<i64>),
+        Approximate(f64)
+    }
+
+    impl Value {
+        // Parse a decimal number, optionally with an exponent, i.e. 12.5, -3 or 1.2e-3. Numbers too long to be held
+        // exactly are approximated, and those too large for a double (or NaN) are not numbers
+        pub fn parse(text: &str) -> Option<Value> {
+            let approximate = text.parse::<f64>().ok().filter(|approximate| approximate.is_finite())?;
+
+            let (mantissa, exponent) = match text.find(['e', 'E']) {
+                Some(index) => (&text[..index], text[index + 1..].parse::<i32>().ok()?),
+                None => (text, 0)
+            };
+
+            let (whole, fraction) = match mantissa.find('.') {
+                Some(index) => (&mantissa[..index], &mantissa[index + 1..]),
+                None => (mantissa, "")
+            };
+
+            let exact = || -> Option<Ratio<i64>> {
+                let digits = format!("{}{}", whole, fraction).parse::<i64>().ok()?;
+
+                let places = exponent - fraction.len() as i32;
+
+                let power = 10i64.checked_pow(places.unsigned_abs())?;
+
+                if places >= 0 {
+                    Some(Ratio::from_integer(digits.checked_mul(power)?))
+                }
+                else {
+                    Some(Ratio::new(digits, power))
+                }
+            };
+
+            Some(match exact() {
+                Some(exact) => Value::Exact(exact),
+                None => Value::Approximate(approximate)
+            })
+        }
+
+        pub fn to_f64(self) -> f64 {
+            match self {
+                Value::Exact(exact) => *exact.numer() as f64 / *exact.denom() as f64,
+                Value::Approximate(approximate) => approximate
+            }
+        }
+
+        //Combine two values exactly if both are exact and the result fits, otherwise approximately
+        fn combine(self, other: Value, exact: fn(&Ratio<i64>, &Ratio<i64>) -> Option<Ratio<i64>>, approximate: fn(f64, f64) -> f64) -> Value {
+            match (self, other) {
+                (Value::Exact(first), Value::Exact(second)) => match exact(&first, &second) {
+                    Some(result) => Value::Exact(result),
+                    None => Value::Approximate(approximate(self.to_f64(), other.to_f64()))
+                },
+                _ => Value::Approximate(approximate(self.to_f64(), other.to_f64()))
+            }
+        }
+
+    }
+
+    impl Add for Value {
+        type Output = Value;
+
+        fn add(self, other: Value) -> Value {
+            self.combine(other, |first, second| first.checked_add(second), |first, second| first + second)
+        }
+    }
+
+    impl Sub for Value {
+        type Output = Value;
+
+        fn sub(self, other: Value) -> Value {
+            self.combine(other, |first, second| first.checked_sub(second), |first, second| first - second)
+        }
+    }
+
+    impl Mul for Value {
+        type Output = Value;
+
+        fn mul(self, other: Value) -> Value {
+            self.combine(other, |first, second| first.checked_mul(second), |first, second| first * second)
+        }
+    }
+
+    impl Div for Value {
+        type Output = Value;
+
+        fn div(self, other: Value) -> Value {
+            self.combine(other, |first, second| if second.is_zero() { None } else { first.checked_div(second) }, |first, second| first / second)
+        }
+    }
+
+    impl fmt::Display for Value {
+        // An exact value with a finite decimal form is written in full, i.e. 0.25, anything else as a float
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let exact = match self {
+                Value::Exact(exact) => *exact,
+                Value::Approximate(approximate) => return write!(f, "{}", approximate)
+            };
+
+            //The number of decimal places is the larger power of two or five in the denominator
+            let mut denom = *exact.denom();
+            let mut twos = 0u32;
+            let mut fives = 0u32;
+
+            while denom % 2 == 0 {
+                denom /= 2;
+                twos += 1;
+            }
+
+            while denom % 5 == 0 {
+                denom /= 5;
+                fives += 1;
+            }
+
+            let places = twos.max(fives);
+
+            //A denominator with factors other than two and five has no finite decimal form
+            let scaled = match 10i64.checked_pow(places).and_then(|power| exact.checked_mul(&Ratio::from_integer(power))) {
+                Some(scaled) if denom == 1 => scaled.to_integer().checked_abs(),
+                _ => None
+            };
+
+            let (scaled, power) = match scaled {
+                Some(scaled) => (scaled, 10i64.pow(places)),
+                None => return write!(f, "{}", self.to_f64())
+            };
+
+            let sign = if *exact.numer() < 0 { "-" } else { "" };
+
+            if places == 0 {
+                write!(f, "{}{}", sign, scaled)
+            }
+            else {
+                let fraction = format!("{:0width$}", scaled % power, width = places as usize);
+
+                write!(f, "{}{}.{}", sign, scaled / power, fraction.trim_end_matches('0'))
+            }
+        }
+    }
+
+    //A numerator and denominator
+    type Fraction = (i64, i64);
+
+    //Each unit with its dimension, its size in SI base units (mol, kg, m, s and K) as a fraction, and for temperatures
+    //the offset added to give K as a fraction
+    const UNITS: [(&str, Dimension, Fraction, Fraction); 26] = [
+        ("mol", Dimension::AMOUNT, (1, 1), (0, 1)),
+        ("mmol", Dimension::AMOUNT, (1, 1000), (0, 1)),
+        ("umol", Dimension::AMOUNT, (1, 1000000), (0, 1)),
+        ("kmol", Dimension::AMOUNT, (1000, 1), (0, 1)),
+        ("kg", Dimension::MASS, (1, 1), (0, 1)),
+        ("g", Dimension::MASS, (1, 1000), (0, 1)),
+        ("mg", Dimension::MASS, (1, 1000000), (0, 1)),
+        ("ug", Dimension::MASS, (1, 1000000000), (0, 1)),
+        ("m3", Dimension::VOLUME, (1, 1), (0, 1)),
+        ("L", Dimension::VOLUME, (1, 1000), (0, 1)),
+        ("dm3", Dimension::VOLUME, (1, 1000), (0, 1)),
+        ("mL", Dimension::VOLUME, (1, 1000000), (0, 1)),
+        ("cm3", Dimension::VOLUME, (1, 1000000), (0, 1)),
+        ("uL", Dimension::VOLUME, (1, 1000000000), (0, 1)),
+        ("Pa", Dimension::PRESSURE, (1, 1), (0, 1)),
+        ("kPa", Dimension::PRESSURE, (1000, 1), (0, 1)),
+        ("bar", Dimension::PRESSURE, (100000, 1), (0, 1)),
+        ("atm", Dimension::PRESSURE, (101325, 1), (0, 1)),
+        ("mmHg", Dimension::PRESSURE, (101325, 760), (0, 1)),
+        ("Torr", Dimension::PRESSURE, (101325, 760), (0, 1)),
+        ("K", Dimension::TEMPERATURE, (1, 1), (0, 1)),
+        ("C", Dimension::TEMPERATURE, (1, 1), (27315, 100)),
+        ("degC", Dimension::TEMPERATURE, (1, 1), (27315, 100)),
+        ("\u{b0}C", Dimension::TEMPERATURE, (1, 1), (27315, 100)),
+        ("M", Dimension::CONCENTRATION, (1000, 1), (0, 1)),
+        ("mM", Dimension::CONCENTRATION, (1, 1), (0, 1))
+    ];
+
+    // A unit of measurement, either one from the table or a product and quotient of them, i.e. mol/L or g/mol
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Unit {
+        pub symbol: String,
+        pub dimension: Dimension,
+        scale: Value, // The size of the unit in SI base units
+        offset: Value // Added after scaling to give SI base units, only for temperatures
+    }
+
+    impl Unit {
+        //A single unit from the table
+        fn simple(symbol: &str) -> Result<Unit, BalanceError> {
+            let known: Vec<&str> = UNITS.iter().map(|(symbol, _, _, _)| *symbol).filter(|symbol| symbol.is_ascii()).collect();
+
+            UNITS.iter()
+                .find(|(name, _, _, _)| *name == symbol)
+                .map(|(name, dimension, scale, offset)| Unit {
+                    symbol: String::from(*name),
+                    dimension: *dimension,
+                    scale: Value::Exact(Ratio::new(scale.0, scale.1)),
+                    offset: Value::Exact(Ratio::new(offset.0, offset.1))
+                })
+                .ok_or_else(|| BalanceError::new(ErrorKind::Input, format!("Unknown unit '{}', expected one of {}", symbol, known.join(", "))))
+        }
+
+        // Parse a unit, i.e. mL, or a product and quotient of units, i.e. mol/L or g/mol. Units of temperature with an
+        // offset, such as C, can only be used on their own
+        pub fn parse(symbol: &str) -> Result<Unit, BalanceError> {
+            let mut result = Unit {
+                symbol: String::from(symbol),
+                dimension: Dimension::NONE,
+                scale: Value::Exact(Ratio::one()),
+                offset: Value::Exact(Ratio::zero())
+            };
+
+            let mut parts = 0;
+
+            for (index, quotient) in symbol.split('/').enumerate() {
+                for name in quotient.split('*') {
+                    let unit = Unit::simple(name)?;
+
+                    let power = if index == 0 { 1 } else { -1 };
+
+                    result.dimension = result.dimension.combine(unit.dimension, power);
+                    result.scale = if index == 0 { result.scale * unit.scale } else { result.scale / unit.scale };
+                    if unit.offset != Value::Exact(Ratio::zero()) {
+                        result.offset = unit.offset;
+                    }
+
+                    parts += 1;
+                }
+            }
+
+            if parts > 1 && result.offset != Value::Exact(Ratio::zero()) {
+                return Err(BalanceError::new(ErrorKind::Input, format!("'{}' cannot combine a temperature in C with other units, use K instead", symbol)));
+            }
+
+            Ok(result)
+        }
+    }
+
+    impl fmt::Display for Unit {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.symbol)
+        }
+    }
+
+    // A value with a unit, i.e. 12.5 g or 0.1 mol/L
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Quantity {
+        pub value: Value,
+        pub unit: Unit
+    }
+
+    impl Quantity {
+        // Parse a number and unit at the start of text, i.e. "0.1 M, 25 mL HCl", returning the rest of the text after
+        // any whitespace or comma. A space between the number and unit is optional
+        pub fn parse_prefix(text: &str) -> Result<(Quantity, &str), BalanceError> {
+            let text = text.trim();
+
+            let number_end = text.char_indices()
+                .find(|(index, ch)| {
+                    let exponent = (*ch == 'e' || *ch == 'E') && text[index + 1..].starts_with(|next: char| next.is_ascii_digit() || next == '-');
+                    let sign = *ch == '-' && (*index == 0 || text[..*index].ends_with(['e', 'E']));
+
+                    !(ch.is_ascii_digit() || *ch == '.' || exponent || sign)
+                })
+                .map(|(index, _)| index)
+                .unwrap_or(text.len());
+
+            let value = Value::parse(&text[..number_end])
+                .ok_or_else(|| BalanceError::new(ErrorKind::Input, format!("'{}' must start with a number, i.e. 12.5 g", text)))?;
+
+            let rest = text[number_end..].trim_start();
+
+            let unit_end = rest.find(|ch: char| ch.is_whitespace() || ch == ',').unwrap_or(rest.len());
+
+            if unit_end == 0 {
+                return Err(BalanceError::new(ErrorKind::Input, format!("'{}' must give a unit after the number, i.e. 12.5 g", text)));
+            }
+
+            let unit = Unit::parse(&rest[..unit_end])?;
+
+            Ok((Quantity { value, unit }, rest[unit_end..].trim_start_matches(|ch: char| ch.is_whitespace() || ch == ',')))
+        }
+
+        // Parse a number and unit, i.e. "250 mL" or "0.5 mol/L"
+        pub fn parse(text: &str) -> Result<Quantity, BalanceError> {
+            let (quantity, rest) = Quantity::parse_prefix(text)?;
+
+            if !rest.is_empty() {
+                return Err(BalanceError::new(ErrorKind::Input, format!("'{}' must be a number and a unit only, i.e. 250 mL", text.trim())));
+            }
+
+            Ok(quantity)
+        }
+
+        // The value in SI base units (mol, kg, m3, Pa, K and mol/m3)
+        pub fn base(&self) -> Value {
+            self.value * self.unit.scale + self.unit.offset
+        }
+
+        // The value in SI base units as a float, after checking the quantity measures the right thing
+        pub fn expect(&self, dimension: Dimension) -> Result<f64, BalanceError> {
+            if self.unit.dimension != dimension {
+                return Err(BalanceError::new(ErrorKind::Input, format!("'{}' is {}, but {} is needed", self, self.unit.dimension.name(), dimension.name())));
+            }
+
+            Ok(self.base().to_f64())
+        }
+
+        // The same quantity in another unit, which must measure the same thing
+        pub fn convert(&self, unit: &Unit) -> Result<Quantity, BalanceError> {
+            if self.unit.dimension != unit.dimension {
+                return Err(BalanceError::new(ErrorKind::Input, format!("Cannot convert {} to {}, {} is {} and {} is {}", self.unit, unit,
+                                                                       self.unit, self.unit.dimension.name(), unit, unit.dimension.name())));
+            }
+
+            Ok(Quantity {
+                value: (self.base() - unit.offset) / unit.scale,
+                unit: unit.clone()
+            })
+        }
+
+        // A quantity from a value in SI base units
+        pub fn from_base(value: Value, unit: &Unit) -> Quantity {
+            Quantity {
+                value: (value - unit.offset) / unit.scale,
+                unit: unit.clone()
+            }
+        }
+    }
+
+    impl fmt::Display for Quantity {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{} {}", self.value, self.unit)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn convert(text: &str, unit: &str) -> String {
+            Quantity::parse(text).unwrap().convert(&Unit::parse(unit).unwrap()).unwrap().to_string()
+        }
+
+        #[test]
+        fn parses_values_exactly() {
+            assert_eq!(Value::parse("12.5"), Some(Value::Exact(Ratio::new(25, 2))));
+            assert_eq!(Value::parse("-3"), Some(Value::Exact(Ratio::from_integer(-3))));
+            assert_eq!(Value::parse("1.2e-3"), Some(Value::Exact(Ratio::new(3, 2500))));
+            assert_eq!(Value::parse("1e30"), Some(Value::Approximate(1e30)));
+            assert_eq!(Value::parse("g"), None);
+            assert_eq!(Value::parse("nan"), None);
+            assert_eq!(Value::parse("1e400"), None);
+        }
+
+        #[test]
+        fn writes_values() {
+            assert_eq!(Value::Exact(Ratio::new(1, 4)).to_string(), "0.25");
+            assert_eq!(Value::Exact(Ratio::new(-5, 2)).to_string(), "-2.5");
+            assert_eq!(Value::Exact(Ratio::from_integer(760)).to_string(), "760");
+            assert_eq!(Value::Exact(Ratio::new(1, 3)).to_string(), (1.0f64 / 3.0).to_string());
+        }
+
+        #[test]
+        fn converts_between_units() {
+            assert_eq!(convert("1 atm", "mmHg"), "760 mmHg");
+            assert_eq!(convert("1 atm", "kPa"), "101.325 kPa");
+            assert_eq!(convert("25 C", "K"), "298.15 K");
+            assert_eq!(convert("0 K", "degC"), "-273.15 degC");
+            assert_eq!(convert("250 mL", "L"), "0.25 L");
+            assert_eq!(convert("2.5 mg", "g"), "0.0025 g");
+            assert_eq!(convert("0.1 M", "mM"), "100 mM");
+            assert_eq!(convert("1 mol/L", "M"), "1 M");
+        }
+
+        #[test]
+        fn checks_dimensions() {
+            assert_eq!(Unit::parse("g/mol").unwrap().dimension, Dimension::MOLAR_MASS);
+            assert_eq!(Unit::parse("mol/L").unwrap().dimension, Dimension::CONCENTRATION);
+            assert!(Quantity::parse("1 atm").unwrap().convert(&Unit::parse("K").unwrap()).is_err());
+            assert!(Quantity::parse("25 C").unwrap().expect(Dimension::PRESSURE).is_err());
+            assert!((Quantity::parse("25 C").unwrap().expect(Dimension::TEMPERATURE).unwrap() - 298.15).abs() < 1e-9);
+        }
+
+        #[test]
+        fn rejects_bad_quantities() {
+            assert!(Unit::parse("furlong").is_err());
+            assert!(Unit::parse("C/mol").is_err());
+            assert!(Quantity::parse("12.5").is_err());
+            assert!(Quantity::parse("g").is_err());
+            assert!(Quantity::parse("250 mL HCl").is_err());
+            assert_eq!(Quantity::parse_prefix("0.1 M, 25 mL HCl").unwrap().1, "25 mL HCl");
+        }
+    }
+}